@@ -1,7 +1,9 @@
 use criterion::{black_box, measurement::Measurement, BenchmarkGroup, Criterion};
 
 use jackpot::lotteryscheme::{
+    ipa_lottery::IpaLottery,
     jack::{get_jack_parameters, Jack},
+    merkle_lottery::MerkleLottery,
     LotteryScheme,
 };
 
@@ -61,7 +63,103 @@ fn bench<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usi
     });
 }
 
-/// benchmark aggregation of Jack
+/// benchmark aggregation of the IPA-based lottery
+/// for 2^log_num_tickets many tickets
+fn bench_ipa<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    let num_lotteries = (1 << 4) - 2;
+    let k = 512;
+    let num_tickets = 1 << log_num_tickets;
+    let par = <IpaLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the IPA-based lottery
+    let label = format!("aggregate_ipa_{}", log_num_tickets);
+    c.bench_function(&label, |b| {
+        // Preparation 1: Generate L users
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        let mut pids = Vec::new();
+        for j in 0..num_tickets {
+            let (pk, sk) = <IpaLottery as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+            pids.push(j as u32);
+        }
+
+        // Preparation 2: Do a lottery and generate all of their tickets
+        let i = 0; // say we do the first lottery
+        let lseed = <IpaLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut tickets = Vec::new();
+        for j in 0..num_tickets {
+            let ticket = <IpaLottery as LotteryScheme>::get_ticket(
+                &par, i, &lseed, pids[j], &sks[j], &pks[j],
+            )
+            .unwrap();
+            tickets.push(ticket);
+        }
+        // Actual Benchmark: Measure running time of aggregation
+        b.iter(|| {
+            <IpaLottery as LotteryScheme>::aggregate(
+                black_box(&par),
+                black_box(i),
+                black_box(&lseed),
+                black_box(&pids),
+                black_box(&pks),
+                black_box(&tickets),
+            );
+        });
+    });
+}
+
+/// benchmark aggregation of the Merkle-based lottery
+/// for 2^log_num_tickets many tickets
+fn bench_merkle<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    let num_lotteries = (1 << 4) - 2;
+    let k = 512;
+    let num_tickets = 1 << log_num_tickets;
+    let par = <MerkleLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the Merkle-based lottery
+    let label = format!("aggregate_merkle_{}", log_num_tickets);
+    c.bench_function(&label, |b| {
+        // Preparation 1: Generate L users
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        let mut pids = Vec::new();
+        for j in 0..num_tickets {
+            let (pk, sk) = <MerkleLottery as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+            pids.push(j as u32);
+        }
+
+        // Preparation 2: Do a lottery and generate all of their tickets
+        let i = 0; // say we do the first lottery
+        let lseed = <MerkleLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut tickets = Vec::new();
+        for j in 0..num_tickets {
+            let ticket = <MerkleLottery as LotteryScheme>::get_ticket(
+                &par, i, &lseed, pids[j], &sks[j], &pks[j],
+            )
+            .unwrap();
+            tickets.push(ticket);
+        }
+        // Actual Benchmark: Measure running time of aggregation
+        b.iter(|| {
+            <MerkleLottery as LotteryScheme>::aggregate(
+                black_box(&par),
+                black_box(i),
+                black_box(&lseed),
+                black_box(&pids),
+                black_box(&pks),
+                black_box(&tickets),
+            );
+        });
+    });
+}
+
+/// benchmark aggregation of Jack, the IPA-based and the Merkle-based lottery
 pub fn aggregate_bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("aggregate");
     bench(&mut group, 0);
@@ -69,5 +167,11 @@ pub fn aggregate_bench(c: &mut Criterion) {
     bench(&mut group, 8);
     bench(&mut group, 10);
     bench(&mut group, 11);
+    bench_ipa(&mut group, 0);
+    bench_ipa(&mut group, 4);
+    bench_ipa(&mut group, 8);
+    bench_merkle(&mut group, 0);
+    bench_merkle(&mut group, 4);
+    bench_merkle(&mut group, 8);
     group.finish();
 }
@@ -1,7 +1,9 @@
 use criterion::{black_box, measurement::Measurement, BenchmarkGroup, Criterion};
 
 use jackpot::lotteryscheme::{
+    ipa_lottery::IpaLottery,
     jack::{get_jack_parameters, Jack},
+    merkle_lottery::MerkleLottery,
     LotteryScheme,
 };
 
@@ -32,11 +34,67 @@ fn bench<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, ld: usize) {
     });
 }
 
+/// benchmark get_ticket of the IPA-based lottery for 2^{ld}-2 lotteries
+fn bench_ipa<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, ld: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    let num_lotteries = (1 << ld) - 2;
+    let k = 512;
+    let par = <IpaLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the IPA-based lottery
+    let label = format!("get_ticket_ipa_{}", ld);
+    c.bench_function(&label, |b| {
+        let (pk, sk) = <IpaLottery as LotteryScheme>::gen(&mut rng, &par);
+        let pid = 132;
+        let i = 2;
+        let lseed = <IpaLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        b.iter(|| {
+            <IpaLottery as LotteryScheme>::get_ticket(
+                &par,
+                black_box(i),
+                black_box(&lseed),
+                black_box(pid),
+                black_box(&sk),
+                black_box(&pk),
+            )
+        });
+    });
+}
+
+/// benchmark get_ticket of the Merkle-based lottery for 2^{ld}-2 lotteries
+fn bench_merkle<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, ld: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    let num_lotteries = (1 << ld) - 2;
+    let k = 512;
+    let par = <MerkleLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the Merkle-based lottery
+    let label = format!("get_ticket_merkle_{}", ld);
+    c.bench_function(&label, |b| {
+        let (pk, sk) = <MerkleLottery as LotteryScheme>::gen(&mut rng, &par);
+        let pid = 132;
+        let i = 2;
+        let lseed = <MerkleLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        b.iter(|| {
+            <MerkleLottery as LotteryScheme>::get_ticket(
+                &par,
+                black_box(i),
+                black_box(&lseed),
+                black_box(pid),
+                black_box(&sk),
+                black_box(&pk),
+            )
+        });
+    });
+}
+
 /// benchmark get_ticket of jacks
 pub fn get_ticket_bench(c: &mut Criterion) {
     let mut group = c.benchmark_group("get_ticket");
     bench(&mut group, 10);
     bench(&mut group, 15);
     bench(&mut group, 20);
+    bench_ipa(&mut group, 10);
+    bench_merkle(&mut group, 10);
     group.finish();
 }
@@ -2,7 +2,10 @@ use criterion::{black_box, measurement::Measurement, BenchmarkGroup, Criterion};
 
 use jackpot::lotteryscheme::{
     bls_hash::BLSHash,
+    ipa_lottery::IpaLottery,
     jack::{get_jack_parameters, Jack},
+    merkle_lottery::MerkleLottery,
+    vcbased::VerifierContext,
     LotteryScheme,
 };
 
@@ -67,6 +70,193 @@ fn bench_jack<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets
     });
 }
 
+/// benchmark verification of Jack for 2^log_num_tickets many tickets
+/// using a verifier context, in which all keys have been registered
+fn bench_jack_context<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    // number of lotteries should have no impact
+    // on the running time of verify. To make sure
+    // the (setup of the) benchmark does not run forever,
+    // we choose num_lotteries to be small
+    let num_lotteries = (1 << 4) - 2;
+    let k = 512;
+    let num_tickets = 1 << log_num_tickets;
+    let par = get_jack_parameters(&mut rng, num_lotteries, k);
+
+    // benchmark jack
+    let label = format!("verify_jack_context_{}", log_num_tickets);
+    c.bench_function(&label, |b| {
+        // structure of the benchmark:
+        // we need num_tickets many users and a lottery seed
+        // then we aggregate their tickets, register the users
+        // in a context and verify the agg ticket using the context
+
+        // Preparation 1: Generate L users
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        let mut pids = Vec::new();
+        for j in 0..num_tickets {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+            pids.push(j as u32);
+        }
+
+        // Preparation 2: Do a lottery and generate all of their tickets
+        let i = 0; // say we do the first lottery
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut tickets = Vec::new();
+        for j in 0..num_tickets {
+            let ticket =
+                <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pids[j], &sks[j], &pks[j])
+                    .unwrap();
+            tickets.push(ticket);
+        }
+
+        // Preparation 3: Aggregate the tickets and register the users
+        let ticket =
+            <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets).unwrap();
+        let mut ctx = VerifierContext::new();
+        for (pid, pk) in pids.iter().zip(pks) {
+            assert!(ctx.register(&par, *pid, pk));
+        }
+
+        // Actual Benchmark: Measure running time of verification
+        b.iter(|| {
+            Jack::verify_with_context(
+                black_box(&par),
+                black_box(&ctx),
+                black_box(i),
+                black_box(&lseed),
+                black_box(&pids),
+                black_box(&ticket),
+            )
+        });
+    });
+}
+
+/// benchmark verification of the IPA-based lottery
+/// for 2^log_num_tickets many tickets
+fn bench_ipa<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    // number of lotteries should have only logarithmic
+    // impact on the running time of verify. We use the
+    // same number of lotteries as for Jack to compare them
+    let num_lotteries = (1 << 4) - 2;
+    let k = 512;
+    let num_tickets = 1 << log_num_tickets;
+    let par = <IpaLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the IPA-based lottery
+    let label = format!("verify_ipa_{}", log_num_tickets);
+    c.bench_function(&label, |b| {
+        // structure of the benchmark:
+        // we need num_tickets many users and a lottery seed
+        // then we aggregate their tickets and verify the agg ticket
+
+        // Preparation 1: Generate L users
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        let mut pids = Vec::new();
+        for j in 0..num_tickets {
+            let (pk, sk) = <IpaLottery as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+            pids.push(j as u32);
+        }
+
+        // Preparation 2: Do a lottery and generate all of their tickets
+        let i = 0; // say we do the first lottery
+        let lseed = <IpaLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut tickets = Vec::new();
+        for j in 0..num_tickets {
+            let ticket = <IpaLottery as LotteryScheme>::get_ticket(
+                &par, i, &lseed, pids[j], &sks[j], &pks[j],
+            )
+            .unwrap();
+            tickets.push(ticket);
+        }
+
+        // Preparation 3: Aggregate the tickets
+        let ticket =
+            <IpaLottery as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets)
+                .unwrap();
+
+        // Actual Benchmark: Measure running time of verification
+        b.iter(|| {
+            <IpaLottery as LotteryScheme>::verify(
+                black_box(&par),
+                black_box(i),
+                black_box(&lseed),
+                black_box(&pids),
+                black_box(&pks),
+                black_box(&ticket),
+            )
+        });
+    });
+}
+
+/// benchmark verification of the Merkle-based lottery
+/// for 2^log_num_tickets many tickets
+fn bench_merkle<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
+    let mut rng = ark_std::rand::thread_rng();
+    // number of lotteries should have only logarithmic
+    // impact on the running time of verify. We use the
+    // same number of lotteries as for Jack to compare them
+    let num_lotteries = (1 << 4) - 2;
+    let k = 512;
+    let num_tickets = 1 << log_num_tickets;
+    let par = <MerkleLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+
+    // benchmark the Merkle-based lottery
+    let label = format!("verify_merkle_{}", log_num_tickets);
+    c.bench_function(&label, |b| {
+        // structure of the benchmark:
+        // we need num_tickets many users and a lottery seed
+        // then we aggregate their tickets and verify the agg ticket
+
+        // Preparation 1: Generate L users
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        let mut pids = Vec::new();
+        for j in 0..num_tickets {
+            let (pk, sk) = <MerkleLottery as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+            pids.push(j as u32);
+        }
+
+        // Preparation 2: Do a lottery and generate all of their tickets
+        let i = 0; // say we do the first lottery
+        let lseed = <MerkleLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut tickets = Vec::new();
+        for j in 0..num_tickets {
+            let ticket = <MerkleLottery as LotteryScheme>::get_ticket(
+                &par, i, &lseed, pids[j], &sks[j], &pks[j],
+            )
+            .unwrap();
+            tickets.push(ticket);
+        }
+
+        // Preparation 3: Aggregate the tickets
+        let ticket =
+            <MerkleLottery as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets)
+                .unwrap();
+
+        // Actual Benchmark: Measure running time of verification
+        b.iter(|| {
+            <MerkleLottery as LotteryScheme>::verify(
+                black_box(&par),
+                black_box(i),
+                black_box(&lseed),
+                black_box(&pids),
+                black_box(&pks),
+                black_box(&ticket),
+            )
+        });
+    });
+}
+
 /// benchmark verification of BLS+Hash for 2^log_num_tickets many tickets
 fn bench_blshash<'a, M: Measurement>(c: &mut BenchmarkGroup<'a, M>, log_num_tickets: usize) {
     let mut rng = ark_std::rand::thread_rng();
@@ -134,6 +324,17 @@ pub fn verify_bench(c: &mut Criterion) {
     bench_jack(&mut group, 8);
     bench_jack(&mut group, 10);
     bench_jack(&mut group, 11);
+    bench_jack_context(&mut group, 0);
+    bench_jack_context(&mut group, 4);
+    bench_jack_context(&mut group, 8);
+    bench_jack_context(&mut group, 10);
+    bench_jack_context(&mut group, 11);
+    bench_ipa(&mut group, 0);
+    bench_ipa(&mut group, 4);
+    bench_ipa(&mut group, 8);
+    bench_merkle(&mut group, 0);
+    bench_merkle(&mut group, 4);
+    bench_merkle(&mut group, 8);
     bench_blshash(&mut group, 0);
     bench_blshash(&mut group, 4);
     bench_blshash(&mut group, 8);
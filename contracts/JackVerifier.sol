@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MIT
+// This file is generated by jackpot::lotteryscheme::jack_evm::solidity_verifier
+pragma solidity ^0.8.19;
+
+/// Verifier for aggregated tickets of the lottery scheme JackEvm
+contract JackVerifier {
+    uint256 constant P = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    bytes constant CHALL_DST = "Chall//";
+    bytes constant AGG_DST = "KZG-AGG-EVM//";
+
+    uint256[2] public g1;
+    uint256[2] public h;
+    uint256[4] public g2;
+    uint256[4][] public d;
+    uint32 public logK;
+
+    constructor(
+        uint256[2] memory g1_,
+        uint256[2] memory h_,
+        uint256[4] memory g2_,
+        uint256[4][] memory d_,
+        uint32 logK_
+    ) {
+        g1 = g1_;
+        h = h_;
+        g2 = g2_;
+        for (uint256 t = 0; t < d_.length; t++) {
+            d.push(d_[t]);
+        }
+        logK = logK_;
+    }
+
+    /// challenge x = Keccak256(DST,pk,pid,i,lseed) mod 2^logK
+    function challenge(bytes calldata pk, uint32 pid, uint32 i, bytes32 lseed)
+        public
+        view
+        returns (uint256)
+    {
+        uint256 digest = uint256(keccak256(abi.encodePacked(CHALL_DST, pk, pid, i, lseed)));
+        return digest & ((uint256(1) << logK) - 1);
+    }
+
+    function ecAdd(uint256[2] memory a, uint256[2] memory b)
+        internal
+        view
+        returns (uint256[2] memory c)
+    {
+        uint256[4] memory input = [a[0], a[1], b[0], b[1]];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x06, input, 0x80, c, 0x40)
+        }
+        require(ok, "ecAdd failed");
+    }
+
+    function ecMul(uint256[2] memory a, uint256 s)
+        internal
+        view
+        returns (uint256[2] memory c)
+    {
+        uint256[3] memory input = [a[0], a[1], s];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x07, input, 0x60, c, 0x40)
+        }
+        require(ok, "ecMul failed");
+    }
+
+    /// verifies the aggregated ticket for users pids[j] with encoded
+    /// public keys pks[j] (192 bytes each) in the ith lottery
+    /// with seed lseed. The ticket is encoded as hat_y, v (96 bytes).
+    /// Identifiers must be strictly increasing, so no user is counted twice
+    function verify(
+        uint32 i,
+        bytes32 lseed,
+        uint32[] calldata pids,
+        bytes[] calldata pks,
+        bytes calldata ticket
+    ) external view returns (bool) {
+        uint256 l = pids.length;
+        if (l == 0 || l != pks.length || i >= d.length || ticket.length != 96) {
+            return false;
+        }
+
+        // compute challenges and the aggregation challenge chi
+        uint256[] memory xs = new uint256[](l);
+        bytes memory aggInput = abi.encodePacked(AGG_DST, i);
+        for (uint256 j = 0; j < l; j++) {
+            if (pks[j].length != 192 || (j > 0 && pids[j] <= pids[j - 1])) {
+                return false;
+            }
+            xs[j] = challenge(pks[j], pids[j], i, lseed);
+            aggInput = abi.encodePacked(aggInput, xs[j], pks[j][0:64]);
+        }
+        uint256 chi = uint256(keccak256(aggInput)) % R;
+
+        // com = sum_j chi^j com[j], mi = sum_j chi^j x[j]
+        uint256[2] memory com;
+        uint256 mi = 0;
+        uint256 c = 1;
+        for (uint256 j = 0; j < l; j++) {
+            uint256[2] memory comJ = [uint256(bytes32(pks[j][0:32])), uint256(bytes32(pks[j][32:64]))];
+            com = ecAdd(com, ecMul(comJ, c));
+            mi = addmod(mi, mulmod(xs[j], c, R), R);
+            c = mulmod(c, chi, R);
+        }
+
+        uint256 hatY = uint256(bytes32(ticket[0:32]));
+        if (hatY >= R) {
+            return false;
+        }
+        uint256[2] memory v = [uint256(bytes32(ticket[32:64])), uint256(bytes32(ticket[64:96]))];
+
+        // check e(-(com*g1^{-mi}*h^{-hatY}),g2) * e(v,d[i]) == 1
+        if (com[1] != 0) {
+            com[1] = P - com[1];
+        }
+        uint256[2] memory negLhs = ecAdd(ecAdd(ecMul(g1, mi), ecMul(h, hatY)), com);
+        uint256[4] memory di = d[i];
+        uint256[12] memory input = [
+            negLhs[0], negLhs[1], g2[0], g2[1], g2[2], g2[3],
+            v[0], v[1], di[0], di[1], di[2], di[3]
+        ];
+        uint256[1] memory out;
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x08, input, 0x180, out, 0x20)
+        }
+        return ok && out[0] == 1;
+    }
+}
@@ -0,0 +1,55 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ark_bls12_381::G1Affine;
+use ark_serialize::CanonicalDeserialize;
+use jackpot::lotteryscheme::bls_hash::BLSHash;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::vcbased::Ticket;
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot::vectorcommitment::kzg::Opening;
+use jackpot_fuzz::{bls, jack, NUM_USERS};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    i: u32,
+    lseed: [u8; 32],
+    pids: Vec<u32>,
+    first_user: u8,
+    num_users: u8,
+    tickets: Vec<Vec<u8>>,
+}
+
+// aggregate must not panic for untrusted tickets, which
+// may not match the number of users or the lottery
+fuzz_target!(|input: Input| {
+    let start = input.first_user as usize % NUM_USERS;
+    let end = (start + input.num_users as usize).min(NUM_USERS);
+
+    let f = jack();
+    let tickets: Vec<_> = input
+        .tickets
+        .iter()
+        .filter_map(|bytes| Opening::deserialize_compressed(bytes.as_slice()).ok())
+        .map(|opening| Ticket { opening })
+        .collect();
+    let pks = &f.pks[start..end];
+    if let Some(ticket) = Jack::aggregate(&f.par, input.i, &input.lseed, &input.pids, pks, &tickets)
+    {
+        let _ = Jack::verify(&f.par, input.i, &input.lseed, &input.pids, pks, &ticket);
+    }
+
+    let f = bls();
+    let tickets: Vec<_> = input
+        .tickets
+        .iter()
+        .filter_map(|bytes| Vec::<G1Affine>::deserialize_compressed(bytes.as_slice()).ok())
+        .collect();
+    let pks = &f.pks[start..end];
+    if let Some(ticket) =
+        <BLSHash>::aggregate(&f.par, input.i, &input.lseed, &input.pids, pks, &tickets)
+    {
+        let _ = <BLSHash>::verify(&f.par, input.i, &input.lseed, &input.pids, pks, &ticket);
+    }
+});
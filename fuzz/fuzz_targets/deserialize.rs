@@ -0,0 +1,61 @@
+#![no_main]
+
+use ark_bls12_381::{Bls12_381, G1Projective};
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_serialize::{CanonicalDeserialize, Compress, Validate};
+use jackpot::keystore::Keystore;
+use jackpot::lotteryscheme::bls_hash::BLSSecretKey;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::jack_evm::{decode_field, decode_g1, decode_g2};
+use jackpot::lotteryscheme::registry::{WinnerBitmap, WinnerSet};
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot::vectorcommitment::kzg::{Commitment, CrossOpening, MergeableOpening, Opening};
+use jackpot::vectorcommitment::{ipa, merkle};
+use jackpot_fuzz::{jack, registry, JackRegistry, JackWinningProof};
+use libfuzzer_sys::fuzz_target;
+
+type SecretKey = <Jack as LotteryScheme>::SecretKey;
+
+/// deserialize x in all modes, which must not panic
+fn deserialize<T: CanonicalDeserialize>(data: &[u8]) {
+    for compress in [Compress::Yes, Compress::No] {
+        for validate in [Validate::Yes, Validate::No] {
+            let _ = T::deserialize_with_mode(data, compress, validate);
+        }
+    }
+}
+
+// all deserializers must reject malformed input without panicking
+fuzz_target!(|data: &[u8]| {
+    deserialize::<Commitment<Bls12_381>>(data);
+    deserialize::<Opening<Bls12_381>>(data);
+    deserialize::<CrossOpening<Bls12_381>>(data);
+    deserialize::<MergeableOpening<Bls12_381>>(data);
+    deserialize::<ipa::Commitment<G1Projective>>(data);
+    deserialize::<ipa::Opening<G1Projective>>(data);
+    deserialize::<merkle::Commitment>(data);
+    deserialize::<merkle::Opening>(data);
+    deserialize::<WinnerSet>(data);
+    deserialize::<WinnerBitmap>(data);
+    deserialize::<SecretKey>(data);
+    deserialize::<BLSSecretKey>(data);
+    let _ = JackRegistry::deserialize(data);
+
+    // decoded proofs may contain huge indices, which must be
+    // rejected before anything of their size is allocated
+    if let Ok(proof) = JackWinningProof::deserialize(data) {
+        let fixture = jack();
+        let _ = Jack::verify_winners(&fixture.par, registry(), 0, &[0u8; 32], &proof);
+    }
+
+    let _ = decode_field::<Fr>(data);
+    let _: Option<G1Affine> = decode_g1(data);
+    let _: Option<G2Affine> = decode_g2(data);
+
+    // decrypting runs scrypt, so we only do it for cheap parameters
+    if let Some(keystore) = std::str::from_utf8(data).ok().and_then(Keystore::from_json) {
+        if keystore.crypto.kdf.params.n <= 1 << 4 {
+            let _ = keystore.decrypt(&keystore.kind, "password");
+        }
+    }
+});
@@ -0,0 +1,60 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use jackpot::lotteryscheme::bls_hash::BLSHash;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot_fuzz::{bls, jack, NUM_USERS};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    i: u32,
+    pid: u32,
+    lseed: [u8; 32],
+    user: u8,
+}
+
+// participate and get_ticket must not panic for any lottery,
+// and winners must always be able to get a ticket
+fuzz_target!(|input: Input| {
+    let j = input.user as usize % NUM_USERS;
+
+    let f = jack();
+    let won = Jack::participate(
+        &f.par,
+        input.i,
+        &input.lseed,
+        input.pid,
+        &f.sks[j],
+        &f.pks[j],
+    );
+    let ticket = Jack::get_ticket(
+        &f.par,
+        input.i,
+        &input.lseed,
+        input.pid,
+        &f.sks[j],
+        &f.pks[j],
+    );
+    assert!(!won || ticket.is_some());
+
+    let f = bls();
+    let won = <BLSHash>::participate(
+        &f.par,
+        input.i,
+        &input.lseed,
+        input.pid,
+        &f.sks[j],
+        &f.pks[j],
+    );
+    let ticket = <BLSHash>::get_ticket(
+        &f.par,
+        input.i,
+        &input.lseed,
+        input.pid,
+        &f.sks[j],
+        &f.pks[j],
+    );
+    assert!(!won || ticket.is_some());
+});
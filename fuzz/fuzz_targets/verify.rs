@@ -0,0 +1,65 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ark_bls12_381::G1Affine;
+use ark_serialize::CanonicalDeserialize;
+use jackpot::lotteryscheme::bls_hash::BLSHash;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::jack_evm::verify_encoded;
+use jackpot::lotteryscheme::vcbased::Ticket;
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot::vectorcommitment::kzg::Opening;
+use jackpot_fuzz::{bls, evm, jack, NUM_USERS};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    i: u32,
+    lseed: [u8; 32],
+    pids: Vec<u32>,
+    first_user: u8,
+    num_users: u8,
+    ticket: Vec<u8>,
+    encoded_pks: Vec<Vec<u8>>,
+}
+
+// verify must reject untrusted tickets without panicking.
+// As the fixtures only win with probability 1/2 per lottery,
+// the fuzzer can not forge tickets, so verification must fail
+fuzz_target!(|input: Input| {
+    let start = input.first_user as usize % NUM_USERS;
+    let end = (start + input.num_users as usize).min(NUM_USERS);
+
+    let f = jack();
+    if let Ok(opening) = Opening::deserialize_compressed(input.ticket.as_slice()) {
+        let ticket = Ticket { opening };
+        let pks = &f.pks[start..end];
+        assert!(!Jack::verify(
+            &f.par,
+            input.i,
+            &input.lseed,
+            &input.pids,
+            pks,
+            &ticket
+        ));
+    }
+
+    let f = bls();
+    if let Ok(ticket) = Vec::<G1Affine>::deserialize_compressed(input.ticket.as_slice()) {
+        let pks = &f.pks[start..end];
+        let _ = <BLSHash>::verify(&f.par, input.i, &input.lseed, &input.pids, pks, &ticket);
+    }
+
+    // encoded public keys are either those of the fixture or arbitrary
+    let f = evm();
+    let mut pks: Vec<&[u8]> = f.pks[start..end].iter().map(|pk| pk.as_slice()).collect();
+    pks.extend(input.encoded_pks.iter().map(|pk| pk.as_slice()));
+    let _ = verify_encoded(
+        &f.vk,
+        input.i,
+        &input.lseed,
+        &input.pids,
+        &pks,
+        &input.ticket,
+    );
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use ark_bls12_381::G2Affine;
+use ark_serialize::CanonicalDeserialize;
+use jackpot::lotteryscheme::bls_hash::BLSHash;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::vcbased::PublicKey;
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot::vectorcommitment::kzg::Commitment;
+use jackpot_fuzz::{bls, jack};
+use libfuzzer_sys::fuzz_target;
+
+// verify_key must not panic for untrusted public keys
+fuzz_target!(|data: &[u8]| {
+    if let Ok(com) = Commitment::deserialize_compressed(data) {
+        let pk = PublicKey { com };
+        let _ = Jack::verify_key(&jack().par, &pk);
+    }
+    if let Ok(pk) = G2Affine::deserialize_compressed(data) {
+        let _ = <BLSHash>::verify_key(&bls().par, &pk);
+    }
+});
@@ -0,0 +1,95 @@
+use std::sync::OnceLock;
+
+use ark_bls12_381::{Bls12_381, Fr};
+use ark_poly::Radix2EvaluationDomain;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use jackpot::lotteryscheme::bls_hash::BLSHash;
+use jackpot::lotteryscheme::jack::Jack;
+use jackpot::lotteryscheme::jack_evm::{
+    encode_public_key, encode_verifier_key, EvmVerifierKey, JackEvm,
+};
+use jackpot::lotteryscheme::registry::{KeyRegistry, WinningProof};
+use jackpot::lotteryscheme::vcbased::PublicKey;
+use jackpot::lotteryscheme::LotteryScheme;
+use jackpot::vectorcommitment::kzg::VcKZG;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Fixtures shared by the fuzz targets. Setting up parameters and
+// keys is expensive, so we do it once per process from a fixed seed
+
+/// number of lotteries of all fixtures
+pub const NUM_LOTTERIES: usize = 6;
+/// winning probability 1/K of all fixtures
+pub const K: u32 = 2;
+/// number of users of all fixtures
+pub const NUM_USERS: usize = 3;
+
+/// vector commitment scheme of Jack
+pub type JackVC = VcKZG<Bls12_381, Radix2EvaluationDomain<Fr>>;
+/// registry of keys of Jack
+pub type JackRegistry = KeyRegistry<Fr, JackVC>;
+/// proof that registered users won a lottery of Jack
+pub type JackWinningProof = WinningProof<Fr, JackVC>;
+
+pub struct Fixture<L: LotteryScheme> {
+    pub par: L::Parameters,
+    pub pks: Vec<L::PublicKey>,
+    pub sks: Vec<L::SecretKey>,
+}
+
+impl<L: LotteryScheme> Fixture<L> {
+    fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let par = L::setup(&mut rng, NUM_LOTTERIES, K).expect("Failed to set up fixture");
+        let (pks, sks) = (0..NUM_USERS).map(|_| L::gen(&mut rng, &par)).unzip();
+        Self { par, pks, sks }
+    }
+}
+
+pub fn jack() -> &'static Fixture<Jack> {
+    static FIXTURE: OnceLock<Fixture<Jack>> = OnceLock::new();
+    FIXTURE.get_or_init(|| Fixture::new(0))
+}
+
+pub fn bls() -> &'static Fixture<BLSHash> {
+    static FIXTURE: OnceLock<Fixture<BLSHash>> = OnceLock::new();
+    FIXTURE.get_or_init(|| Fixture::new(1))
+}
+
+/// registry of the keys of the jack fixture,
+/// where the jth user has identifier j
+pub fn registry() -> &'static JackRegistry {
+    static REGISTRY: OnceLock<JackRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let fixture = jack();
+        let mut registry = JackRegistry::new();
+        for (pid, pk) in fixture.pks.iter().enumerate() {
+            // keys are not Clone, so we copy them from their serialization
+            let mut bytes = Vec::new();
+            pk.com
+                .serialize_compressed(&mut bytes)
+                .expect("Failed to serialize fixture key");
+            let com = CanonicalDeserialize::deserialize_compressed(&bytes[..])
+                .expect("Failed to deserialize fixture key");
+            assert!(registry.register(&fixture.par, pid as u32, PublicKey { com }));
+        }
+        registry
+    })
+}
+
+pub struct EvmFixture {
+    pub vk: EvmVerifierKey,
+    pub pks: Vec<Vec<u8>>,
+}
+
+pub fn evm() -> &'static EvmFixture {
+    static FIXTURE: OnceLock<EvmFixture> = OnceLock::new();
+    FIXTURE.get_or_init(|| {
+        let fixture = Fixture::<JackEvm>::new(2);
+        EvmFixture {
+            vk: encode_verifier_key(&fixture.par),
+            pks: fixture.pks.iter().map(encode_public_key).collect(),
+        }
+    })
+}
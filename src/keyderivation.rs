@@ -0,0 +1,103 @@
+use hkdf::Hkdf;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::Sha256;
+
+// This module contains the derivation of keys from seeds. Following
+// EIP-2333, a master seed is derived from some input key material, and
+// children are derived from their parent using HKDF-SHA256, so that a
+// single master seed produces the seeds for all epochs. In contrast to
+// EIP-2333, seeds are not secret keys themselves, but are expanded into
+// a ChaCha20 RNG from which the lottery scheme generates the secret key.
+// All derivations are hardened, i.e., children reveal nothing about
+// their parent or their siblings
+
+/// seed from which keys are derived
+pub type Seed = [u8; 32];
+
+/// salt of HKDF used for all derivations
+const KEYGEN_SALT: &[u8] = b"JACKPOT-KEYGEN-SALT-";
+/// info of HKDF for the master seed
+const MASTER_INFO: &[u8] = b"JACKPOT-KEYGEN-MASTER//";
+/// info of HKDF for child seeds, followed by the index
+const CHILD_INFO: &[u8] = b"JACKPOT-KEYGEN-CHILD//";
+/// info of HKDF for the seed of the key generation RNG
+const RNG_INFO: &[u8] = b"JACKPOT-KEYGEN-RNG//";
+
+/// Computes HKDF-SHA256 with the keygen salt
+fn hkdf(ikm: &[u8], info: &[&[u8]]) -> Seed {
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(KEYGEN_SALT), ikm)
+        .expand_multi_info(info, &mut okm)
+        .expect("32 bytes are a valid length for HKDF-SHA256");
+    okm
+}
+
+/// Derive the master seed from input key material,
+/// which must have at least 32 bytes, as in EIP-2333
+pub fn derive_master_seed(ikm: &[u8]) -> Option<Seed> {
+    if ikm.len() < 32 {
+        return None;
+    }
+    Some(hkdf(ikm, &[MASTER_INFO]))
+}
+
+/// Derive the child seed with the given index, e.g., the seed for an epoch
+pub fn derive_child_seed(parent: &Seed, index: u32) -> Seed {
+    hkdf(parent, &[CHILD_INFO, &index.to_be_bytes()])
+}
+
+/// Derive the seed for a path of the form "m/1/2", as in EIP-2334
+pub fn derive_path(master: &Seed, path: &str) -> Option<Seed> {
+    let mut nodes = path.split('/');
+    if nodes.next() != Some("m") {
+        return None;
+    }
+    let mut seed = *master;
+    for node in nodes {
+        let index = node.parse::<u32>().ok()?;
+        seed = derive_child_seed(&seed, index);
+    }
+    Some(seed)
+}
+
+/// Get the RNG from which keys are generated for the given seed
+pub fn keygen_rng(seed: &Seed) -> ChaCha20Rng {
+    ChaCha20Rng::from_seed(hkdf(seed, &[RNG_INFO]))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::{derive_child_seed, derive_master_seed, derive_path, keygen_rng};
+
+    /// test that derivations are deterministic and separated
+    #[test]
+    fn keyderivation_test_derive() {
+        assert!(derive_master_seed(&[0x01; 31]).is_none());
+        let master = derive_master_seed(&[0x01; 32]).unwrap();
+        assert_eq!(master, derive_master_seed(&[0x01; 32]).unwrap());
+        assert_ne!(master, derive_master_seed(&[0x02; 32]).unwrap());
+
+        let child = derive_child_seed(&master, 7);
+        assert_ne!(child, derive_child_seed(&master, 8));
+        assert_eq!(derive_path(&master, "m").unwrap(), master);
+        assert_eq!(derive_path(&master, "m/7").unwrap(), child);
+        assert_eq!(
+            derive_path(&master, "m/7/3").unwrap(),
+            derive_child_seed(&child, 3)
+        );
+        assert!(derive_path(&master, "7/3").is_none());
+        assert!(derive_path(&master, "m/x").is_none());
+        assert!(derive_path(&master, "m/7/").is_none());
+
+        // the RNG is not seeded with the seed itself
+        let mut bytes = [0u8; 32];
+        keygen_rng(&master).fill_bytes(&mut bytes);
+        assert_ne!(bytes, master);
+        let mut bytes2 = [0u8; 32];
+        keygen_rng(&master).fill_bytes(&mut bytes2);
+        assert_eq!(bytes, bytes2);
+    }
+}
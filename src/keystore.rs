@@ -0,0 +1,369 @@
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+// This module contains an encrypted keystore for secret keys, modelled
+// on the EIP-2335 keystore. The key is derived from a password with
+// scrypt, and the secret is encrypted with ChaCha20-Poly1305. As the
+// cipher is authenticated, there is no separate checksum module
+
+/// version of the keystore format
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// maximum memory of scrypt, i.e., 128*n*r bytes, that we accept
+/// when loading keystores, so that they can not exhaust memory
+pub const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+/// maximum parallelization parameter of scrypt that we accept
+pub const MAX_SCRYPT_P: u32 = 16;
+
+/// parameters of scrypt. The default ones are those of EIP-2335
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct KdfParams {
+    /// length of the derived key in bytes
+    pub dklen: usize,
+    /// cost parameter, a power of two
+    pub n: u64,
+    /// block size
+    pub r: u32,
+    /// parallelization parameter
+    pub p: u32,
+    /// random salt, hex encoded
+    pub salt: String,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            dklen: 32,
+            n: 1 << 18,
+            r: 8,
+            p: 1,
+            salt: String::new(),
+        }
+    }
+}
+
+/// the key derivation function, which is always scrypt
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct KdfModule {
+    pub function: String,
+    pub params: KdfParams,
+    pub message: String,
+}
+
+/// parameters of the cipher
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CipherParams {
+    /// nonce, hex encoded
+    pub nonce: String,
+}
+
+/// the cipher, which is always ChaCha20-Poly1305
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CipherModule {
+    pub function: String,
+    pub params: CipherParams,
+    /// ciphertext including the tag, hex encoded
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct CryptoModules {
+    pub kdf: KdfModule,
+    pub cipher: CipherModule,
+}
+
+/// encrypted secret, which is stored as JSON
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Keystore {
+    pub crypto: CryptoModules,
+    /// kind of the secret, e.g., "jack", which is authenticated,
+    /// so that a keystore can not be loaded as a different type
+    pub kind: String,
+    pub description: String,
+    pub version: u32,
+}
+
+/// Removes control codes from the password, as done in EIP-2335
+fn normalize_password(password: &str) -> Zeroizing<Vec<u8>> {
+    let normalized: String = password.chars().filter(|c| !c.is_control()).collect();
+    Zeroizing::new(normalized.into_bytes())
+}
+
+/// Derives the key from the password using scrypt
+fn derive_key(password: &str, params: &KdfParams) -> Option<Zeroizing<Vec<u8>>> {
+    if params.dklen != 32 || !params.n.is_power_of_two() || params.n < 2 {
+        return None;
+    }
+    let memory = 128u64.checked_mul(params.n)?.checked_mul(params.r as u64)?;
+    if memory > MAX_SCRYPT_MEMORY || params.p > MAX_SCRYPT_P {
+        return None;
+    }
+    let log_n = params.n.trailing_zeros() as u8;
+    let scrypt_params = scrypt::Params::new(log_n, params.r, params.p, params.dklen).ok()?;
+    let salt = hex::decode(&params.salt).ok()?;
+    let mut key = Zeroizing::new(vec![0u8; params.dklen]);
+    scrypt::scrypt(
+        &normalize_password(password),
+        &salt,
+        &scrypt_params,
+        &mut key,
+    )
+    .ok()?;
+    Some(key)
+}
+
+/// Computes the associated data, which binds the
+/// ciphertext to the format, the kind, and the kdf
+fn associated_data(kind: &str, params: &KdfParams) -> Vec<u8> {
+    let mut ad = Vec::new();
+    ad.extend_from_slice(&KEYSTORE_VERSION.to_be_bytes());
+    ad.extend_from_slice(&(kind.len() as u32).to_be_bytes());
+    ad.extend_from_slice(kind.as_bytes());
+    ad.extend_from_slice(&params.n.to_be_bytes());
+    ad.extend_from_slice(&params.r.to_be_bytes());
+    ad.extend_from_slice(&params.p.to_be_bytes());
+    ad.extend_from_slice(params.salt.as_bytes());
+    ad
+}
+
+impl Keystore {
+    /// Encrypts the secret under the given password, using scrypt with
+    /// the cost parameters of params and a fresh salt and nonce
+    pub fn encrypt<R: Rng>(
+        rng: &mut R,
+        secret: &[u8],
+        kind: &str,
+        password: &str,
+        params: &KdfParams,
+    ) -> Option<Self> {
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+        let params = KdfParams {
+            salt: hex::encode(salt),
+            ..params.clone()
+        };
+        let key = derive_key(password, &params)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let ad = associated_data(kind, &params);
+        let payload = Payload {
+            msg: secret,
+            aad: &ad,
+        };
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), payload).ok()?;
+        Some(Self {
+            crypto: CryptoModules {
+                kdf: KdfModule {
+                    function: "scrypt".to_string(),
+                    params,
+                    message: String::new(),
+                },
+                cipher: CipherModule {
+                    function: "chacha20-poly1305".to_string(),
+                    params: CipherParams {
+                        nonce: hex::encode(nonce),
+                    },
+                    message: hex::encode(ciphertext),
+                },
+            },
+            kind: kind.to_string(),
+            description: String::new(),
+            version: KEYSTORE_VERSION,
+        })
+    }
+
+    /// Decrypts the secret, if the password is correct
+    /// and the keystore has the expected kind
+    pub fn decrypt(&self, kind: &str, password: &str) -> Option<Zeroizing<Vec<u8>>> {
+        if self.version != KEYSTORE_VERSION || self.kind != kind {
+            return None;
+        }
+        if self.crypto.kdf.function != "scrypt"
+            || self.crypto.cipher.function != "chacha20-poly1305"
+        {
+            return None;
+        }
+        let params = &self.crypto.kdf.params;
+        let key = derive_key(password, params)?;
+        let nonce = hex::decode(&self.crypto.cipher.params.nonce).ok()?;
+        if nonce.len() != 12 {
+            return None;
+        }
+        let ciphertext = hex::decode(&self.crypto.cipher.message).ok()?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let ad = associated_data(kind, params);
+        let payload = Payload {
+            msg: &ciphertext,
+            aad: &ad,
+        };
+        let secret = cipher.decrypt(Nonce::from_slice(&nonce), payload).ok()?;
+        Some(Zeroizing::new(secret))
+    }
+
+    /// Encodes the keystore as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Failed to encode keystore")
+    }
+
+    /// Decodes a keystore from JSON
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str(json).ok()
+    }
+}
+
+/// writes bytes to the file at path. They are written to a temporary
+/// file first, which only the owner can access on unix, and which is
+/// renamed once it is on disk, so that an existing file is never lost
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    let tmp = path.with_file_name(format!(
+        ".{}.tmp-{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    // a stale temporary file may have other permissions
+    let _ = fs::remove_file(&tmp);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let written = options.open(&tmp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Serializes x, encrypts it under the given password,
+/// and writes the keystore to the file at path atomically
+pub fn save_encrypted<T: CanonicalSerialize, R: Rng>(
+    rng: &mut R,
+    x: &T,
+    kind: &str,
+    path: &Path,
+    password: &str,
+    params: &KdfParams,
+) -> bool {
+    let mut secret = Zeroizing::new(Vec::new());
+    if x.serialize_compressed(&mut *secret).is_err() {
+        return false;
+    }
+    match Keystore::encrypt(rng, &secret, kind, password, params) {
+        Some(keystore) => write_atomic(path, keystore.to_json().as_bytes()).is_ok(),
+        None => false,
+    }
+}
+
+/// Reads the keystore from the file at path, decrypts
+/// it with the given password, and deserializes it
+pub fn load_encrypted<T: CanonicalDeserialize>(
+    kind: &str,
+    path: &Path,
+    password: &str,
+) -> Option<T> {
+    let json = fs::read_to_string(path).ok()?;
+    let keystore = Keystore::from_json(&json)?;
+    let secret = keystore.decrypt(kind, password)?;
+    T::deserialize_compressed(secret.as_slice()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_encrypted, save_encrypted, KdfParams, Keystore};
+
+    /// cheap parameters, so that tests run fast
+    fn test_params() -> KdfParams {
+        KdfParams {
+            n: 1 << 10,
+            ..KdfParams::default()
+        }
+    }
+
+    /// test that secrets can be decrypted exactly
+    /// with the right password and kind
+    #[test]
+    fn keystore_test_encrypt_decrypt() {
+        let mut rng = ark_std::rand::thread_rng();
+        let secret = b"lottery vector";
+        let keystore =
+            Keystore::encrypt(&mut rng, secret, "test", "pass\u{7f}word", &test_params()).unwrap();
+        let keystore = Keystore::from_json(&keystore.to_json()).unwrap();
+        let decrypted = keystore.decrypt("test", "password").unwrap();
+        assert_eq!(decrypted.as_slice(), secret);
+        assert!(keystore.decrypt("test", "passw0rd").is_none());
+        assert!(keystore.decrypt("other", "password").is_none());
+
+        // modified ciphertexts and parameters are rejected
+        let mut modified = keystore.clone();
+        let mut ciphertext = hex::decode(&modified.crypto.cipher.message).unwrap();
+        ciphertext[0] ^= 1;
+        modified.crypto.cipher.message = hex::encode(ciphertext);
+        assert!(modified.decrypt("test", "password").is_none());
+        let mut modified = keystore.clone();
+        modified.crypto.kdf.params.r = 4;
+        assert!(modified.decrypt("test", "password").is_none());
+
+        // excessive costs are rejected before running scrypt
+        let mut modified = keystore.clone();
+        modified.crypto.kdf.params.n = 1 << 63;
+        assert!(modified.decrypt("test", "password").is_none());
+    }
+
+    /// test that keystores replace existing files atomically
+    /// and, on unix, can only be read by their owner
+    #[test]
+    fn keystore_test_save_load() {
+        let mut rng = ark_std::rand::thread_rng();
+        let dir = std::env::temp_dir().join(format!("jack-keystore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("key.json");
+        std::fs::write(&path, "old").unwrap();
+        let secret = vec![1u8, 2, 3];
+        assert!(save_encrypted(
+            &mut rng,
+            &secret,
+            "test",
+            &path,
+            "password",
+            &test_params()
+        ));
+        let loaded: Vec<u8> = load_encrypted("test", &path, "password").unwrap();
+        assert_eq!(loaded, secret);
+        // no temporary file is left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // saving fails if the directory does not exist
+        let missing = dir.join("missing").join("key.json");
+        assert!(!save_encrypted(
+            &mut rng,
+            &secret,
+            "test",
+            &missing,
+            "password",
+            &test_params()
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,7 +1,24 @@
+// without the feature "std", only alloc is needed, so that keys
+// and tickets can be verified in embedded or enclave environments
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// module containing the hierarchical derivation
+/// of key generation seeds, modelled on EIP-2333
+pub mod keyderivation;
+/// module containing an encrypted keystore
+/// for secret keys, modelled on EIP-2335
+#[cfg(feature = "std")]
+pub mod keystore;
 /// module containing a trait for (aggregatable)
 /// lottery schemes and implementations of it.
 /// It contains Jack and the BLS+Hash lottery.
 pub mod lotteryscheme;
+/// module containing a guard that locks
+/// the memory pages of secrets, if enabled
+pub mod memlock;
+/// module containing a Fiat-Shamir transcript with
+/// labelled appends, used by all hash-based challenges
+pub mod transcript;
 /// module containing a trait for (aggregatable)
 /// vector commitments and implementation from KZG
 pub mod vectorcommitment;
@@ -1,13 +1,44 @@
 use ark_std::rand::Rng;
-use std::vec;
+use zeroize::ZeroizeOnDrop;
+
+use crate::keyderivation::{keygen_rng, Seed};
 
 /// This module contains the folklore BLS+Hash
 /// lottery scheme. That is, one wins if H(sig) < T
 pub mod bls_hash;
+/// This module contains the hash functions that
+/// the generic lottery scheme can use to compute
+/// the challenge of a user for a lottery
+pub mod challenge;
+/// This module contains a lottery scheme with a
+/// transparent setup, based on the IPA-based
+/// vector commitment instantiated using curve Bls12_381
+pub mod ipa_lottery;
 /// This module contains Jack, the lottery scheme
 /// based on the simulation-extractable KZG variant
 /// instantiated using curve Bls12_381
 pub mod jack;
+/// This module contains a variant of Jack over BN254
+/// whose tickets can be verified by Ethereum contracts
+#[cfg(feature = "std")]
+pub mod jack_evm;
+/// This module contains a variant of Jack over BLS12-377
+/// whose tickets can be verified inside of SNARK circuits
+#[cfg(feature = "r1cs")]
+pub mod jack_r1cs;
+/// This module contains a lottery scheme that only
+/// relies on hash functions, based on the vector
+/// commitment from salted Merkle trees
+pub mod merkle_lottery;
+/// This module contains a store for the parameters of Jack,
+/// which are saved to files named after their content
+#[cfg(feature = "std")]
+pub mod paramstore;
+/// This module contains a registry of verified public keys
+/// for the generic lottery scheme, which allows to refer
+/// to winners by a bitmap of their registry indices
+#[cfg(feature = "std")]
+pub mod registry;
 /// This module contains a generic lottery scheme
 /// based on a given vector commitment scheme
 pub mod vcbased;
@@ -16,7 +47,8 @@ pub mod vcbased;
 pub trait LotteryScheme {
     type Parameters;
     type PublicKey;
-    type SecretKey;
+    /// secret key, which should be zeroized on drop
+    type SecretKey: ZeroizeOnDrop;
     type Ticket;
     type LotterySeed;
 
@@ -27,6 +59,13 @@ pub trait LotteryScheme {
     /// Generate keys for a user
     fn gen<R: Rng>(rng: &mut R, par: &Self::Parameters) -> (Self::PublicKey, Self::SecretKey);
 
+    /// Generate keys for a user deterministically from a seed, e.g.,
+    /// one derived for an epoch with keyderivation::derive_child_seed.
+    /// The keys are generated by gen using a ChaCha20 RNG for the seed
+    fn gen_from_seed(par: &Self::Parameters, seed: &Seed) -> (Self::PublicKey, Self::SecretKey) {
+        Self::gen(&mut keygen_rng(seed), par)
+    }
+
     /// Verify the well-formedness of a public key
     fn verify_key(par: &Self::Parameters, pk: &Self::PublicKey) -> bool;
 
@@ -61,24 +100,27 @@ pub trait LotteryScheme {
     ) -> Option<Self::Ticket>;
 
     /// Aggregate tickets tickets[j] of users
-    /// with identifiers pids[j] and public keys pks[j] for the ith lottery
+    /// with identifiers pids[j] and public keys pks[j] for the ith lottery.
+    /// Tickets are aggregated in the order of the identifiers, so that the
+    /// aggregate is unique. Outputs None if a pid or a pk occurs twice
     fn aggregate(
         par: &Self::Parameters,
         i: u32,
         lseed: &Self::LotterySeed,
-        pids: &Vec<u32>,
-        pks: &Vec<Self::PublicKey>,
-        tickets: &Vec<Self::Ticket>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
+        tickets: &[Self::Ticket],
     ) -> Option<Self::Ticket>;
 
     /// Verify ticket for the ith lottery with lottery seed lseed
-    /// For users with identifiers pids[j] and public keys pks[j]
+    /// For users with identifiers pids[j] and public keys pks[j].
+    /// Fails if a pid or a pk occurs twice
     fn verify(
         par: &Self::Parameters,
         i: u32,
         lseed: &Self::LotterySeed,
-        pids: &Vec<u32>,
-        pks: &Vec<Self::PublicKey>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
         ticket: &Self::Ticket,
     ) -> bool;
 }
@@ -87,6 +129,7 @@ pub trait LotteryScheme {
 // be used by implementors of this trait
 
 /// test that if we generate keys honestly, they verify
+#[cfg(feature = "std")]
 fn _lottery_test_key_verify<L: LotteryScheme>() {
     let mut rng = ark_std::rand::thread_rng();
     let runs = 10;
@@ -106,6 +149,7 @@ fn _lottery_test_key_verify<L: LotteryScheme>() {
 
 /// test that if we set winning probability to 1/k = 1/1 = 1,
 /// then every ticket is winning
+#[cfg(feature = "std")]
 fn _lottery_test_always_winning<L: LotteryScheme>() {
     let mut rng = ark_std::rand::thread_rng();
     let runs = 5;
@@ -119,9 +163,9 @@ fn _lottery_test_always_winning<L: LotteryScheme>() {
         // generate key pairs for two users
         let (pk0, sk0) = L::gen(&mut rng, &par);
         let (pk1, sk1) = L::gen(&mut rng, &par);
-        let sks = vec![sk0, sk1];
-        let pks = vec![pk0, pk1];
-        let pids = vec![0, 1];
+        let sks = [sk0, sk1];
+        let pks = [pk0, pk1];
+        let pids = [0, 1];
         // do the lotteries
         for i in 0..num_lotteries {
             // participate should output true
@@ -141,7 +185,7 @@ fn _lottery_test_always_winning<L: LotteryScheme>() {
             let ticket1 = ticket1.unwrap();
             let ticket2 = ticket2.unwrap();
             // we aggregate the tickets
-            let ticket = L::aggregate(&par, i as u32, &lseed, &pids, &pks, &vec![ticket1, ticket2]);
+            let ticket = L::aggregate(&par, i as u32, &lseed, &pids, &pks, &[ticket1, ticket2]);
             assert!(ticket.is_some());
             let ticket = ticket.unwrap();
             // the aggregated ticket should verify
@@ -149,3 +193,131 @@ fn _lottery_test_always_winning<L: LotteryScheme>() {
         }
     }
 }
+
+/// test that keys generated from the same seed are
+/// interchangeable, and that different seeds give different keys
+#[cfg(feature = "std")]
+fn _lottery_test_gen_from_seed<L: LotteryScheme>() {
+    use std::slice::from_ref;
+
+    let mut rng = ark_std::rand::thread_rng();
+    // everyone wins with probability 1
+    let num_lotteries = 14;
+    let k = 1;
+    let par = L::setup(&mut rng, num_lotteries, k).unwrap();
+    let seed = [0x42; 32];
+    let (pk0, _sk0) = L::gen_from_seed(&par, &seed);
+    let (_pk1, sk1) = L::gen_from_seed(&par, &seed);
+    let (pk2, _sk2) = L::gen_from_seed(&par, &[0x43; 32]);
+    assert!(L::verify_key(&par, &pk0));
+
+    // a ticket from the second key verifies for the first
+    // public key, but not for the one from another seed
+    let pid = 0;
+    for i in 0..num_lotteries as u32 {
+        let lseed = L::sample_seed(&mut rng, &par, i);
+        let ticket = L::get_ticket(&par, i, &lseed, pid, &sk1, &pk0).unwrap();
+        let ticket = L::aggregate(&par, i, &lseed, &[pid], from_ref(&pk0), &[ticket]).unwrap();
+        assert!(L::verify(&par, i, &lseed, &[pid], from_ref(&pk0), &ticket));
+        assert!(!L::verify(&par, i, &lseed, &[pid], from_ref(&pk2), &ticket));
+    }
+}
+
+/// test that no user can be counted twice, either under the same
+/// identifier or with the same key under two identifiers, and that
+/// aggregates do not depend on the order of the tickets
+#[cfg(feature = "std")]
+fn _lottery_test_duplicates<L: LotteryScheme>() {
+    let mut rng = ark_std::rand::thread_rng();
+    // everyone wins with probability 1
+    let num_lotteries = 14;
+    let k = 1;
+    let par = L::setup(&mut rng, num_lotteries, k).unwrap();
+    let i = 3;
+    let lseed = L::sample_seed(&mut rng, &par, i);
+    // keys are not Clone, so we get copies from their seeds
+    let key = |seed: u8| L::gen_from_seed(&par, &[seed; 32]);
+    let (pk0, sk0) = key(0);
+    let (pk1, sk1) = key(1);
+    let ticket0 = |pid| L::get_ticket(&par, i, &lseed, pid, &sk0, &pk0).unwrap();
+    let ticket1 = |pid| L::get_ticket(&par, i, &lseed, pid, &sk1, &pk1).unwrap();
+    let pks = [key(0).0, key(1).0];
+    let pks_dup = [key(0).0, key(0).0];
+
+    // the same identifier twice, or the same key under two identifiers
+    assert!(L::aggregate(&par, i, &lseed, &[0, 0], &pks, &[ticket0(0), ticket1(0)]).is_none());
+    assert!(L::aggregate(
+        &par,
+        i,
+        &lseed,
+        &[0, 1],
+        &pks_dup,
+        &[ticket0(0), ticket0(1)]
+    )
+    .is_none());
+
+    // tickets given in any order aggregate to a ticket
+    // for the users sorted by their identifiers
+    let pks_rev = [key(1).0, key(0).0];
+    let tickets_rev = [ticket1(1), ticket0(0)];
+    let ticket = L::aggregate(&par, i, &lseed, &[1, 0], &pks_rev, &tickets_rev).unwrap();
+    assert!(L::verify(&par, i, &lseed, &[0, 1], &pks, &ticket));
+    // but it does not verify with a user counted twice
+    assert!(!L::verify(&par, i, &lseed, &[0, 0], &pks, &ticket));
+    assert!(!L::verify(&par, i, &lseed, &[0, 1], &pks_dup, &ticket));
+}
+
+/// Welch's t-statistic for the means of two samples
+#[cfg(feature = "std")]
+fn _welch_t(a: &[f64], b: &[f64]) -> f64 {
+    let mean_var = |xs: &[f64]| {
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let var = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1.0);
+        (mean, var / n)
+    };
+    let (mean_a, var_a) = mean_var(a);
+    let (mean_b, var_b) = mean_var(b);
+    (mean_a - mean_b) / (var_a + var_b).sqrt()
+}
+
+/// test that the running time of participate does not depend on whether
+/// the user wins, in the style of dudect. We time participate for random
+/// lottery seeds with winning probability 1/2, drop outliers, and check
+/// with Welch's t-test that winning and losing runs take the same time.
+/// This is sensitive to noise, so implementors should mark it as ignored
+#[cfg(feature = "std")]
+fn _lottery_test_constant_time<L: LotteryScheme>() {
+    use std::time::Instant;
+
+    let mut rng = ark_std::rand::thread_rng();
+    let num_lotteries = 14;
+    let k = 2;
+    let runs = 4000;
+    let par = L::setup(&mut rng, num_lotteries, k).unwrap();
+    let (pk, sk) = L::gen(&mut rng, &par);
+    let mut samples = Vec::with_capacity(runs);
+    for r in 0..runs {
+        let i = (r % num_lotteries) as u32;
+        let lseed = L::sample_seed(&mut rng, &par, i);
+        let start = Instant::now();
+        let won = L::participate(&par, i, &lseed, 0, &sk, &pk);
+        samples.push((won, start.elapsed().as_nanos() as f64));
+    }
+
+    // drop the slowest 5 percent, which are mostly due to interrupts
+    let mut times: Vec<f64> = samples.iter().map(|(_, t)| *t).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let cutoff = times[runs * 95 / 100];
+    let (wins, losses): (Vec<_>, Vec<_>) = samples
+        .into_iter()
+        .filter(|(_, t)| *t <= cutoff)
+        .partition(|(won, _)| *won);
+    let wins: Vec<f64> = wins.into_iter().map(|(_, t)| t).collect();
+    let losses: Vec<f64> = losses.into_iter().map(|(_, t)| t).collect();
+    assert!(wins.len() > runs / 10 && losses.len() > runs / 10);
+
+    // dudect considers |t| > 10 as clear evidence of a leak
+    let t = _welch_t(&wins, &losses);
+    assert!(t.abs() < 10.0, "timing differs: t = {}", t);
+}
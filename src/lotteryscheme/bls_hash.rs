@@ -1,4 +1,4 @@
-use std::ops::Mul;
+use ark_std::ops::Mul;
 
 use super::LotteryScheme;
 use ark_bls12_381::g1::Config as G1Config;
@@ -11,13 +11,28 @@ use ark_ec::{
     CurveGroup,
 };
 use ark_ff::field_hashers::DefaultFieldHasher;
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::cmp::Ordering;
+use ark_std::collections::BTreeSet;
+use ark_std::fmt;
+use ark_std::marker::PhantomData;
+use ark_std::{vec, vec::Vec};
 use ark_std::{One, UniformRand, Zero};
-use sha2::Digest;
 use sha2::Sha256;
-
-/// BLS+Hash lottery scheme
-pub struct BLSHash;
+#[cfg(feature = "std")]
+use std::path::Path;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+#[cfg(feature = "std")]
+use crate::keystore::{self, KdfParams};
+use crate::transcript::{Transcript, TranscriptHash};
+
+/// BLS+Hash lottery scheme, where the winning predicate
+/// hashes signatures using a transcript with backend H
+pub struct BLSHash<H: TranscriptHash = Sha256> {
+    _h: PhantomData<H>,
+}
 
 /// See https://github.com/ethereum/bls12-381-tests
 const DOMAIN: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
@@ -31,6 +46,42 @@ type G1Prepared = <Bls12_381 as Pairing>::G1Prepared;
 type G2Prepared = <Bls12_381 as Pairing>::G2Prepared;
 type F = <Bls12_381 as Pairing>::ScalarField;
 
+/// BLS secret key, which is zeroized on drop
+#[derive(CanonicalSerialize, CanonicalDeserialize, Zeroize, ZeroizeOnDrop)]
+pub struct BLSSecretKey {
+    sk: F,
+}
+
+/// kind of keystores containing BLS secret keys
+pub const BLS_SECRET_KEY_KIND: &str = "bls-secret-key";
+
+#[cfg(feature = "std")]
+impl BLSSecretKey {
+    /// Encrypt the secret key under the given password and write
+    /// it to the file at path. Returns whether this succeeded
+    pub fn save_encrypted<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        path: &Path,
+        password: &str,
+        params: &KdfParams,
+    ) -> bool {
+        keystore::save_encrypted(rng, self, BLS_SECRET_KEY_KIND, path, password, params)
+    }
+
+    /// Read a secret key from the file at path and decrypt it
+    /// with the given password. Returns None if this fails
+    pub fn load_encrypted(path: &Path, password: &str) -> Option<Self> {
+        keystore::load_encrypted(BLS_SECRET_KEY_KIND, path, password)
+    }
+}
+
+impl fmt::Debug for BLSSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BLSSecretKey(<redacted>)")
+    }
+}
+
 pub struct BLSParameters {
     /// generator for G2
     g2: G2Affine,
@@ -40,33 +91,35 @@ pub struct BLSParameters {
 }
 
 /// predicate to check if a signature is "winning"
-/// Recall: A party wins if its signature is valid and winning
-fn winning_predicate(log_k: u32, sig: &G1Affine) -> bool {
+/// Recall: A party wins if its signature is valid and winning.
+/// The predicate takes constant time, i.e., its running
+/// time does not depend on whether the party wins
+fn winning_predicate<H: TranscriptHash>(log_k: u32, sig: &G1Affine) -> bool {
     // We hash the signature and check if
     // the first log k bits of it are zero
-    let mut hasher = Sha256::new_with_prefix("BLS-HASH-PRED//".as_bytes());
+    let mut transcript = Transcript::<H>::new(b"BLS-HASH-PRED//");
     let mut sig_ser = Vec::new();
     sig.serialize_compressed(&mut sig_ser)
         .expect("Failed to serialize signature in winning_predicate.");
-    hasher.update(&sig_ser);
-    let digest = hasher.finalize();
+    transcript.append_message(b"sig", &sig_ser);
+    let mut digest = [0u8; 32];
+    transcript.challenge_bytes(b"pred", &mut digest);
 
-    // check that first 8*floor(log_k/8) bits are zero
+    // we mask out the first log_k bits of the digest, i.e., the first
+    // floor(log_k/8) bytes and log_k modulo 8 bits of the next byte,
+    // and check that all of them are zero without branching on them
     assert!(log_k <= 32 * 8);
     let zerobytes = (log_k >> 3) as usize;
-    for i in 0..zerobytes {
-        if digest[i] != 0x00 {
-            return false;
-        }
+    let mut acc = 0u8;
+    for (j, byte) in digest.iter().enumerate() {
+        let mask = match j.cmp(&zerobytes) {
+            Ordering::Less => 0xff,
+            Ordering::Equal => ((1u16 << (log_k & 0x07)) - 1) as u8,
+            Ordering::Greater => 0x00,
+        };
+        acc |= byte & mask;
     }
-    // check that remaining log_k modulo 8 bits are zero
-    let nextbyte = digest[zerobytes];
-    let expected = log_k & 0x07;
-    let mask = (1 << expected) - 1;
-    if (nextbyte & mask) != 0 {
-        return false;
-    }
-    true
+    acc.ct_eq(&0x00).into()
 }
 
 /// hash a message into group G1
@@ -101,11 +154,16 @@ fn bls_ver(g2: &G2Affine, pk: &G2, sig: &G1, mes: &[u8; 36]) -> bool {
 }
 
 /// verifies a bunch of BLS signatures for the same message
-fn bls_batch_ver(g2: &G2Affine, pks: &[G2Affine], sigs: &[G1Affine], mes: &[u8; 36]) -> bool {
+fn bls_batch_ver<H: TranscriptHash>(
+    g2: &G2Affine,
+    pks: &[G2Affine],
+    sigs: &[G1Affine],
+    mes: &[u8; 36],
+) -> bool {
     if pks.len() != sigs.len() {
         return false;
     }
-    if pks.len() < 1 {
+    if pks.is_empty() {
         return false;
     }
     let le = pks.len();
@@ -116,43 +174,60 @@ fn bls_batch_ver(g2: &G2Affine, pks: &[G2Affine], sigs: &[G1Affine], mes: &[u8;
     // e(aggsig, g2) = e(h, aggpk)
     // for aggsig = prod_i sig_i^{chi^{i-1}}
     // and aggpk  = prod_i  pk_i^{chi^{i-1}}
-    // where chi is random and we use MSMs
-    let mut rng = ark_std::rand::thread_rng();
-    let chi = F::rand(&mut rng);
+    // where chi is derived from all inputs, so that the signatures
+    // are fixed before chi is known, and we use MSMs
+    let mut transcript = Transcript::<H>::new(b"BLS-HASH-BATCH//");
+    transcript.append_serializable(b"g2", g2);
+    transcript.append_message(b"mes", mes);
+    transcript.append_serializable(b"pks", pks);
+    transcript.append_serializable(b"sigs", sigs);
+    let chi: F = transcript.challenge_field(b"chi");
     let mut chi_powers = Vec::with_capacity(le);
     chi_powers.push(F::one());
     for j in 1..le {
         chi_powers.push(chi_powers[j - 1] * chi);
     }
-    let aggsig = <G1 as VariableBaseMSM>::msm(&sigs, &chi_powers).unwrap();
-    let aggpk = <G2 as VariableBaseMSM>::msm(&pks, &chi_powers).unwrap();
+    let aggsig = <G1 as VariableBaseMSM>::msm(sigs, &chi_powers).unwrap();
+    let aggpk = <G2 as VariableBaseMSM>::msm(pks, &chi_powers).unwrap();
 
     bls_ver(g2, &aggpk, &aggsig, mes)
 }
 
+/// returns the order in which the users with identifiers pids[j]
+/// and public keys pks[j] are aggregated, i.e., sorted by identifier.
+/// Returns None if an identifier or a public key occurs twice
+fn canonical_order(pids: &[u32], pks: &[G2Affine]) -> Option<Vec<usize>> {
+    let mut order: Vec<usize> = (0..pids.len()).collect();
+    order.sort_unstable_by_key(|j| pids[*j]);
+    if order.windows(2).any(|w| pids[w[0]] == pids[w[1]]) {
+        return None;
+    }
+    let mut keys = BTreeSet::new();
+    if !pks.iter().all(|pk| keys.insert((pk.x, pk.y))) {
+        return None;
+    }
+    Some(order)
+}
+
 /// function to assemble the message to sign
 /// from lseed, and lottery number i.
 /// Note: We do not add pid as part of the message
 /// to make batch verification possible. However,
 /// this means that two parties with the same public
 /// key will always win either both or not.
-/// A real system should handle this case differently
+/// Therefore, aggregate and verify reject such parties
 fn assemble_message(i: u32, lseed: &[u8; 32]) -> [u8; 36] {
     let ibytes = i.to_le_bytes();
     let mut mes = [0; 36];
-    for j in 0..4 {
-        mes[j] = ibytes[j];
-    }
-    for j in 0..32 {
-        mes[4 + j] = lseed[j];
-    }
+    mes[0..4].copy_from_slice(&ibytes);
+    mes[4..36].copy_from_slice(lseed);
     mes
 }
 
-impl LotteryScheme for BLSHash {
+impl<H: TranscriptHash> LotteryScheme for BLSHash<H> {
     type Parameters = BLSParameters;
     type PublicKey = G2Affine;
-    type SecretKey = F;
+    type SecretKey = BLSSecretKey;
     type Ticket = Vec<G1Affine>; // trivial aggregation
     type LotterySeed = [u8; 32];
 
@@ -175,8 +250,8 @@ impl LotteryScheme for BLSHash {
         par: &Self::Parameters,
     ) -> (Self::PublicKey, Self::SecretKey) {
         // key for the lottery is just a BLS key
-        let sk = F::rand(rng);
-        let pk = par.g2.mul(sk).into_affine();
+        let sk = BLSSecretKey { sk: F::rand(rng) };
+        let pk = par.g2.mul(sk.sk).into_affine();
         (pk, sk)
     }
 
@@ -203,11 +278,11 @@ impl LotteryScheme for BLSHash {
         sk: &Self::SecretKey,
         pk: &Self::PublicKey,
     ) -> bool {
-        // compute the ticket. This does not panic.
-        let opt_ticket = Self::get_ticket(par, i, lseed, pid, sk, pk);
-        let sig = opt_ticket.unwrap()[0];
-        // check if it is winning.
-        winning_predicate(par.log_k, &sig)
+        // compute the ticket and check if it is winning
+        match Self::get_ticket(par, i, lseed, pid, sk, pk) {
+            Some(ticket) if ticket.len() == 1 => winning_predicate::<H>(par.log_k, &ticket[0]),
+            _ => false,
+        }
     }
 
     fn get_ticket(
@@ -220,7 +295,7 @@ impl LotteryScheme for BLSHash {
     ) -> Option<Self::Ticket> {
         // Compute a signature of (lseed,pid,i)
         let mes = assemble_message(i, lseed);
-        let sig = bls_sign(sk, &mes);
+        let sig = bls_sign(&sk.sk, &mes);
         // The signature is the ticket
         Some(vec![sig])
     }
@@ -230,21 +305,29 @@ impl LotteryScheme for BLSHash {
         _par: &Self::Parameters,
         _i: u32,
         _lseed: &Self::LotterySeed,
-        _pids: &Vec<u32>,
-        _pks: &Vec<Self::PublicKey>,
-        tickets: &Vec<Self::Ticket>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
+        tickets: &[Self::Ticket],
     ) -> Option<Self::Ticket> {
+        if pids.len() != pks.len() || pids.len() != tickets.len() {
+            return None;
+        }
+        if tickets.iter().any(|ticket| ticket.len() != 1) {
+            return None;
+        }
         // Trivial aggregation:
-        // Tickets are just concatenated
-        Some(tickets.concat())
+        // Tickets are just concatenated, sorted
+        // by identifier, so that aggregates are unique
+        let order = canonical_order(pids, pks)?;
+        Some(order.into_iter().map(|j| tickets[j][0]).collect())
     }
 
     fn verify(
         par: &Self::Parameters,
         i: u32,
         lseed: &Self::LotterySeed,
-        pids: &Vec<u32>,
-        pks: &Vec<Self::PublicKey>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
         ticket: &Self::Ticket,
     ) -> bool {
         if pids.len() != pks.len() {
@@ -253,17 +336,23 @@ impl LotteryScheme for BLSHash {
         if pids.len() != ticket.len() {
             return false;
         }
-        if pids.len() < 1 {
+        if pids.is_empty() {
             return false;
         }
+        // signatures are in the order of aggregation,
+        // and none of the users can be counted twice
+        let pks: Vec<G2Affine> = match canonical_order(pids, pks) {
+            Some(order) => order.into_iter().map(|j| pks[j]).collect(),
+            None => return false,
+        };
         // verify all signatures
         let mes = assemble_message(i, lseed);
-        if !bls_batch_ver(&par.g2, pks, ticket, &mes) {
+        if !bls_batch_ver::<H>(&par.g2, &pks, ticket, &mes) {
             return false;
         }
         // verify that all signatures are winning
         for sig in ticket {
-            if !winning_predicate(par.log_k, &sig) {
+            if !winning_predicate::<H>(par.log_k, sig) {
                 return false;
             }
         }
@@ -276,13 +365,17 @@ mod tests {
     use ark_bls12_381::Bls12_381;
     use ark_ec::{pairing::Pairing, AffineRepr};
     use ark_std::UniformRand;
+    use sha2::Sha256;
 
     use crate::lotteryscheme::{
+        _lottery_test_always_winning, _lottery_test_constant_time, _lottery_test_duplicates,
+        _lottery_test_gen_from_seed, _lottery_test_key_verify,
         bls_hash::{bls_batch_ver, bls_ver},
-        LotteryScheme, _lottery_test_always_winning, _lottery_test_key_verify,
+        LotteryScheme,
     };
 
-    use super::{bls_sign, BLSHash};
+    use super::{bls_sign, BLSHash, BLSSecretKey};
+    use crate::keystore::KdfParams;
 
     type G1 = <Bls12_381 as Pairing>::G1;
 
@@ -294,11 +387,12 @@ mod tests {
 
         for _ in 0..runs {
             // generate parameters and keys
-            let par = BLSHash::setup(&mut rng, 1024, 1024).unwrap();
-            let (pk, sk) = BLSHash::gen(&mut rng, &par);
+            let par = <BLSHash>::setup(&mut rng, 1024, 1024).unwrap();
+            let (pk, sk) = <BLSHash>::gen(&mut rng, &par);
+            assert_eq!(format!("{:?}", sk), "BLSSecretKey(<redacted>)");
             // sign a message
             let mes = [0x08; 36];
-            let sig = bls_sign(&sk, &mes);
+            let sig = bls_sign(&sk.sk, &mes);
             //let sig = <Bls12<ark_bls12_381::Config> as Pairing>::G1Affine::rand(&mut rng);
             // assert that it verifies
             assert!(bls_ver(&par.g2, &pk.into_group(), &sig.into_group(), &mes));
@@ -308,6 +402,25 @@ mod tests {
         }
     }
 
+    /// test that secret keys can be stored encrypted
+    /// and only loaded with the right password
+    #[test]
+    fn test_bls_keystore() {
+        let mut rng = ark_std::rand::thread_rng();
+        let par = <BLSHash>::setup(&mut rng, 1024, 1024).unwrap();
+        let (_pk, sk) = <BLSHash>::gen(&mut rng, &par);
+        let params = KdfParams {
+            n: 1 << 10,
+            ..KdfParams::default()
+        };
+        let path = std::env::temp_dir().join(format!("bls-sk-{}.json", std::process::id()));
+        assert!(sk.save_encrypted(&mut rng, &path, "password", &params));
+        assert!(BLSSecretKey::load_encrypted(&path, "passw0rd").is_none());
+        let loaded = BLSSecretKey::load_encrypted(&path, "password").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.sk, sk.sk);
+    }
+
     /// test that a bunch of honest BLS signatures batch verify
     #[test]
     fn test_bls_sign_and_batch_ver() {
@@ -316,11 +429,11 @@ mod tests {
         let numkeys = 20;
         for _ in 0..runs {
             // generate parameters and some keys
-            let par = BLSHash::setup(&mut rng, 1024, 1024).unwrap();
+            let par = <BLSHash>::setup(&mut rng, 1024, 1024).unwrap();
             let mut pks = Vec::new();
             let mut sks = Vec::new();
             for _ in 0..numkeys {
-                let (pk, sk) = BLSHash::gen(&mut rng, &par);
+                let (pk, sk) = <BLSHash>::gen(&mut rng, &par);
                 pks.push(pk);
                 sks.push(sk);
             }
@@ -328,12 +441,15 @@ mod tests {
             // sign a message with all keys
             let mes = [0x08; 36];
             let mut sigs = Vec::new();
-            for j in 0..numkeys {
-                let sig = bls_sign(&sks[j], &mes);
+            for sk in &sks {
+                let sig = bls_sign(&sk.sk, &mes);
                 sigs.push(sig);
             }
             // assert that they batch verify
-            assert!(bls_batch_ver(&par.g2, &pks, &sigs, &mes));
+            assert!(bls_batch_ver::<Sha256>(&par.g2, &pks, &sigs, &mes));
+            // but not if two of them are swapped
+            sigs.swap(0, 1);
+            assert!(!bls_batch_ver::<Sha256>(&par.g2, &pks, &sigs, &mes));
         }
     }
 
@@ -346,4 +462,90 @@ mod tests {
     fn blshash_lottery_test_always_winning() {
         _lottery_test_always_winning::<BLSHash>();
     }
+
+    #[test]
+    fn blshash_lottery_test_gen_from_seed() {
+        _lottery_test_gen_from_seed::<BLSHash>();
+    }
+
+    /// test that users cannot be counted twice,
+    /// and that aggregates are unique
+    #[test]
+    fn blshash_lottery_test_duplicates() {
+        _lottery_test_duplicates::<BLSHash>();
+
+        let mut rng = ark_std::rand::thread_rng();
+        let par = <BLSHash>::setup(&mut rng, 14, 1).unwrap();
+        let i = 5;
+        let lseed = <BLSHash>::sample_seed(&mut rng, &par, i);
+        let (pk0, sk0) = <BLSHash>::gen(&mut rng, &par);
+        let (pk1, sk1) = <BLSHash>::gen(&mut rng, &par);
+        let t0 = <BLSHash>::get_ticket(&par, i, &lseed, 0, &sk0, &pk0).unwrap();
+        let t1 = <BLSHash>::get_ticket(&par, i, &lseed, 1, &sk1, &pk1).unwrap();
+
+        // the same signature twice, under one or two identifiers
+        let forged = [t0.clone(), t0.clone()].concat();
+        assert!(!<BLSHash>::verify(
+            &par,
+            i,
+            &lseed,
+            &[0, 0],
+            &[pk0, pk0],
+            &forged
+        ));
+        assert!(!<BLSHash>::verify(
+            &par,
+            i,
+            &lseed,
+            &[0, 1],
+            &[pk0, pk0],
+            &forged
+        ));
+
+        // aggregates are sorted by identifier, and verify accepts
+        // users in any order, but only signatures in this order
+        let agg = <BLSHash>::aggregate(
+            &par,
+            i,
+            &lseed,
+            &[0, 1],
+            &[pk0, pk1],
+            &[t0.clone(), t1.clone()],
+        );
+        let agg_rev = <BLSHash>::aggregate(&par, i, &lseed, &[1, 0], &[pk1, pk0], &[t1, t0]);
+        let agg = agg.unwrap();
+        assert_eq!(agg, agg_rev.unwrap());
+        assert!(<BLSHash>::verify(
+            &par,
+            i,
+            &lseed,
+            &[0, 1],
+            &[pk0, pk1],
+            &agg
+        ));
+        assert!(<BLSHash>::verify(
+            &par,
+            i,
+            &lseed,
+            &[1, 0],
+            &[pk1, pk0],
+            &agg
+        ));
+        let agg_rev: Vec<_> = agg.iter().rev().copied().collect();
+        assert!(!<BLSHash>::verify(
+            &par,
+            i,
+            &lseed,
+            &[1, 0],
+            &[pk1, pk0],
+            &agg_rev
+        ));
+    }
+
+    /// timing test, which is ignored as it depends on the machine
+    #[test]
+    #[ignore]
+    fn blshash_lottery_test_constant_time() {
+        _lottery_test_constant_time::<BLSHash>();
+    }
 }
@@ -0,0 +1,311 @@
+use ark_crypto_primitives::sponge::poseidon::{
+    find_poseidon_ark_and_mds, PoseidonConfig, PoseidonSponge,
+};
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ff::{Field, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use ark_std::marker::PhantomData;
+use ark_std::{vec, vec::Vec};
+use blake2::Blake2s256;
+use sha2::Sha256;
+use sha3::{Keccak256, Sha3_256};
+
+use super::vcbased::LotterySeed;
+use crate::transcript::{Transcript, TranscriptHash};
+
+/// trait representing domain separation tags, which
+/// are prepended to everything that is hashed
+pub trait DomainSeparationTag {
+    const DST: &'static [u8];
+}
+
+/// the domain separation tag used by default
+pub struct ChallDst;
+
+impl DomainSeparationTag for ChallDst {
+    const DST: &'static [u8] = b"Chall//";
+}
+
+/// trait representing hash functions that compute the challenge
+/// x = H(pk,pid,i,lseed) of the generic lottery scheme, where public
+/// keys are commitments of type C. The hash state after absorbing
+/// the public key can be cached, as it is the same for all lottery rounds
+pub trait ChallengeHasher<F: Field, C: CanonicalSerialize> {
+    /// hash state after absorbing the tag and a public key
+    type Midstate: Clone;
+
+    /// Absorb the domain separation tag and the public key com
+    fn midstate(com: &C) -> Self::Midstate;
+
+    /// Compute the challenge for user pid and the ith lottery with
+    /// seed lseed, given the midstate for the public key of the user.
+    /// The challenge is an integer with log_k bits, as a field element
+    fn challenge(log_k: u32, midstate: &Self::Midstate, pid: u32, i: u32, lseed: &LotterySeed)
+        -> F;
+}
+
+/// challenge hasher based on a Fiat-Shamir transcript over a hash
+/// function with byte-oriented interface, e.g., SHA-256 or Keccak-256
+pub struct DigestHasher<H: TranscriptHash, T: DomainSeparationTag = ChallDst> {
+    _h: PhantomData<H>,
+    _t: PhantomData<T>,
+}
+
+/// challenge hasher based on SHA-256, which is used by default
+pub type Sha256Hasher<T = ChallDst> = DigestHasher<Sha256, T>;
+/// challenge hasher based on SHA3-256
+pub type Sha3Hasher<T = ChallDst> = DigestHasher<Sha3_256, T>;
+/// challenge hasher based on Keccak-256
+pub type Keccak256Hasher<T = ChallDst> = DigestHasher<Keccak256, T>;
+/// challenge hasher based on BLAKE2s
+pub type Blake2Hasher<T = ChallDst> = DigestHasher<Blake2s256, T>;
+/// challenge hasher based on BLAKE3
+pub type Blake3Hasher<T = ChallDst> = DigestHasher<blake3::Hasher, T>;
+
+/// interprets the first log_k bits of digest as an integer.
+/// In contrast to other challenges, x is not a uniform field element
+/// but a uniform integer below k, so the bits are not reduced modulo p
+fn challenge_from_bytes<F: Field>(log_k: u32, digest: &[u8]) -> F {
+    // we take the first log_k bits and interpret as an integer
+    // for that, first find out how many bytes we use entirely
+    assert!((log_k >> 3) < digest.len() as u32);
+    let num_fullbytes = (log_k >> 3) as usize;
+    let mut hashbytes: Vec<u8> = vec![0x00; num_fullbytes + 1];
+    hashbytes[..num_fullbytes].copy_from_slice(&digest[..num_fullbytes]);
+    // for the final byte we only need a part of it
+    let nextbyte = digest[num_fullbytes];
+    let expected = log_k & 0x07;
+    let mask = (1 << expected) - 1;
+    hashbytes[num_fullbytes] = nextbyte & mask;
+    // interpret as field element, which is always below p
+    F::from_base_prime_field(F::BasePrimeField::from_le_bytes_mod_order(&hashbytes))
+}
+
+/// public keys are absorbed in their uncompressed serialization
+impl<F: Field, C: CanonicalSerialize, H: TranscriptHash, T: DomainSeparationTag>
+    ChallengeHasher<F, C> for DigestHasher<H, T>
+{
+    type Midstate = Transcript<H>;
+
+    fn midstate(com: &C) -> Self::Midstate {
+        let mut transcript = Transcript::new(T::DST);
+        transcript.append_serializable(b"pk", com);
+        transcript
+    }
+
+    fn challenge(
+        log_k: u32,
+        midstate: &Self::Midstate,
+        pid: u32,
+        i: u32,
+        lseed: &LotterySeed,
+    ) -> F {
+        // x = H(pk,pid,i,lseed)
+        let mut transcript = midstate.clone();
+        transcript.append_u32(b"pid", pid);
+        transcript.append_u32(b"i", i);
+        transcript.append_message(b"lseed", lseed);
+        let mut bytes = [0u8; 32];
+        transcript.challenge_bytes(b"x", &mut bytes);
+        challenge_from_bytes(log_k, &bytes)
+    }
+}
+
+/// challenge hasher based on the algebraic hash function Poseidon,
+/// which is cheap to evaluate inside of SNARK circuits
+pub struct PoseidonHasher<T: DomainSeparationTag = ChallDst> {
+    _t: PhantomData<T>,
+}
+
+/// parameters of Poseidon with rate 2 and capacity 1,
+/// derived deterministically from the field. Deriving them takes
+/// much longer than hashing, so with std they are derived only
+/// once per field and cached
+pub fn poseidon_config<F: PrimeField>() -> PoseidonConfig<F> {
+    #[cfg(feature = "std")]
+    {
+        use std::any::{Any, TypeId};
+        use std::collections::HashMap;
+        use std::sync::{Mutex, OnceLock};
+
+        type Configs = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
+        static CONFIGS: OnceLock<Mutex<Configs>> = OnceLock::new();
+        let mut configs = CONFIGS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        configs
+            .entry(TypeId::of::<F>())
+            .or_insert_with(|| Box::new(derive_poseidon_config::<F>()))
+            .downcast_ref::<PoseidonConfig<F>>()
+            .expect("Poseidon config cached for the wrong field")
+            .clone()
+    }
+    #[cfg(not(feature = "std"))]
+    derive_poseidon_config::<F>()
+}
+
+/// derives the parameters of Poseidon for the field using the Grain LFSR
+fn derive_poseidon_config<F: PrimeField>() -> PoseidonConfig<F> {
+    let full_rounds = 8;
+    let partial_rounds = 31;
+    let alpha = 17;
+    let rate = 2;
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(
+        F::MODULUS_BIT_SIZE as u64,
+        rate,
+        full_rounds,
+        partial_rounds,
+        0,
+    );
+    PoseidonConfig::new(
+        full_rounds as usize,
+        partial_rounds as usize,
+        alpha,
+        mds,
+        ark,
+        rate,
+        1,
+    )
+}
+
+/// public keys are absorbed in their uncompressed serialization
+impl<F: PrimeField + Absorb, C: CanonicalSerialize, T: DomainSeparationTag> ChallengeHasher<F, C>
+    for PoseidonHasher<T>
+{
+    type Midstate = PoseidonSponge<F>;
+
+    fn midstate(com: &C) -> Self::Midstate {
+        let mut pk_ser = Vec::new();
+        com.serialize_uncompressed(&mut pk_ser)
+            .expect("Failed to serialize public key in PoseidonHasher");
+        let mut sponge = PoseidonSponge::new(&poseidon_config::<F>());
+        sponge.absorb(&T::DST);
+        sponge.absorb(&pk_ser.as_slice());
+        sponge
+    }
+
+    fn challenge(
+        log_k: u32,
+        midstate: &Self::Midstate,
+        pid: u32,
+        i: u32,
+        lseed: &LotterySeed,
+    ) -> F {
+        // x = H(pk,pid,i,lseed)
+        let mut sponge = midstate.clone();
+        sponge.absorb(&pid);
+        sponge.absorb(&i);
+        sponge.absorb(&lseed.as_slice());
+        // interpret log_k squeezed bits as an integer
+        let bits = sponge.squeeze_bits(log_k as usize);
+        let x = bits
+            .iter()
+            .enumerate()
+            .fold(0u64, |x, (t, bit)| x | ((*bit as u64) << t));
+        F::from(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Fr;
+    use ark_ff::{BigInteger, PrimeField};
+
+    use super::{
+        derive_poseidon_config, poseidon_config, Blake2Hasher, Blake3Hasher, ChallengeHasher,
+        DomainSeparationTag, Keccak256Hasher, PoseidonHasher, Sha256Hasher, Sha3Hasher,
+    };
+    use crate::transcript::Sha256Transcript;
+
+    type F = Fr;
+    /// a public key that serializes to its bytes
+    type Key = [u8; 10];
+
+    struct OtherDst;
+
+    impl DomainSeparationTag for OtherDst {
+        const DST: &'static [u8] = b"Other//";
+    }
+
+    /// computes the challenge for a fixed key and lottery
+    fn challenge<H: ChallengeHasher<F, Key>>(log_k: u32) -> F {
+        challenge_for::<H>(log_k, 7)
+    }
+
+    /// computes the challenge of user pid for a fixed key and lottery
+    fn challenge_for<H: ChallengeHasher<F, Key>>(log_k: u32, pid: u32) -> F {
+        let midstate = H::midstate(b"public key");
+        H::challenge(log_k, &midstate, pid, 3, &[0x42; 32])
+    }
+
+    /// test that challenges have at most log_k bits
+    fn check_range<H: ChallengeHasher<F, Key>>() {
+        for log_k in [0, 1, 5, 8, 9, 20, 31] {
+            let x = challenge::<H>(log_k).into_bigint();
+            assert!(x.num_bits() <= log_k);
+        }
+        // with overwhelming probability, not all challenges are equal
+        assert_ne!(challenge_for::<H>(31, 7), challenge_for::<H>(31, 8));
+    }
+
+    #[test]
+    fn challenge_test_range() {
+        check_range::<Sha256Hasher>();
+        check_range::<Sha3Hasher>();
+        check_range::<Keccak256Hasher>();
+        check_range::<Blake2Hasher>();
+        check_range::<Blake3Hasher>();
+        check_range::<PoseidonHasher>();
+    }
+
+    /// test that the default hasher computes the first log_k
+    /// bits of a SHA-256 transcript with label "Chall//"
+    #[test]
+    fn challenge_test_sha256() {
+        let mut transcript = Sha256Transcript::new(b"Chall//");
+        transcript.append_message(b"pk", b"public key");
+        transcript.append_u32(b"pid", 7);
+        transcript.append_u32(b"i", 3);
+        transcript.append_message(b"lseed", &[0x42; 32]);
+        let mut bytes = [0u8; 32];
+        transcript.challenge_bytes(b"x", &mut bytes);
+        let expected = u16::from_le_bytes([bytes[0], bytes[1] & 0x0f]);
+        assert_eq!(challenge::<Sha256Hasher>(12), F::from(expected));
+    }
+
+    /// test that hash functions and tags are separated
+    #[test]
+    fn challenge_test_separation() {
+        let xs = [
+            challenge::<Sha256Hasher>(31),
+            challenge::<Sha256Hasher<OtherDst>>(31),
+            challenge::<Sha3Hasher>(31),
+            challenge::<Keccak256Hasher>(31),
+            challenge::<Blake2Hasher>(31),
+            challenge::<Blake3Hasher>(31),
+            challenge::<PoseidonHasher>(31),
+            challenge::<PoseidonHasher<OtherDst>>(31),
+        ];
+        for a in 0..xs.len() {
+            for b in a + 1..xs.len() {
+                assert_ne!(xs[a], xs[b]);
+            }
+        }
+    }
+
+    /// test that cached Poseidon parameters are those of their field
+    #[test]
+    fn challenge_test_poseidon_config() {
+        fn check<G: PrimeField>() {
+            let cached = poseidon_config::<G>();
+            let derived = derive_poseidon_config::<G>();
+            assert_eq!(cached.ark, derived.ark);
+            assert_eq!(cached.mds, derived.mds);
+            assert_eq!(poseidon_config::<G>().ark, derived.ark);
+        }
+        check::<F>();
+        check::<ark_bls12_381::Fq>();
+        check::<F>();
+    }
+}
@@ -0,0 +1,28 @@
+use super::vcbased::VCLotteryScheme;
+use crate::vectorcommitment::ipa::VcIPA;
+use ark_bls12_381::G1Projective;
+use ark_ec::Group;
+
+type F = <G1Projective as Group>::ScalarField;
+type VC = VcIPA<G1Projective>;
+
+/// Aggregatable lottery scheme with a transparent setup,
+/// instantiated with the IPA-based vector commitment
+/// over the group G1 of BLS12-381
+pub type IpaLottery = VCLotteryScheme<F, VC>;
+
+#[cfg(test)]
+mod tests {
+    use super::IpaLottery;
+    use crate::lotteryscheme::{_lottery_test_always_winning, _lottery_test_key_verify};
+
+    #[test]
+    fn ipa_lottery_test_key_verify() {
+        _lottery_test_key_verify::<IpaLottery>();
+    }
+
+    #[test]
+    fn ipa_lottery_test_always_winning() {
+        _lottery_test_always_winning::<IpaLottery>();
+    }
+}
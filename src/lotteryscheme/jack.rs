@@ -1,17 +1,11 @@
-use std::fs::{self, File};
-
-use super::{
-    vcbased::{Parameters, VCLotteryScheme},
-    LotteryScheme,
-};
-use crate::vectorcommitment::{
-    kzg::{all_openings, VcKZG},
-    VectorCommitmentScheme,
-};
+use super::{challenge::PoseidonHasher, vcbased::VCLotteryScheme, LotteryScheme};
+use crate::vectorcommitment::kzg::{all_openings, VcKZG};
 use ark_bls12_381::Bls12_381;
 use ark_ec::pairing::Pairing;
 use ark_poly::Radix2EvaluationDomain;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Write};
+
+#[cfg(feature = "std")]
+use super::paramstore::ParameterStore;
 
 type F = <Bls12_381 as Pairing>::ScalarField;
 type D = Radix2EvaluationDomain<F>;
@@ -21,44 +15,23 @@ type VC = VcKZG<Bls12_381, D>;
 /// instantiated with KZG over BLS12-381
 pub type Jack = VCLotteryScheme<F, VC>;
 
+/// Jackpot with challenges computed using Poseidon,
+/// which is cheap to evaluate inside of SNARK circuits
+pub type JackPoseidon = VCLotteryScheme<F, VC, PoseidonHasher>;
+
 /// function we use to generate system parameters for our benchmarks
-/// or read it from file rto avoid doing the setup over and over again
+/// or load them from the default parameter store to avoid
+/// doing the setup over and over again. As no parameters are
+/// pinned, this must not be used outside of benchmarks
+#[cfg(feature = "std")]
 pub fn get_jack_parameters<R: rand::Rng>(
     rng: &mut R,
     num_lotteries: usize,
     k: u32,
 ) -> <Jack as LotteryScheme>::Parameters {
-    let dir = "crs_precomputed/".to_string();
-    let path = format!("crs_precomputed/{}.crs", num_lotteries);
-
-    // check if we already have a file containing such a commitment key
-    let file = File::open(path.clone());
-    println!("[INFO] Looking for parameters in file.");
-    if let Ok(file) = file {
-        let ck = <VC as VectorCommitmentScheme<F>>::CommitmentKey::deserialize_compressed(&file)
-            .unwrap();
-        let log_k = u32::BITS - k.leading_zeros() - 1;
-        let par = Parameters {
-            ck,
-            num_lotteries,
-            k,
-            log_k,
-        };
-        println!("[INFO] Found parameters in file.");
-        return par;
-    }
-    println!("[INFO] Did not find parameters in file. Generating new ones.");
-    // otherwise, we generate it and write the commitment key to a file
-    let par = <Jack as LotteryScheme>::setup(rng, num_lotteries, k).unwrap();
-    // write it to a file
-    let mut ck_bytes = Vec::new();
-    par.ck.serialize_compressed(&mut ck_bytes).unwrap();
-
-    fs::create_dir_all(dir).expect("fail to create directory");
-    let mut file = File::create(path).expect("fail to create file");
-    file.write_all(&ck_bytes).expect("fail to write to file");
-
-    par
+    ParameterStore::default()
+        .load_or_setup(rng, num_lotteries, k)
+        .expect("Failed to set up parameters")
 }
 
 impl Jack {
@@ -72,9 +45,27 @@ impl Jack {
 
 #[cfg(test)]
 mod tests {
-    use crate::lotteryscheme::{_lottery_test_always_winning, _lottery_test_key_verify};
+    use crate::lotteryscheme::{
+        _lottery_test_always_winning, _lottery_test_constant_time, _lottery_test_duplicates,
+        _lottery_test_gen_from_seed, _lottery_test_key_verify, LotteryScheme,
+    };
+
+    use ark_bls12_381::G1Affine;
+    use ark_ec::AffineRepr;
+    use ark_ff::One;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use std::collections::HashSet;
+    use zeroize::Zeroize;
 
-    use super::Jack;
+    use super::{Jack, JackPoseidon, F, VC};
+    use crate::keyderivation::derive_child_seed;
+    use crate::keystore::KdfParams;
+    use crate::lotteryscheme::challenge::Sha256Hasher;
+    use crate::lotteryscheme::vcbased::{
+        get_challenge, CrossTicket, MergeableTicket, SecretKey, Ticket, VerifierContext,
+        VerifierParameters,
+    };
+    use crate::vectorcommitment::{CrossAggregation, VectorCommitmentScheme};
 
     #[test]
     fn jack_lottery_test_key_verify() {
@@ -85,4 +76,712 @@ mod tests {
     fn jack_lottery_test_always_winning() {
         _lottery_test_always_winning::<Jack>();
     }
+
+    #[test]
+    fn jack_poseidon_lottery_test_always_winning() {
+        _lottery_test_always_winning::<JackPoseidon>();
+    }
+
+    /// timing test, which is ignored as it depends on the machine
+    #[test]
+    #[ignore]
+    fn jack_lottery_test_constant_time() {
+        _lottery_test_constant_time::<Jack>();
+    }
+
+    /// test that users cannot be counted twice, even though the vector
+    /// commitment accepts such aggregates, and that aggregates are unique
+    /// and can be compared and deduplicated
+    #[test]
+    fn jack_lottery_test_duplicates() {
+        _lottery_test_duplicates::<Jack>();
+
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = 6;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let i = 2;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let key = |seed: u8| Jack::gen_from_seed(&par, &[seed; 32]);
+        let (pk0, sk0) = key(0);
+        let (pk1, sk1) = key(1);
+        let ticket = |pid, sk, pk| {
+            <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, sk, pk).unwrap()
+        };
+        let pks_dup = [key(0).0, key(0).0];
+        let coms = [&pk0.com, &pk0.com];
+
+        // the same ticket twice
+        let x0 = get_challenge::<F, VC, Sha256Hasher>(par.log_k, &pk0, 0, i, &lseed);
+        let t0 = ticket(0, &sk0, &pk0);
+        let opening = VC::aggregate(&par.ck, i, &[x0, x0], &coms, &[&t0.opening, &t0.opening]);
+        let forged = Ticket {
+            opening: opening.unwrap(),
+        };
+        assert!(VC::verify(&par.ck.vk, i, &[x0, x0], &coms, &forged.opening));
+        assert!(!<Jack as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &[0, 0],
+            &pks_dup,
+            &forged
+        ));
+
+        // the same key under two identifiers
+        let x1 = get_challenge::<F, VC, Sha256Hasher>(par.log_k, &pk0, 1, i, &lseed);
+        let t0_1 = ticket(1, &sk0, &pk0);
+        let opening = VC::aggregate(&par.ck, i, &[x0, x1], &coms, &[&t0.opening, &t0_1.opening]);
+        let forged = Ticket {
+            opening: opening.unwrap(),
+        };
+        assert!(VC::verify(&par.ck.vk, i, &[x0, x1], &coms, &forged.opening));
+        assert!(!<Jack as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &[0, 1],
+            &pks_dup,
+            &forged
+        ));
+
+        // the same ticket twice across lotteries
+        let i2 = i + 1;
+        let lseed2 = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i2);
+        let t0_2 = <Jack as LotteryScheme>::get_ticket(&par, i2, &lseed2, 0, &sk0, &pk0).unwrap();
+        let is = [i, i2, i];
+        let lseeds = [lseed, lseed2, lseed];
+        let pks_cross = [&pk0, &pk0, &pk0];
+        let tickets_cross = [&t0, &t0_2, &t0];
+        assert!(
+            Jack::aggregate_cross(&par, &is, &lseeds, &[0, 0, 0], &pks_cross, &tickets_cross)
+                .is_none()
+        );
+        let x0_2 = get_challenge::<F, VC, Sha256Hasher>(par.log_k, &pk0, 0, i2, &lseed2);
+        let opening = VC::aggregate_cross(
+            &par.ck,
+            &is,
+            &[x0, x0_2, x0],
+            &[&pk0.com, &pk0.com, &pk0.com],
+            &[&t0.opening, &t0_2.opening, &t0.opening],
+        );
+        let forged = CrossTicket {
+            opening: opening.unwrap(),
+        };
+        assert!(VC::verify_cross(
+            &par.ck.vk,
+            &is,
+            &[x0, x0_2, x0],
+            &[&pk0.com, &pk0.com, &pk0.com],
+            &forged.opening
+        ));
+        assert!(!Jack::verify_cross(
+            &par,
+            &is,
+            &lseeds,
+            &[0, 0, 0],
+            &pks_cross,
+            &forged
+        ));
+
+        // and the same key under two identifiers in one lottery
+        let tickets_cross = [&t0, &t0_2, &t0_1];
+        assert!(
+            Jack::aggregate_cross(&par, &is, &lseeds, &[0, 0, 1], &pks_cross, &tickets_cross)
+                .is_none()
+        );
+
+        // while the same user may win several lotteries
+        let cross = Jack::aggregate_cross(
+            &par,
+            &is[..2],
+            &lseeds[..2],
+            &[0, 0],
+            &pks_cross[..2],
+            &tickets_cross[..2],
+        );
+        assert!(Jack::verify_cross(
+            &par,
+            &is[..2],
+            &lseeds[..2],
+            &[0, 0],
+            &pks_cross[..2],
+            &cross.unwrap()
+        ));
+
+        // aggregating in any order gives the same ticket,
+        // which verifies for the users in any order
+        let pks = [key(0).0, key(1).0];
+        let pks_rev = [key(1).0, key(0).0];
+        let tickets = [ticket(0, &sk0, &pk0), ticket(1, &sk1, &pk1)];
+        let tickets_rev = [ticket(1, &sk1, &pk1), ticket(0, &sk0, &pk0)];
+        let agg = <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &[0, 1], &pks, &tickets);
+        let agg_rev =
+            <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &[1, 0], &pks_rev, &tickets_rev);
+        let agg = agg.unwrap();
+        let agg_rev = agg_rev.unwrap();
+        assert_eq!(agg, agg_rev);
+        assert_eq!(agg.to_bytes(), agg_rev.to_bytes());
+        assert_ne!(agg, tickets[0]);
+
+        // identical proofs are deduplicated, and
+        // tickets are read back from their encoding
+        let proofs: HashSet<_> = [agg_rev, tickets_rev.into_iter().next().unwrap()]
+            .into_iter()
+            .chain(tickets)
+            .collect();
+        assert_eq!(proofs.len(), 3);
+        assert!(proofs.contains(&agg));
+        let bytes = agg.to_bytes();
+        assert_eq!(
+            Ticket::deserialize_compressed(bytes.as_slice()).unwrap(),
+            agg
+        );
+        assert!(<Jack as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &[1, 0],
+            &pks_rev,
+            &agg
+        ));
+    }
+
+    /// test that invalid tickets are found and
+    /// excluded when aggregating with checks
+    #[test]
+    fn jack_lottery_test_aggregate_checked() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = 6;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let i = 1;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let pids = [4, 0, 3, 1, 2];
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        for pid in pids {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            // users 3 and 1 send tickets for the wrong lottery
+            let j = if pid % 2 == 1 { i + 1 } else { i };
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, j, &lseed, pid, &sk, &pk);
+            pks.push(pk);
+            tickets.push(ticket.unwrap());
+        }
+        // aggregate does not notice invalid tickets
+        assert!(
+            <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets).is_some()
+        );
+        let checked =
+            Jack::aggregate_checked(&mut rng, &par, i, &lseed, &pids[1..], &pks, &tickets);
+        assert!(checked.is_none());
+
+        // the aggregate of the others verifies
+        let checked =
+            Jack::aggregate_checked(&mut rng, &par, i, &lseed, &pids, &pks, &tickets).unwrap();
+        assert_eq!(checked.invalid, vec![1, 3]);
+        assert_eq!(checked.pids, vec![0, 2, 4]);
+        let valid_pks = [pks.remove(1), pks.remove(3), pks.remove(0)];
+        let ticket = checked.ticket.unwrap();
+        assert!(<Jack as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &checked.pids,
+            &valid_pks,
+            &ticket
+        ));
+
+        // without invalid tickets, this is the same as aggregate
+        let valid_pids = [0, 2, 4];
+        let valid_tickets = [tickets.remove(1), tickets.remove(3), tickets.remove(0)];
+        let checked = Jack::aggregate_checked(
+            &mut rng,
+            &par,
+            i,
+            &lseed,
+            &valid_pids,
+            &valid_pks,
+            &valid_tickets,
+        );
+        let checked = checked.unwrap();
+        assert!(checked.invalid.is_empty());
+        let agg = <Jack as LotteryScheme>::aggregate(
+            &par,
+            i,
+            &lseed,
+            &valid_pids,
+            &valid_pks,
+            &valid_tickets,
+        );
+        assert_eq!(checked.ticket, agg);
+
+        // and if all tickets are invalid, there is no aggregate
+        let checked = Jack::aggregate_checked(&mut rng, &par, i, &lseed, &[3, 1], &pks, &tickets);
+        let checked = checked.unwrap();
+        assert!(checked.ticket.is_none() && checked.pids.is_empty());
+        assert_eq!(checked.invalid, vec![1, 3]);
+    }
+
+    /// test that keys and tickets can be verified with the
+    /// verifier parameters alone, which are much smaller
+    #[test]
+    fn jack_lottery_test_verifier_parameters() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = 6;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let vpar = par.verifier_parameters();
+        assert!(vpar.compressed_size() < par.ck.vk.compressed_size());
+        assert!(vpar.compressed_size() < par.ck.compressed_size());
+
+        // light clients receive the serialized verifier parameters
+        let mut bytes = Vec::new();
+        vpar.serialize_compressed(&mut bytes).unwrap();
+        let vpar = VerifierParameters::<F, VC>::deserialize_compressed(bytes.as_slice()).unwrap();
+        assert_eq!(vpar.vk, par.ck.vk.light());
+        assert_eq!(vpar.log_k, par.log_k);
+
+        let i = 2;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let pids = [1, 0];
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        for pid in pids {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            assert!(Jack::verify_key_light(&vpar, &pk));
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, &sk, &pk);
+            pks.push(pk);
+            tickets.push(ticket.unwrap());
+        }
+        let ticket =
+            <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets).unwrap();
+        assert!(Jack::verify_light(&vpar, i, &lseed, &pids, &pks, &ticket));
+        assert!(!Jack::verify_light(
+            &vpar,
+            i + 1,
+            &lseed,
+            &pids,
+            &pks,
+            &ticket
+        ));
+        assert!(!Jack::verify_light(
+            &vpar,
+            i,
+            &lseed,
+            &pids[..1],
+            &pks[..1],
+            &ticket
+        ));
+        let ticket = &tickets[0];
+        assert!(Jack::verify_light(
+            &vpar,
+            i,
+            &lseed,
+            &pids[..1],
+            &pks[..1],
+            ticket
+        ));
+        let out_of_range = num_lotteries as u32;
+        assert!(!Jack::verify_light(
+            &vpar,
+            out_of_range,
+            &lseed,
+            &pids[..1],
+            &pks[..1],
+            ticket
+        ));
+    }
+
+    /// test that inputs out of range and malformed
+    /// states are rejected and do not panic
+    #[test]
+    fn jack_lottery_test_malformed_inputs() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = 6;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, 1).unwrap();
+        let (pk, mut sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        let lseed = [0x00; 32];
+        for i in [num_lotteries as u32, u32::MAX] {
+            assert!(!<Jack as LotteryScheme>::participate(
+                &par, i, &lseed, 0, &sk, &pk
+            ));
+            assert!(<Jack as LotteryScheme>::get_ticket(&par, i, &lseed, 0, &sk, &pk).is_none());
+        }
+        let ticket = <Jack as LotteryScheme>::get_ticket(&par, 0, &lseed, 0, &sk, &pk).unwrap();
+        let pks = [pk];
+        assert!(<Jack as LotteryScheme>::aggregate(&par, 0, &lseed, &[0, 1], &pks, &[]).is_none());
+        assert!(!<Jack as LotteryScheme>::verify(
+            &par,
+            num_lotteries as u32,
+            &lseed,
+            &[0],
+            &pks,
+            &ticket
+        ));
+
+        // states that do not match the parameters
+        Jack::fk_preprocess(&par, &mut sk);
+        sk.state.precomputed_v.as_mut().unwrap().truncate(1);
+        assert!(<Jack as LotteryScheme>::get_ticket(&par, 1, &lseed, 0, &sk, &pks[0]).is_none());
+        sk.state.evals.truncate(1);
+        assert!(<Jack as LotteryScheme>::get_ticket(&par, 0, &lseed, 0, &sk, &pks[0]).is_none());
+    }
+
+    /// test that keys generated from a seed, including
+    /// the masking evaluations, are deterministic
+    #[test]
+    fn jack_lottery_test_gen_from_seed() {
+        _lottery_test_gen_from_seed::<Jack>();
+        let mut rng = ark_std::rand::thread_rng();
+        let par = <Jack as LotteryScheme>::setup(&mut rng, 6, 1024).unwrap();
+        let seed = derive_child_seed(&[0x42; 32], 1);
+        let (_pk0, sk0) = <Jack as LotteryScheme>::gen_from_seed(&par, &seed);
+        let (_pk1, sk1) = <Jack as LotteryScheme>::gen_from_seed(&par, &seed);
+        assert_eq!(sk0.v, sk1.v);
+        assert_eq!(sk0.state.evals, sk1.state.evals);
+    }
+
+    /// test that secret keys do not reveal their content
+    /// when formatted, and that they can be zeroized
+    #[test]
+    fn jack_lottery_test_secret_key() {
+        let mut rng = ark_std::rand::thread_rng();
+        let par = <Jack as LotteryScheme>::setup(&mut rng, 6, 1024).unwrap();
+        let (_pk, mut sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        assert_eq!(format!("{:?}", sk), "SecretKey(<redacted>)");
+        assert_eq!(sk.lock.is_locked(), cfg!(feature = "mlock"));
+        assert_eq!(sk.state.lock.is_locked(), cfg!(feature = "mlock"));
+        sk.zeroize();
+        assert!(sk.v.is_empty());
+        assert!(sk.state.evals.is_empty());
+    }
+
+    /// test that secret keys, including precomputed openings,
+    /// can be stored encrypted and only loaded with the password
+    #[test]
+    fn jack_lottery_test_keystore() {
+        let mut rng = ark_std::rand::thread_rng();
+        let par = <Jack as LotteryScheme>::setup(&mut rng, 6, 1024).unwrap();
+        let (_pk, mut sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        Jack::fk_preprocess(&par, &mut sk);
+        let params = KdfParams {
+            n: 1 << 10,
+            ..KdfParams::default()
+        };
+        let path = std::env::temp_dir().join(format!("jack-sk-{}.json", std::process::id()));
+        assert!(sk.save_encrypted(&mut rng, &path, "password", &params));
+        assert!(SecretKey::<F, VC>::load_encrypted(&path, "passw0rd").is_none());
+        let loaded = SecretKey::<F, VC>::load_encrypted(&path, "password").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.v, sk.v);
+        assert_eq!(loaded.state.evals, sk.state.evals);
+        assert_eq!(loaded.state.precomputed_v, sk.state.precomputed_v);
+        assert_eq!(loaded.lock.is_locked(), cfg!(feature = "mlock"));
+    }
+
+    /// test that tickets of different lotteries
+    /// can be aggregated into a single ticket
+    #[test]
+    fn jack_lottery_test_cross_aggregate() {
+        let mut rng = ark_std::rand::thread_rng();
+        // everyone wins with probability 1
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        for _ in 0..3 {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+        }
+
+        // every user wins every lottery, so we collect
+        // tickets for all users and lotteries
+        let mut is = Vec::new();
+        let mut lseeds = Vec::new();
+        let mut pids = Vec::new();
+        let mut pks_r = Vec::new();
+        let mut tickets = Vec::new();
+        for i in 0..num_lotteries as u32 {
+            let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+            for j in 0..pks.len() {
+                let pid = j as u32;
+                assert!(<Jack as LotteryScheme>::participate(
+                    &par, i, &lseed, pid, &sks[j], &pks[j]
+                ));
+                let ticket =
+                    <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, &sks[j], &pks[j])
+                        .unwrap();
+                is.push(i);
+                lseeds.push(lseed);
+                pids.push(pid);
+                pks_r.push(&pks[j]);
+                tickets.push(ticket);
+            }
+        }
+        let tickets_r: Vec<_> = tickets.iter().collect();
+
+        // aggregate all of them and verify
+        let ticket = Jack::aggregate_cross(&par, &is, &lseeds, &pids, &pks_r, &tickets_r);
+        assert!(ticket.is_some());
+        let ticket = ticket.unwrap();
+        assert!(Jack::verify_cross(
+            &par, &is, &lseeds, &pids, &pks_r, &ticket
+        ));
+
+        // the ticket should not verify for a different assignment of lotteries
+        let mut is_wrong = is.clone();
+        is_wrong.swap(0, is.len() - 1);
+        assert!(!Jack::verify_cross(
+            &par, &is_wrong, &lseeds, &pids, &pks_r, &ticket
+        ));
+    }
+
+    /// test that tickets can be aggregated one by one
+    /// and that partial aggregates can be merged
+    #[test]
+    fn jack_lottery_test_merge() {
+        let mut rng = ark_std::rand::thread_rng();
+        // everyone wins with probability 1
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let num_users = 4;
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        let i = 1;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        for j in 0..num_users {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            let ticket =
+                <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, j as u32, &sk, &pk).unwrap();
+            pks.push(pk);
+            tickets.push(ticket);
+        }
+        let pids: Vec<u32> = (0..num_users as u32).collect();
+        let pks_r: Vec<_> = pks.iter().collect();
+        let tickets_r: Vec<_> = tickets.iter().collect();
+
+        // aggregate tickets one at a time, as a gossiping aggregator would
+        let mut agg =
+            Jack::aggregate_mergeable(&par, i, &lseed, &pids[..1], &pks_r[..1], &tickets_r[..1])
+                .unwrap();
+        for j in 1..num_users {
+            let single = Jack::aggregate_mergeable(
+                &par,
+                i,
+                &lseed,
+                &pids[j..j + 1],
+                &pks_r[j..j + 1],
+                &tickets_r[j..j + 1],
+            )
+            .unwrap();
+            agg = Jack::merge(&par, &agg, &single).unwrap();
+            assert!(Jack::verify_mergeable(
+                &par,
+                i,
+                &lseed,
+                &pids[..=j],
+                &pks_r[..=j],
+                &agg
+            ));
+        }
+
+        // merged aggregates are kept apart, so aggregating
+        // all at once results in a shorter opening
+        let all = Jack::aggregate_mergeable(&par, i, &lseed, &pids, &pks_r, &tickets_r).unwrap();
+        let mut agg_ser = Vec::new();
+        let mut all_ser = Vec::new();
+        agg.opening.serialize_compressed(&mut agg_ser).unwrap();
+        all.opening.serialize_compressed(&mut all_ser).unwrap();
+        assert_eq!(agg.pids, all.pids);
+        assert_eq!(agg.opening.parts.len(), num_users);
+        assert_eq!(all.opening.parts.len(), 1);
+        assert!(all_ser.len() < agg_ser.len());
+        assert!(Jack::verify_mergeable(&par, i, &lseed, &pids, &pks_r, &all));
+
+        // the order in which users are given does not matter
+        let pids_rev: Vec<_> = pids.iter().rev().copied().collect();
+        let pks_rev: Vec<_> = pks_r.iter().rev().copied().collect();
+        assert!(Jack::verify_mergeable(
+            &par, i, &lseed, &pids_rev, &pks_rev, &agg
+        ));
+    }
+
+    /// test that merging can not be abused to
+    /// count winners twice or to add users that did not win
+    #[test]
+    fn jack_lottery_test_merge_rogue_tickets() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let i = 2;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        for j in 0..3 {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, j, &sk, &pk).unwrap();
+            pks.push(pk);
+            tickets.push(ticket);
+        }
+        let agg01 = Jack::aggregate_mergeable(
+            &par,
+            i,
+            &lseed,
+            &[0, 1],
+            &[&pks[0], &pks[1]],
+            &[&tickets[0], &tickets[1]],
+        )
+        .unwrap();
+        let agg1 =
+            Jack::aggregate_mergeable(&par, i, &lseed, &[1], &[&pks[1]], &[&tickets[1]]).unwrap();
+
+        // overlapping aggregates can not be merged
+        assert!(Jack::merge(&par, &agg01, &agg1).is_none());
+        assert!(Jack::merge(&par, &agg01, &agg01).is_none());
+        // duplicates are not accepted when aggregating
+        assert!(Jack::aggregate_mergeable(
+            &par,
+            i,
+            &lseed,
+            &[1, 1],
+            &[&pks[1], &pks[1]],
+            &[&tickets[1], &tickets[1]],
+        )
+        .is_none());
+
+        // user 1 submits its own ticket on behalf of user 2
+        let rogue =
+            Jack::aggregate_mergeable(&par, i, &lseed, &[2], &[&pks[2]], &[&tickets[1]]).unwrap();
+        assert!(!Jack::verify_mergeable(
+            &par,
+            i,
+            &lseed,
+            &[2],
+            &[&pks[2]],
+            &rogue
+        ));
+        let merged = Jack::merge(&par, &agg01, &rogue).unwrap();
+        assert!(!Jack::verify_mergeable(
+            &par,
+            i,
+            &lseed,
+            &[0, 1, 2],
+            &[&pks[0], &pks[1], &pks[2]],
+            &merged
+        ));
+
+        // an aggregate does not verify for more users than it contains
+        let relabeled = MergeableTicket::<F, VC> {
+            pids: vec![0, 1, 2],
+            opening: agg01.opening,
+        };
+        assert!(!Jack::verify_mergeable(
+            &par,
+            i,
+            &lseed,
+            &[0, 1, 2],
+            &[&pks[0], &pks[1], &pks[2]],
+            &relabeled
+        ));
+
+        // a ticket for a user that did not win is rejected
+        let k = 1 << 16;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        let mut lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        while <Jack as LotteryScheme>::participate(&par, i, &lseed, 0, &sk, &pk) {
+            lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        }
+        let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, 0, &sk, &pk).unwrap();
+        let agg = Jack::aggregate_mergeable(&par, i, &lseed, &[0], &[&pk], &[&ticket]).unwrap();
+        assert!(!Jack::verify_mergeable(&par, i, &lseed, &[0], &[&pk], &agg));
+    }
+
+    /// test that verification with cached keys
+    /// behaves exactly like plain verification
+    #[test]
+    fn jack_lottery_test_verify_with_context() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let i = 3;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut ctx = VerifierContext::new();
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        let pids = vec![3, 7, 11];
+        for pid in &pids {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            let ticket =
+                <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, *pid, &sk, &pk).unwrap();
+            tickets.push(ticket);
+            pks.push(pk);
+        }
+        let ticket =
+            <Jack as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets).unwrap();
+        // invalid keys are not registered
+        let (mut pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        pk.com.y0 += F::one();
+        assert!(!ctx.register(&par, pids[0], pk));
+        let (mut pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        pk.com.com_kzg = G1Affine::zero();
+        assert!(!ctx.register(&par, pids[0], pk));
+        assert!(ctx.is_empty());
+        for (pid, pk) in pids.iter().zip(pks.drain(..)) {
+            assert!(ctx.register(&par, *pid, pk));
+        }
+        assert_eq!(ctx.len(), 3);
+
+        assert!(Jack::verify_with_context(
+            &par, &ctx, i, &lseed, &pids, &ticket
+        ));
+        // wrong lottery, wrong users, and unknown users are rejected
+        assert!(!Jack::verify_with_context(
+            &par,
+            &ctx,
+            i + 1,
+            &lseed,
+            &pids,
+            &ticket
+        ));
+        assert!(!Jack::verify_with_context(
+            &par,
+            &ctx,
+            i,
+            &lseed,
+            &pids[1..],
+            &ticket
+        ));
+        assert!(!Jack::verify_with_context(
+            &par,
+            &ctx,
+            i,
+            &lseed,
+            &[3, 7, 12],
+            &ticket
+        ));
+        // the order of users does not matter, but they can only be counted once
+        assert!(Jack::verify_with_context(
+            &par,
+            &ctx,
+            i,
+            &lseed,
+            &[11, 3, 7],
+            &ticket
+        ));
+        assert!(!Jack::verify_with_context(
+            &par,
+            &ctx,
+            i,
+            &lseed,
+            &[3, 7, 11, 11],
+            &ticket
+        ));
+    }
 }
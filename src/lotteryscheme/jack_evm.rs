@@ -0,0 +1,646 @@
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{BigInteger, Field, PrimeField, Zero};
+use ark_poly::Radix2EvaluationDomain;
+use sha3::{Digest, Keccak256};
+use std::marker::PhantomData;
+use std::ops::Mul;
+
+use super::challenge::{ChallDst, ChallengeHasher, DomainSeparationTag};
+use super::vcbased::{LotterySeed, Parameters, PublicKey, Ticket, VCLotteryScheme};
+use crate::vectorcommitment::kzg::{Commitment, Keccak256KzgHasher, KzgHasher, VcKZG};
+
+type D = Radix2EvaluationDomain<Fr>;
+type VC = VcKZG<Bn254, D, EvmKzgHasher>;
+
+/// Jackpot instantiated with KZG over BN254, where all hashes
+/// of the verifier are computed with Keccak-256 over the encoding
+/// expected by the Ethereum precompiles, so that tickets can
+/// be verified by the generated Solidity verifier
+pub type JackEvm = VCLotteryScheme<Fr, VC, EvmHasher>;
+
+/// length of an encoded field element
+pub const FIELD_LEN: usize = 32;
+/// length of an encoded G1 element
+pub const G1_LEN: usize = 2 * FIELD_LEN;
+/// length of an encoded G2 element
+pub const G2_LEN: usize = 4 * FIELD_LEN;
+/// length of an encoded public key: com_kzg, y0, tau0.hat_y, tau0.v
+pub const PUBLIC_KEY_LEN: usize = 2 * G1_LEN + 2 * FIELD_LEN;
+/// length of an encoded ticket: hat_y, v
+pub const TICKET_LEN: usize = FIELD_LEN + G1_LEN;
+
+/// domain separation tag for aggregation with Keccak-256
+pub const KECCAK_AGG_DST: &[u8] = b"KZG-AGG-EVM//";
+
+/// Encodes a field element as 32 bytes big-endian
+pub fn encode_field<F: PrimeField>(x: &F, out: &mut Vec<u8>) {
+    let bytes = x.into_bigint().to_bytes_be();
+    out.resize(out.len() + FIELD_LEN - bytes.len(), 0u8);
+    out.extend(bytes);
+}
+
+/// Decodes a field element from 32 bytes big-endian,
+/// rejecting non-canonical encodings
+pub fn decode_field<F: PrimeField>(bytes: &[u8]) -> Option<F> {
+    if bytes.len() != FIELD_LEN {
+        return None;
+    }
+    let x = F::from_be_bytes_mod_order(bytes);
+    let mut canonical = Vec::with_capacity(FIELD_LEN);
+    encode_field(&x, &mut canonical);
+    if canonical != bytes {
+        return None;
+    }
+    Some(x)
+}
+
+/// Encodes a G1 element as (x,y), as expected by ecAdd,
+/// ecMul and ecPairing. The identity is encoded as (0,0)
+pub fn encode_g1(p: &G1Affine, out: &mut Vec<u8>) {
+    let (x, y) = p.xy().unwrap_or((&Fq::ZERO, &Fq::ZERO));
+    encode_field(x, out);
+    encode_field(y, out);
+}
+
+/// Encodes a G2 element as (x.c1,x.c0,y.c1,y.c0), as expected
+/// by ecPairing. The identity is encoded as (0,0,0,0)
+pub fn encode_g2(p: &G2Affine, out: &mut Vec<u8>) {
+    let (x, y) = p.xy().unwrap_or((&Fq2::ZERO, &Fq2::ZERO));
+    encode_field(&x.c1, out);
+    encode_field(&x.c0, out);
+    encode_field(&y.c1, out);
+    encode_field(&y.c0, out);
+}
+
+/// Decodes a G1 element, rejecting points that are not on the curve
+pub fn decode_g1(bytes: &[u8]) -> Option<G1Affine> {
+    if bytes.len() != G1_LEN {
+        return None;
+    }
+    let x = decode_field::<Fq>(&bytes[..FIELD_LEN])?;
+    let y = decode_field::<Fq>(&bytes[FIELD_LEN..])?;
+    if x.is_zero() && y.is_zero() {
+        return Some(G1Affine::zero());
+    }
+    let p = G1Affine::new_unchecked(x, y);
+    // G1 has cofactor 1, so being on the curve is enough
+    if !p.is_on_curve() {
+        return None;
+    }
+    Some(p)
+}
+
+/// Decodes a G2 element, rejecting points that are
+/// not on the curve or not in the prime order subgroup
+pub fn decode_g2(bytes: &[u8]) -> Option<G2Affine> {
+    if bytes.len() != G2_LEN {
+        return None;
+    }
+    let mut fs = Vec::with_capacity(4);
+    for t in 0..4 {
+        fs.push(decode_field::<Fq>(
+            &bytes[t * FIELD_LEN..(t + 1) * FIELD_LEN],
+        )?);
+    }
+    let x = Fq2::new(fs[1], fs[0]);
+    let y = Fq2::new(fs[3], fs[2]);
+    if x.is_zero() && y.is_zero() {
+        return Some(G2Affine::zero());
+    }
+    let p = G2Affine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return None;
+    }
+    Some(p)
+}
+
+/// Encodes a commitment (i.e., a public key) as
+/// com_kzg, y0, tau0.hat_y, tau0.v
+pub fn encode_commitment(com: &Commitment<Bn254>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(PUBLIC_KEY_LEN);
+    encode_g1(&com.com_kzg, &mut out);
+    encode_field(&com.y0, &mut out);
+    encode_field(&com.tau0.hat_y, &mut out);
+    encode_g1(&com.tau0.v, &mut out);
+    out
+}
+
+/// Encodes a public key of JackEvm
+pub fn encode_public_key(pk: &PublicKey<Fr, VC>) -> Vec<u8> {
+    encode_commitment(&pk.com)
+}
+
+/// Encodes a ticket of JackEvm as hat_y, v
+pub fn encode_ticket(ticket: &Ticket<Fr, VC>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(TICKET_LEN);
+    encode_field(&ticket.opening.hat_y, &mut out);
+    encode_g1(&ticket.opening.v, &mut out);
+    out
+}
+
+/// challenge hasher computing x = Keccak256(DST,pk,pid,i,lseed) mod 2^log_k,
+/// where pk is encoded as in encode_public_key and pid, i as 4 bytes
+/// big-endian. This matches abi.encodePacked in Solidity
+pub struct EvmHasher<T: DomainSeparationTag = ChallDst> {
+    _t: PhantomData<T>,
+}
+
+impl<T: DomainSeparationTag> ChallengeHasher<Fr, Commitment<Bn254>> for EvmHasher<T> {
+    type Midstate = Keccak256;
+
+    fn midstate(com: &Commitment<Bn254>) -> Self::Midstate {
+        let mut hasher = Keccak256::new_with_prefix(T::DST);
+        hasher.update(encode_commitment(com));
+        hasher
+    }
+
+    fn challenge(
+        log_k: u32,
+        midstate: &Self::Midstate,
+        pid: u32,
+        i: u32,
+        lseed: &LotterySeed,
+    ) -> Fr {
+        let mut hasher = midstate.clone();
+        hasher.update(pid.to_be_bytes());
+        hasher.update(i.to_be_bytes());
+        hasher.update(lseed);
+        let digest = hasher.finalize();
+        challenge_from_digest(log_k, &digest)
+    }
+}
+
+/// interprets the digest as a big-endian integer
+/// and takes it modulo 2^log_k
+fn challenge_from_digest(log_k: u32, digest: &[u8]) -> Fr {
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&digest[digest.len() - 8..]);
+    let low = u64::from_be_bytes(low);
+    let mask = if log_k >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << log_k) - 1
+    };
+    Fr::from(low & mask)
+}
+
+/// hashing based on Keccak-256. Coefficients are the powers of
+/// chi = Keccak256(DST,i,(mi[j],com[j])_j) mod r, where only com_kzg
+/// of each commitment is hashed, as the verifier does not use the rest.
+/// The point z0 is computed with the Keccak-256 transcript
+pub struct EvmKzgHasher;
+
+impl KzgHasher<Bn254> for EvmKzgHasher {
+    fn z0(com_kzg: &G1Affine) -> Fr {
+        <Keccak256KzgHasher as KzgHasher<Bn254>>::z0(com_kzg)
+    }
+
+    fn coefficients(i: u32, mis: &[Fr], coms: &[&Commitment<Bn254>]) -> Vec<Fr> {
+        let mut input = Vec::with_capacity(mis.len() * (FIELD_LEN + G1_LEN));
+        for j in 0..mis.len() {
+            encode_field(&mis[j], &mut input);
+            encode_g1(&coms[j].com_kzg, &mut input);
+        }
+        let chi = get_chi_evm(i, &input);
+        let mut cs = Vec::with_capacity(mis.len());
+        let mut c = Fr::from(1u64);
+        for _ in 0..mis.len() {
+            cs.push(c);
+            c *= chi;
+        }
+        cs
+    }
+}
+
+/// computes chi = Keccak256(DST,i,input) mod r
+fn get_chi_evm(i: u32, input: &[u8]) -> Fr {
+    let mut hasher = Keccak256::new_with_prefix(KECCAK_AGG_DST);
+    hasher.update(i.to_be_bytes());
+    hasher.update(input);
+    Fr::from_be_bytes_mod_order(&hasher.finalize())
+}
+
+/// the data the Solidity verifier is deployed with, in the
+/// order of the constructor arguments of the contract
+pub struct EvmVerifierKey {
+    /// generator g1 = u[0]
+    pub g1: Vec<u8>,
+    /// hiding generator h = hat_u[0]
+    pub h: Vec<u8>,
+    /// generator g2
+    pub g2: Vec<u8>,
+    /// d[i] = g2^{alpha - zi} for all lotteries i
+    pub d: Vec<Vec<u8>>,
+    /// number of bits of challenges
+    pub log_k: u32,
+}
+
+/// Encodes the parameters needed to verify tickets
+pub fn encode_verifier_key(par: &Parameters<Fr, VC>) -> EvmVerifierKey {
+    let mut g1 = Vec::new();
+    let mut h = Vec::new();
+    let mut g2 = Vec::new();
+    let vk = &par.ck.vk;
+    encode_g1(&vk.g1, &mut g1);
+    encode_g1(&vk.h, &mut h);
+    encode_g2(&vk.g2, &mut g2);
+    let d = (0..par.num_lotteries)
+        .map(|i| {
+            let mut out = Vec::new();
+            encode_g2(&vk.d(i), &mut out);
+            out
+        })
+        .collect();
+    EvmVerifierKey {
+        g1,
+        h,
+        g2,
+        d,
+        log_k: par.log_k,
+    }
+}
+
+/// Reference verifier for encoded tickets. It verifies the encoded ticket
+/// for users with identifiers pids[j] and encoded public keys pks[j] in the
+/// ith lottery with seed lseed, following the Solidity verifier step by step.
+/// Identifiers must be strictly increasing, which is the order of aggregation,
+/// so that no user is counted twice. The contract that registers the keys
+/// must ensure that every key is registered for one identifier only
+pub fn verify_encoded(
+    vk: &EvmVerifierKey,
+    i: u32,
+    lseed: &LotterySeed,
+    pids: &[u32],
+    pks: &[&[u8]],
+    ticket: &[u8],
+) -> bool {
+    let l = pids.len();
+    if l == 0 || l != pks.len() || i as usize >= vk.d.len() || ticket.len() != TICKET_LEN {
+        return false;
+    }
+
+    // compute challenges x[j] = Keccak256(DST,pk[j],pid[j],i,lseed) mod 2^log_k
+    // and the input (x[j],com[j])_j for the aggregation challenge chi
+    let mut xs = Vec::with_capacity(l);
+    let mut agg_input = Vec::with_capacity(l * (FIELD_LEN + G1_LEN));
+    for j in 0..l {
+        if pks[j].len() != PUBLIC_KEY_LEN || (j > 0 && pids[j] <= pids[j - 1]) {
+            return false;
+        }
+        let mut hasher = Keccak256::new_with_prefix(ChallDst::DST);
+        hasher.update(pks[j]);
+        hasher.update(pids[j].to_be_bytes());
+        hasher.update(i.to_be_bytes());
+        hasher.update(lseed);
+        let x = challenge_from_digest(vk.log_k, &hasher.finalize());
+        encode_field(&x, &mut agg_input);
+        agg_input.extend_from_slice(&pks[j][..G1_LEN]);
+        xs.push(x);
+    }
+    let chi = get_chi_evm(i, &agg_input);
+
+    // com = sum_j chi^j com[j], mi = sum_j chi^j x[j]
+    let mut com = G1Affine::zero().into_group();
+    let mut mi = Fr::zero();
+    let mut c = Fr::from(1u64);
+    for j in 0..l {
+        let com_j = match decode_g1(&pks[j][..G1_LEN]) {
+            Some(com_j) => com_j,
+            None => return false,
+        };
+        com += com_j.mul(c);
+        mi += xs[j] * c;
+        c *= chi;
+    }
+
+    // decode ticket and key
+    let hat_y = decode_field::<Fr>(&ticket[..FIELD_LEN]);
+    let v = decode_g1(&ticket[FIELD_LEN..]);
+    let g1 = decode_g1(&vk.g1);
+    let h = decode_g1(&vk.h);
+    let g2 = decode_g2(&vk.g2);
+    let d = decode_g2(&vk.d[i as usize]);
+    let (hat_y, v, g1, h, g2, d) = match (hat_y, v, g1, h, g2, d) {
+        (Some(hat_y), Some(v), Some(g1), Some(h), Some(g2), Some(d)) => (hat_y, v, g1, h, g2, d),
+        _ => return false,
+    };
+
+    // check e(-(com*g1^{-mi}*h^{-hat_y}),g2) * e(v,d[i]) == 1
+    let neg_lhs = g1.mul(mi) + h.mul(hat_y) - com;
+    let left = [neg_lhs.into_affine(), v];
+    let right = [g2, d];
+    Bn254::multi_pairing(left, right).is_zero()
+}
+
+/// Outputs the source code of the Solidity verifier for JackEvm
+pub fn solidity_verifier() -> String {
+    SOLIDITY_TEMPLATE
+        .replace("{FQ_MODULUS}", &Fq::MODULUS.to_string())
+        .replace("{FR_MODULUS}", &Fr::MODULUS.to_string())
+        .replace("{CHALL_DST}", std::str::from_utf8(ChallDst::DST).unwrap())
+        .replace("{AGG_DST}", std::str::from_utf8(KECCAK_AGG_DST).unwrap())
+        .replace("{PUBLIC_KEY_LEN}", &PUBLIC_KEY_LEN.to_string())
+        .replace("{TICKET_LEN}", &TICKET_LEN.to_string())
+}
+
+const SOLIDITY_TEMPLATE: &str = r#"// SPDX-License-Identifier: MIT
+// This file is generated by jackpot::lotteryscheme::jack_evm::solidity_verifier
+pragma solidity ^0.8.19;
+
+/// Verifier for aggregated tickets of the lottery scheme JackEvm
+contract JackVerifier {
+    uint256 constant P = {FQ_MODULUS};
+    uint256 constant R = {FR_MODULUS};
+    bytes constant CHALL_DST = "{CHALL_DST}";
+    bytes constant AGG_DST = "{AGG_DST}";
+
+    uint256[2] public g1;
+    uint256[2] public h;
+    uint256[4] public g2;
+    uint256[4][] public d;
+    uint32 public logK;
+
+    constructor(
+        uint256[2] memory g1_,
+        uint256[2] memory h_,
+        uint256[4] memory g2_,
+        uint256[4][] memory d_,
+        uint32 logK_
+    ) {
+        g1 = g1_;
+        h = h_;
+        g2 = g2_;
+        for (uint256 t = 0; t < d_.length; t++) {
+            d.push(d_[t]);
+        }
+        logK = logK_;
+    }
+
+    /// challenge x = Keccak256(DST,pk,pid,i,lseed) mod 2^logK
+    function challenge(bytes calldata pk, uint32 pid, uint32 i, bytes32 lseed)
+        public
+        view
+        returns (uint256)
+    {
+        uint256 digest = uint256(keccak256(abi.encodePacked(CHALL_DST, pk, pid, i, lseed)));
+        return digest & ((uint256(1) << logK) - 1);
+    }
+
+    function ecAdd(uint256[2] memory a, uint256[2] memory b)
+        internal
+        view
+        returns (uint256[2] memory c)
+    {
+        uint256[4] memory input = [a[0], a[1], b[0], b[1]];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x06, input, 0x80, c, 0x40)
+        }
+        require(ok, "ecAdd failed");
+    }
+
+    function ecMul(uint256[2] memory a, uint256 s)
+        internal
+        view
+        returns (uint256[2] memory c)
+    {
+        uint256[3] memory input = [a[0], a[1], s];
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x07, input, 0x60, c, 0x40)
+        }
+        require(ok, "ecMul failed");
+    }
+
+    /// verifies the aggregated ticket for users pids[j] with encoded
+    /// public keys pks[j] ({PUBLIC_KEY_LEN} bytes each) in the ith lottery
+    /// with seed lseed. The ticket is encoded as hat_y, v ({TICKET_LEN} bytes).
+    /// Identifiers must be strictly increasing, so no user is counted twice
+    function verify(
+        uint32 i,
+        bytes32 lseed,
+        uint32[] calldata pids,
+        bytes[] calldata pks,
+        bytes calldata ticket
+    ) external view returns (bool) {
+        uint256 l = pids.length;
+        if (l == 0 || l != pks.length || i >= d.length || ticket.length != {TICKET_LEN}) {
+            return false;
+        }
+
+        // compute challenges and the aggregation challenge chi
+        uint256[] memory xs = new uint256[](l);
+        bytes memory aggInput = abi.encodePacked(AGG_DST, i);
+        for (uint256 j = 0; j < l; j++) {
+            if (pks[j].length != {PUBLIC_KEY_LEN} || (j > 0 && pids[j] <= pids[j - 1])) {
+                return false;
+            }
+            xs[j] = challenge(pks[j], pids[j], i, lseed);
+            aggInput = abi.encodePacked(aggInput, xs[j], pks[j][0:64]);
+        }
+        uint256 chi = uint256(keccak256(aggInput)) % R;
+
+        // com = sum_j chi^j com[j], mi = sum_j chi^j x[j]
+        uint256[2] memory com;
+        uint256 mi = 0;
+        uint256 c = 1;
+        for (uint256 j = 0; j < l; j++) {
+            uint256[2] memory comJ = [uint256(bytes32(pks[j][0:32])), uint256(bytes32(pks[j][32:64]))];
+            com = ecAdd(com, ecMul(comJ, c));
+            mi = addmod(mi, mulmod(xs[j], c, R), R);
+            c = mulmod(c, chi, R);
+        }
+
+        uint256 hatY = uint256(bytes32(ticket[0:32]));
+        if (hatY >= R) {
+            return false;
+        }
+        uint256[2] memory v = [uint256(bytes32(ticket[32:64])), uint256(bytes32(ticket[64:96]))];
+
+        // check e(-(com*g1^{-mi}*h^{-hatY}),g2) * e(v,d[i]) == 1
+        if (com[1] != 0) {
+            com[1] = P - com[1];
+        }
+        uint256[2] memory negLhs = ecAdd(ecAdd(ecMul(g1, mi), ecMul(h, hatY)), com);
+        uint256[4] memory di = d[i];
+        uint256[12] memory input = [
+            negLhs[0], negLhs[1], g2[0], g2[1], g2[2], g2[3],
+            v[0], v[1], di[0], di[1], di[2], di[3]
+        ];
+        uint256[1] memory out;
+        bool ok;
+        assembly {
+            ok := staticcall(gas(), 0x08, input, 0x180, out, 0x20)
+        }
+        return ok && out[0] == 1;
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::{Fq, Fr, G1Affine, G2Affine};
+    use ark_ec::AffineRepr;
+    use ark_ff::{BigInteger, PrimeField};
+    use std::str::FromStr;
+
+    use super::{
+        decode_g1, encode_g1, encode_g2, encode_public_key, encode_ticket, encode_verifier_key,
+        solidity_verifier, verify_encoded, JackEvm,
+    };
+    use crate::lotteryscheme::vcbased::Ticket;
+    use crate::lotteryscheme::{
+        _lottery_test_always_winning, _lottery_test_key_verify, LotteryScheme,
+    };
+    use crate::vectorcommitment::kzg::Opening;
+
+    fn field_bytes(s: &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        super::encode_field(&Fq::from_str(s).unwrap(), &mut out);
+        out
+    }
+
+    #[test]
+    fn jack_evm_lottery_test_key_verify() {
+        _lottery_test_key_verify::<JackEvm>();
+    }
+
+    #[test]
+    fn jack_evm_lottery_test_always_winning() {
+        _lottery_test_always_winning::<JackEvm>();
+    }
+
+    /// test the encoding of the generators against
+    /// the values from EIP-196 and EIP-197
+    #[test]
+    fn jack_evm_test_encoding() {
+        let mut g1 = Vec::new();
+        encode_g1(&G1Affine::generator(), &mut g1);
+        let mut expected = field_bytes("1");
+        expected.extend(field_bytes("2"));
+        assert_eq!(g1, expected);
+        assert_eq!(decode_g1(&g1), Some(G1Affine::generator()));
+
+        let mut g2 = Vec::new();
+        encode_g2(&G2Affine::generator(), &mut g2);
+        let mut expected = Vec::new();
+        for s in [
+            "11559732032986387107991004021392285783925812861821192530917403151452391805634",
+            "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+            "4082367875863433681332203403145435568316851327593401208105741076214120093531",
+            "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+        ] {
+            expected.extend(field_bytes(s));
+        }
+        assert_eq!(g2, expected);
+
+        // the identity is (0,0), points not on the curve
+        // and non-canonical coordinates are rejected
+        let zero = vec![0u8; 64];
+        assert_eq!(decode_g1(&zero), Some(G1Affine::zero()));
+        let mut off_curve = field_bytes("1");
+        off_curve.extend(field_bytes("3"));
+        assert_eq!(decode_g1(&off_curve), None);
+        let mut non_canonical = Fq::MODULUS.to_bytes_be();
+        non_canonical.extend(field_bytes("2"));
+        assert_eq!(decode_g1(&non_canonical), None);
+    }
+
+    /// test that the reference verifier for encoded tickets
+    /// agrees with the native verifier
+    #[test]
+    fn jack_evm_test_verify_encoded() {
+        let mut rng = ark_std::rand::thread_rng();
+        // everyone wins with probability 1
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <JackEvm as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let i = 3;
+        let lseed = <JackEvm as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let mut pks = Vec::new();
+        let mut tickets = Vec::new();
+        for j in 0..3 {
+            let (pk, sk) = <JackEvm as LotteryScheme>::gen(&mut rng, &par);
+            let ticket =
+                <JackEvm as LotteryScheme>::get_ticket(&par, i, &lseed, j, &sk, &pk).unwrap();
+            pks.push(pk);
+            tickets.push(ticket);
+        }
+        let pids = vec![0, 1, 2];
+        let ticket =
+            <JackEvm as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &pks, &tickets).unwrap();
+
+        let vk = encode_verifier_key(&par);
+        let pks_enc: Vec<_> = pks.iter().map(encode_public_key).collect();
+        let pks_enc: Vec<_> = pks_enc.iter().map(|pk| pk.as_slice()).collect();
+        assert!(<JackEvm as LotteryScheme>::verify(
+            &par, i, &lseed, &pids, &pks, &ticket
+        ));
+        assert!(verify_encoded(
+            &vk,
+            i,
+            &lseed,
+            &pids,
+            &pks_enc,
+            &encode_ticket(&ticket)
+        ));
+
+        // the native verifier accepts any order of the users, but the
+        // encoded verifier requires the order of aggregation
+        let pids_rev: Vec<u32> = pids.iter().rev().copied().collect();
+        let pks_rev: Vec<_> = pks_enc.iter().rev().copied().collect();
+        assert!(!verify_encoded(
+            &vk,
+            i,
+            &lseed,
+            &pids_rev,
+            &pks_rev,
+            &encode_ticket(&ticket)
+        ));
+
+        // both reject the ticket for another lottery
+        assert!(!<JackEvm as LotteryScheme>::verify(
+            &par,
+            i + 1,
+            &lseed,
+            &pids,
+            &pks,
+            &ticket
+        ));
+        assert!(!verify_encoded(
+            &vk,
+            i + 1,
+            &lseed,
+            &pids,
+            &pks_enc,
+            &encode_ticket(&ticket)
+        ));
+
+        // both reject a modified ticket
+        let ticket = Ticket {
+            opening: Opening {
+                hat_y: ticket.opening.hat_y + Fr::from(1u64),
+                v: ticket.opening.v,
+            },
+        };
+        assert!(!<JackEvm as LotteryScheme>::verify(
+            &par, i, &lseed, &pids, &pks, &ticket
+        ));
+        assert!(!verify_encoded(
+            &vk,
+            i,
+            &lseed,
+            &pids,
+            &pks_enc,
+            &encode_ticket(&ticket)
+        ));
+    }
+
+    /// test that the checked in Solidity verifier is up to date
+    #[test]
+    fn jack_evm_test_solidity_verifier() {
+        let source = std::fs::read_to_string("contracts/JackVerifier.sol").unwrap();
+        assert_eq!(source, solidity_verifier());
+    }
+}
@@ -0,0 +1,222 @@
+use ark_bls12_377::{constraints::PairingVar as Bls12_377PairingVar, Bls12_377};
+use ark_bw6_761::BW6_761;
+use ark_ec::pairing::Pairing;
+use ark_poly::Radix2EvaluationDomain;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use super::challenge::PoseidonHasher;
+use super::vcbased::{get_challenge, LotterySeed, Parameters, PublicKey, Ticket, VCLotteryScheme};
+use crate::vectorcommitment::kzg::kzg_constraints::{
+    scalar_to_bits_le, verify_aggregated_gadget, OpeningVar, VerifierKeyVar,
+};
+use crate::vectorcommitment::kzg::{PoseidonKzgHasher, VcKZG};
+
+type E = Bls12_377;
+type F = <E as Pairing>::ScalarField;
+type D = Radix2EvaluationDomain<F>;
+type VC = VcKZG<E, D, PoseidonKzgHasher>;
+type PV = Bls12_377PairingVar;
+type G1Var = <PV as PairingVar<E, ConstraintF>>::G1Var;
+
+/// field over which circuits verifying tickets are defined. It is the
+/// base field of BLS12-377 and the scalar field of BW6-761, so that
+/// these circuits can be proven with a SNARK over BW6-761
+pub type ConstraintF = <BW6_761 as Pairing>::ScalarField;
+
+/// Jackpot instantiated with KZG over BLS12-377, where challenges
+/// and aggregation coefficients are computed using Poseidon,
+/// so that tickets can be verified inside of SNARK circuits
+pub type JackR1CS = VCLotteryScheme<F, VC, PoseidonHasher>;
+
+/// Circuit that checks an aggregated ticket for the ith lottery.
+/// Public keys, challenges and the verifier key including d[i] are
+/// public inputs, while the ticket is a witness. Challenges are passed
+/// to the circuit, as they are computed over the scalar field of BLS12-377
+pub struct TicketCircuit<'a> {
+    pub par: &'a Parameters<F, VC>,
+    pub i: u32,
+    pub pks: &'a [PublicKey<F, VC>],
+    pub xs: Vec<F>,
+    pub ticket: Option<&'a Ticket<F, VC>>,
+}
+
+impl<'a> TicketCircuit<'a> {
+    /// Set up the circuit for users with identifiers pids[j] and public
+    /// keys pks[j] in the ith lottery with seed lseed, computing the challenges.
+    /// Identifiers must be strictly increasing, which is the order of aggregation
+    pub fn new(
+        par: &'a Parameters<F, VC>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &'a [PublicKey<F, VC>],
+        ticket: Option<&'a Ticket<F, VC>>,
+    ) -> Option<Self> {
+        if pids.len() != pks.len() || i as usize >= par.num_lotteries {
+            return None;
+        }
+        if pids.windows(2).any(|w| w[0] >= w[1]) {
+            return None;
+        }
+        let xs = pids
+            .iter()
+            .zip(pks)
+            .map(|(pid, pk)| get_challenge::<F, VC, PoseidonHasher>(par.log_k, pk, *pid, i, lseed))
+            .collect();
+        Some(Self {
+            par,
+            i,
+            pks,
+            xs,
+            ticket,
+        })
+    }
+}
+
+impl<'a> ConstraintSynthesizer<ConstraintF> for TicketCircuit<'a> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let vk = VerifierKeyVar::<E, PV>::new(
+            cs.clone(),
+            &self.par.ck.vk,
+            self.i,
+            AllocationMode::Input,
+        )?;
+
+        // challenges have log_k bits, so we only allocate these
+        let mut xs = Vec::new();
+        for x in &self.xs {
+            let mut bits = scalar_to_bits_le(x);
+            bits.truncate(self.par.log_k as usize);
+            xs.push(Vec::<Boolean<ConstraintF>>::new_input(cs.clone(), || {
+                Ok(bits)
+            })?);
+        }
+        let mut coms = Vec::new();
+        for pk in self.pks {
+            coms.push(G1Var::new_input(cs.clone(), || Ok(pk.com.com_kzg))?);
+        }
+        let opening = OpeningVar::<E, PV>::new_witness(cs.clone(), || {
+            self.ticket
+                .map(|t| &t.opening)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let valid = verify_aggregated_gadget(cs, &vk, self.i, &xs, &coms, &opening)?;
+        valid.enforce_equal(&Boolean::TRUE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+    use super::{ConstraintF, JackR1CS, TicketCircuit, F};
+    use crate::lotteryscheme::vcbased::Ticket;
+    use crate::lotteryscheme::{
+        _lottery_test_always_winning, _lottery_test_key_verify, LotteryScheme,
+    };
+    use crate::vectorcommitment::kzg::Opening;
+
+    #[test]
+    fn jack_r1cs_lottery_test_key_verify() {
+        _lottery_test_key_verify::<JackR1CS>();
+    }
+
+    #[test]
+    fn jack_r1cs_lottery_test_always_winning() {
+        _lottery_test_always_winning::<JackR1CS>();
+    }
+
+    /// test that the circuit is satisfied exactly
+    /// when the native verifier accepts the ticket
+    #[test]
+    fn jack_r1cs_test_circuit() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 4;
+        let par = <JackR1CS as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let mut pks = Vec::new();
+        let mut sks = Vec::new();
+        for _ in 0..8 {
+            let (pk, sk) = <JackR1CS as LotteryScheme>::gen(&mut rng, &par);
+            pks.push(pk);
+            sks.push(sk);
+        }
+
+        // find a seed for which at least one user wins
+        // and collect the tickets of all winners
+        let i = 2;
+        let (lseed, pids) = loop {
+            let lseed = <JackR1CS as LotteryScheme>::sample_seed(&mut rng, &par, i);
+            let pids: Vec<u32> = (0..pks.len())
+                .filter(|j| {
+                    let pid = *j as u32;
+                    <JackR1CS as LotteryScheme>::participate(
+                        &par, i, &lseed, pid, &sks[*j], &pks[*j],
+                    )
+                })
+                .map(|j| j as u32)
+                .collect();
+            if !pids.is_empty() {
+                break (lseed, pids);
+            }
+        };
+        let mut tickets = Vec::new();
+        for pid in &pids {
+            let j = *pid as usize;
+            let ticket =
+                <JackR1CS as LotteryScheme>::get_ticket(&par, i, &lseed, *pid, &sks[j], &pks[j])
+                    .unwrap();
+            tickets.push(ticket);
+        }
+        let winner_pks: Vec<_> = pks
+            .into_iter()
+            .enumerate()
+            .filter(|(j, _)| pids.contains(&(*j as u32)))
+            .map(|(_, pk)| pk)
+            .collect();
+        let ticket =
+            <JackR1CS as LotteryScheme>::aggregate(&par, i, &lseed, &pids, &winner_pks, &tickets)
+                .unwrap();
+
+        // the circuit accepts the aggregated ticket
+        assert!(<JackR1CS as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &pids,
+            &winner_pks,
+            &ticket
+        ));
+        let circuit =
+            TicketCircuit::new(&par, i, &lseed, &pids, &winner_pks, Some(&ticket)).unwrap();
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // both reject a modified ticket
+        let ticket = Ticket {
+            opening: Opening {
+                hat_y: ticket.opening.hat_y + F::from(1u64),
+                v: ticket.opening.v,
+            },
+        };
+        assert!(!<JackR1CS as LotteryScheme>::verify(
+            &par,
+            i,
+            &lseed,
+            &pids,
+            &winner_pks,
+            &ticket
+        ));
+        let circuit =
+            TicketCircuit::new(&par, i, &lseed, &pids, &winner_pks, Some(&ticket)).unwrap();
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,75 @@
+use super::vcbased::VCLotteryScheme;
+use crate::vectorcommitment::merkle::VcMerkle;
+use ark_bls12_381::Fr;
+
+type F = Fr;
+type VC = VcMerkle<F>;
+
+/// Lottery scheme that only relies on hash functions,
+/// instantiated with the vector commitment based on salted
+/// Merkle trees. It serves as a post-quantum fallback,
+/// but tickets do not aggregate compactly
+pub type MerkleLottery = VCLotteryScheme<F, VC>;
+
+#[cfg(test)]
+mod tests {
+    use super::MerkleLottery;
+    use crate::lotteryscheme::{
+        _lottery_test_always_winning, _lottery_test_key_verify, LotteryScheme,
+    };
+
+    #[test]
+    fn merkle_lottery_test_key_verify() {
+        _lottery_test_key_verify::<MerkleLottery>();
+    }
+
+    #[test]
+    fn merkle_lottery_test_always_winning() {
+        _lottery_test_always_winning::<MerkleLottery>();
+    }
+
+    /// test that tickets of different lotteries of the same
+    /// user are aggregated into a single multiproof
+    #[test]
+    fn merkle_lottery_test_cross_aggregate() {
+        let mut rng = ark_std::rand::thread_rng();
+        // everyone wins with probability 1
+        let num_lotteries = 8;
+        let k = 1;
+        let par = <MerkleLottery as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let (pk, sk) = <MerkleLottery as LotteryScheme>::gen(&mut rng, &par);
+        let pid = 3;
+
+        let mut is = Vec::new();
+        let mut lseeds = Vec::new();
+        let mut tickets = Vec::new();
+        for i in 0..num_lotteries as u32 {
+            let lseed = <MerkleLottery as LotteryScheme>::sample_seed(&mut rng, &par, i);
+            let ticket =
+                <MerkleLottery as LotteryScheme>::get_ticket(&par, i, &lseed, pid, &sk, &pk);
+            is.push(i);
+            lseeds.push(lseed);
+            tickets.push(ticket.unwrap());
+        }
+        let pids = vec![pid; is.len()];
+        let pks = vec![&pk; is.len()];
+        let tickets_r: Vec<_> = tickets.iter().collect();
+
+        // all leaves are opened, so no nodes are needed
+        let ticket = MerkleLottery::aggregate_cross(&par, &is, &lseeds, &pids, &pks, &tickets_r);
+        let ticket = ticket.unwrap();
+        assert_eq!(ticket.opening.proofs.len(), 1);
+        assert!(ticket.opening.proofs[0].nodes.is_empty());
+        assert!(MerkleLottery::verify_cross(
+            &par, &is, &lseeds, &pids, &pks, &ticket
+        ));
+        assert!(!MerkleLottery::verify_cross(
+            &par,
+            &is[1..],
+            &lseeds[1..],
+            &pids[1..],
+            &pks[1..],
+            &ticket
+        ));
+    }
+}
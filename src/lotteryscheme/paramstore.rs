@@ -0,0 +1,318 @@
+use ark_bls12_381::Fr;
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::jack::Jack;
+use super::vcbased::Parameters;
+use super::LotteryScheme;
+use crate::vectorcommitment::kzg::CommitmentKey;
+
+// This module contains a store for the parameters of Jack. Files are
+// named after the curve, the size of the evaluation domain, the number
+// of lotteries, k, and the hash of their content. Hence, parameters for
+// different settings never overwrite each other, and corrupted files
+// are skipped when loading. Note that the hash only detects corruption:
+// anyone who can write to the directory can add a commitment key whose
+// trapdoor they know, named after its hash, which breaks soundness.
+// Therefore, callers should pin the hashes of trusted parameters, in which
+// case all other files are refused. Without pins, parameters that were
+// not generated by this process are loaded with a warning
+
+/// parameters of Jack
+pub type JackParameters = <Jack as LotteryScheme>::Parameters;
+
+/// default directory of the store
+pub const DEFAULT_DIR: &str = "crs_precomputed";
+
+/// curve over which Jack is instantiated
+const CURVE: &str = "bls12-381";
+
+/// number of bytes of the hash that are part of the file name
+const HASH_LEN: usize = 16;
+
+/// hashes of the parameters generated by this process
+static GENERATED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Store of the parameters of Jack in a directory
+#[derive(Clone, Debug)]
+pub struct ParameterStore {
+    dir: PathBuf,
+    /// hashes of trusted parameters. If not empty, all others are refused
+    pinned: BTreeSet<String>,
+}
+
+impl Default for ParameterStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIR)
+    }
+}
+
+/// returns the hex encoded hash of the content of a file
+fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(&Sha256::digest(bytes)[..HASH_LEN])
+}
+
+/// prefix of the names of all files containing parameters
+/// for num_lotteries lotteries with winning probability 1/k.
+/// Returns None if there are no such parameters
+fn file_prefix(num_lotteries: usize, k: u32) -> Option<String> {
+    if num_lotteries < 1 || !k.is_power_of_two() {
+        return None;
+    }
+    // the same domain as in the setup of the commitment key
+    let domain = Radix2EvaluationDomain::<Fr>::new(num_lotteries + 2)?;
+    Some(format!(
+        "jack-{}-d{}-n{}-k{}-",
+        CURVE,
+        domain.size(),
+        num_lotteries,
+        k
+    ))
+}
+
+/// encodes the parameters as they are stored, i.e., the
+/// number of lotteries and k, followed by the commitment key
+fn encode(par: &JackParameters) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    (par.num_lotteries as u64)
+        .serialize_compressed(&mut bytes)
+        .expect("Failed to serialize number of lotteries");
+    par.k
+        .serialize_compressed(&mut bytes)
+        .expect("Failed to serialize k");
+    par.ck
+        .serialize_compressed(&mut bytes)
+        .expect("Failed to serialize commitment key");
+    bytes
+}
+
+/// reads the parameters for num_lotteries lotteries with winning
+/// probability 1/k from the file at path, whose content should
+/// have the given hash. Returns None if this fails
+fn read(path: &Path, hash: &str, num_lotteries: usize, k: u32) -> Option<JackParameters> {
+    let bytes = fs::read(path).ok()?;
+    if content_hash(&bytes) != hash {
+        return None;
+    }
+    let mut reader = bytes.as_slice();
+    let num_read = u64::deserialize_compressed(&mut reader).ok()?;
+    let k_read = u32::deserialize_compressed(&mut reader).ok()?;
+    if num_read != num_lotteries as u64 || k_read != k {
+        return None;
+    }
+    let ck = CommitmentKey::deserialize_compressed(&mut reader).ok()?;
+    if !reader.is_empty() || ck.message_length != num_lotteries {
+        return None;
+    }
+    Some(Parameters {
+        ck,
+        num_lotteries,
+        k,
+        log_k: k.trailing_zeros(),
+    })
+}
+
+impl ParameterStore {
+    /// Creates a store in the given directory,
+    /// which is created when parameters are saved
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            pinned: BTreeSet::new(),
+        }
+    }
+
+    /// Trust the parameters with the given hash, as output by hash.
+    /// Once a hash is pinned, parameters with other hashes are refused
+    pub fn pin(mut self, hash: &str) -> Self {
+        self.pinned.insert(hash.to_string());
+        self
+    }
+
+    /// Returns the hash of the parameters, which is part of their file name
+    pub fn hash(par: &JackParameters) -> String {
+        content_hash(&encode(par))
+    }
+
+    /// Returns the directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads parameters for num_lotteries lotteries with winning
+    /// probability 1/k. Files whose content does not match their name
+    /// are skipped, as are files that are not pinned, if some are.
+    /// Returns None if there are no valid parameters
+    pub fn load(&self, num_lotteries: usize, k: u32) -> Option<JackParameters> {
+        let prefix = file_prefix(num_lotteries, k)?;
+        let entries = fs::read_dir(&self.dir).ok()?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(&prefix) && name.ends_with(".crs"))
+            .collect();
+        names.sort();
+        for name in names {
+            let path = self.dir.join(&name);
+            let hash = &name[prefix.len()..name.len() - ".crs".len()];
+            if !self.pinned.is_empty() && !self.pinned.contains(hash) {
+                log::warn!("Refusing unpinned parameters in {}", path.display());
+                continue;
+            }
+            match read(&path, hash, num_lotteries, k) {
+                Some(par) => {
+                    let generated = GENERATED
+                        .lock()
+                        .unwrap_or_else(|err| err.into_inner())
+                        .contains(hash);
+                    if self.pinned.is_empty() && !generated {
+                        log::warn!(
+                            "Loaded unpinned parameters from {}, which were not generated by \
+                             this process. Whoever wrote them may know the trapdoor",
+                            path.display()
+                        );
+                    } else {
+                        log::info!("Loaded parameters from {}", path.display());
+                    }
+                    return Some(par);
+                }
+                None => log::warn!("Skipping corrupt parameters in {}", path.display()),
+            }
+        }
+        None
+    }
+
+    /// Saves the parameters to a file named after their content.
+    /// They are written to a temporary file first, which is then renamed,
+    /// so that no one reads partially written parameters.
+    /// Returns the path of the file, or None if saving failed
+    pub fn save(&self, par: &JackParameters) -> Option<PathBuf> {
+        let prefix = file_prefix(par.num_lotteries, par.k)?;
+        let bytes = encode(par);
+        let name = format!("{}{}.crs", prefix, content_hash(&bytes));
+        let path = self.dir.join(&name);
+        let tmp = self
+            .dir
+            .join(format!(".{}.tmp-{}", name, std::process::id()));
+        let written = fs::create_dir_all(&self.dir).and_then(|_| {
+            let mut file = File::create(&tmp)?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            fs::rename(&tmp, &path)
+        });
+        match written {
+            Ok(()) => {
+                log::info!("Saved parameters to {}", path.display());
+                Some(path)
+            }
+            Err(err) => {
+                log::warn!("Failed to save parameters to {}: {}", path.display(), err);
+                let _ = fs::remove_file(&tmp);
+                None
+            }
+        }
+    }
+
+    /// Loads parameters for num_lotteries lotteries with winning
+    /// probability 1/k, or sets up and saves new ones if there are none.
+    /// If hashes are pinned, new parameters are only loaded
+    /// once their hash is pinned. Returns None if the setup fails
+    pub fn load_or_setup<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        num_lotteries: usize,
+        k: u32,
+    ) -> Option<JackParameters> {
+        if let Some(par) = self.load(num_lotteries, k) {
+            return Some(par);
+        }
+        log::info!(
+            "Generating new parameters for {} lotteries and k = {}",
+            num_lotteries,
+            k
+        );
+        let par = <Jack as LotteryScheme>::setup(rng, num_lotteries, k)?;
+        GENERATED
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(Self::hash(&par));
+        // the parameters can still be used if saving fails
+        self.save(&par);
+        Some(par)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::ParameterStore;
+
+    /// returns the names of all files in the directory of the store
+    fn file_names(store: &ParameterStore) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(store.dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn paramstore_test_load_or_setup() {
+        let mut rng = ark_std::rand::thread_rng();
+        let dir = std::env::temp_dir().join(format!("jack-params-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = ParameterStore::new(&dir);
+        assert!(store.load(3, 4).is_none());
+
+        // parameters are set up once and then loaded
+        let par = store.load_or_setup(&mut rng, 3, 4).unwrap();
+        let names = file_names(&store);
+        assert_eq!(names.len(), 1);
+        assert!(names[0].starts_with("jack-bls12-381-d8-n3-k4-"));
+        assert!(names[0].ends_with(".crs"));
+        let loaded = store.load(3, 4).unwrap();
+        assert_eq!(loaded.ck, par.ck);
+        assert_eq!((loaded.num_lotteries, loaded.k, loaded.log_k), (3, 4, 2));
+        let loaded = store.load_or_setup(&mut rng, 3, 4).unwrap();
+        assert_eq!(loaded.ck, par.ck);
+        assert_eq!(file_names(&store).len(), 1);
+
+        // other settings do not use these parameters
+        assert!(store.load(3, 8).is_none());
+        assert!(store.load(4, 4).is_none());
+        assert!(store.load(3, 3).is_none());
+        assert!(store.load(0, 4).is_none());
+
+        // modified files are skipped, and new parameters are set up
+        let path = dir.join(&names[0]);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(store.load(3, 4).is_none());
+        let new_par = store.load_or_setup(&mut rng, 3, 4).unwrap();
+        assert_ne!(new_par.ck, par.ck);
+        assert_eq!(file_names(&store).len(), 2);
+        assert_eq!(store.load(3, 4).unwrap().ck, new_par.ck);
+
+        // only pinned parameters are loaded, if any are pinned
+        let pinned = ParameterStore::new(&dir).pin(&ParameterStore::hash(&new_par));
+        assert_eq!(pinned.load(3, 4).unwrap().ck, new_par.ck);
+        let pinned = ParameterStore::new(&dir).pin(&ParameterStore::hash(&par));
+        assert!(pinned.load(3, 4).is_none());
+
+        // truncated files are skipped as well
+        fs::write(&path, &bytes[..last]).unwrap();
+        fs::remove_file(store.save(&new_par).unwrap()).unwrap();
+        assert!(store.load(3, 4).is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,519 @@
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use super::challenge::{ChallengeHasher, Sha256Hasher};
+use super::vcbased::{
+    get_challenge, LotterySeed, Parameters, PublicKey, Ticket, VCLotteryScheme, VerifierContext,
+};
+use super::LotteryScheme;
+use crate::vectorcommitment::merkle::merkle_types::Digest;
+use crate::vectorcommitment::merkle::merkle_utils::build_tree;
+use crate::vectorcommitment::VectorCommitmentScheme;
+
+/// this module contains the compact encodings
+/// of sets of winners by their registry indices
+pub mod registry_encoding;
+
+/// this module contains certificates of winners
+/// that can be verified against the registry root
+pub mod registry_certificate;
+
+pub use self::registry_certificate::WinnerCertificate;
+pub use self::registry_encoding::WinnerBitmap;
+pub use self::registry_encoding::WinnerSet;
+
+use self::registry_certificate::{registry_depth, registry_leaf, registry_root};
+
+/// A proof that a set of registered users won a lottery,
+/// consisting of the encoded set of winners and their aggregated ticket
+pub struct WinningProof<F: Field, VC: VectorCommitmentScheme<F>> {
+    pub winners: WinnerSet,
+    pub ticket: Ticket<F, VC>,
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> WinningProof<F, VC> {
+    /// Write the proof in compressed form
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.winners.serialize_compressed(&mut writer)?;
+        self.ticket.opening.serialize_compressed(&mut writer)
+    }
+
+    /// Read a proof written by serialize
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let winners = WinnerSet::deserialize_compressed(&mut reader)?;
+        let opening = VC::Opening::deserialize_compressed(&mut reader)?;
+        Ok(WinningProof {
+            winners,
+            ticket: Ticket { opening },
+        })
+    }
+
+    /// Size of the serialized proof in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.winners.compressed_size() + self.ticket.opening.compressed_size()
+    }
+}
+
+/// Registry of public keys that have been accepted by verify_key.
+/// Keys are stored under the identifier pid of their owner,
+/// and every registered key gets an index, which is the
+/// number of keys that have been registered before it
+pub struct KeyRegistry<
+    F: Field,
+    VC: VectorCommitmentScheme<F>,
+    H: ChallengeHasher<F, VC::Commitment> = Sha256Hasher,
+> {
+    /// identifiers in the order of registration
+    pids: Vec<u32>,
+    /// maps identifiers to their index
+    indices: HashMap<u32, usize>,
+    /// serialized commitments of the registered keys
+    coms: HashSet<Vec<u8>>,
+    /// the keys, prepared for fast verification
+    ctx: VerifierContext<F, VC, H>,
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    KeyRegistry<F, VC, H>
+{
+    pub fn new() -> Self {
+        Self {
+            pids: Vec::new(),
+            indices: HashMap::new(),
+            coms: HashSet::new(),
+            ctx: VerifierContext::new(),
+        }
+    }
+
+    /// Verify public key pk and register it for the user with identifier pid.
+    /// Returns false if the key is invalid or pid or the key have already been
+    /// registered, in which case the registry is not changed
+    pub fn register(&mut self, par: &Parameters<F, VC>, pid: u32, pk: PublicKey<F, VC>) -> bool {
+        if !<VCLotteryScheme<F, VC, H> as LotteryScheme>::verify_key(par, &pk) {
+            return false;
+        }
+        self.insert(pid, pk)
+    }
+
+    /// Register a key without verifying it.
+    /// Returns false if pid or the key have already been registered
+    fn insert(&mut self, pid: u32, pk: PublicKey<F, VC>) -> bool {
+        if self.indices.contains_key(&pid) {
+            return false;
+        }
+        let mut com_ser = Vec::new();
+        pk.com
+            .serialize_uncompressed(&mut com_ser)
+            .expect("Failed to serialize public key in insert");
+        if !self.coms.insert(com_ser) {
+            return false;
+        }
+        self.indices.insert(pid, self.pids.len());
+        self.pids.push(pid);
+        self.ctx.insert(pid, pk);
+        true
+    }
+
+    /// Returns the public key registered for pid, if any
+    pub fn get(&self, pid: u32) -> Option<&PublicKey<F, VC>> {
+        self.ctx.get(pid)
+    }
+
+    /// Returns the index of the key registered for pid, if any
+    pub fn index_of(&self, pid: u32) -> Option<usize> {
+        self.indices.get(&pid).copied()
+    }
+
+    /// Returns the identifier of the key with the given index, if any
+    pub fn pid_at(&self, index: usize) -> Option<u32> {
+        self.pids.get(index).copied()
+    }
+
+    /// Returns the number of registered keys
+    pub fn len(&self) -> usize {
+        self.pids.len()
+    }
+
+    /// Returns true if no key is registered
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty()
+    }
+
+    /// Returns the context used for verifying tickets of registered users
+    pub fn context(&self) -> &VerifierContext<F, VC, H> {
+        &self.ctx
+    }
+
+    /// Computes all levels of the Merkle tree over the registered
+    /// identifiers and keys, in the order of registration
+    fn tree(&self) -> Vec<Vec<Digest>> {
+        let depth = registry_depth(self.pids.len() as u64).expect("registry is too large");
+        let mut leaves: Vec<Digest> = self
+            .pids
+            .iter()
+            .enumerate()
+            .map(|(index, pid)| {
+                let com_ser = self
+                    .ctx
+                    .serialized(*pid)
+                    .expect("registered key is missing");
+                registry_leaf(index, *pid, com_ser)
+            })
+            .collect();
+        leaves.resize(1 << depth, [0u8; 32]);
+        build_tree(leaves)
+    }
+
+    /// Returns the root that commits to all registered identifiers and
+    /// keys. Light clients verify certificates against this root only
+    pub fn root(&self) -> Digest {
+        let tree = self.tree();
+        registry_root(self.pids.len() as u64, &tree[tree.len() - 1][0])
+    }
+
+    /// Encode the given users as a bitmap over their indices.
+    /// Returns None if one of them is not registered
+    pub fn bitmap(&self, pids: &[u32]) -> Option<WinnerBitmap> {
+        let indices: Option<Vec<usize>> = pids.iter().map(|pid| self.index_of(*pid)).collect();
+        indices.map(|indices| WinnerBitmap::from_indices(&indices))
+    }
+
+    /// Decode a bitmap into the identifiers of the users it contains,
+    /// ordered by their index. Returns None if the bitmap contains
+    /// an index for which no key is registered
+    pub fn pids_of(&self, bitmap: &WinnerBitmap) -> Option<Vec<u32>> {
+        bitmap
+            .indices()
+            .iter()
+            .map(|index| self.pid_at(*index))
+            .collect()
+    }
+
+    /// Write all registered identifiers and keys, in the order of registration
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        (self.pids.len() as u64).serialize_compressed(&mut writer)?;
+        for pid in &self.pids {
+            pid.serialize_compressed(&mut writer)?;
+            let pk = self.get(*pid).expect("registered key is missing");
+            pk.com.serialize_compressed(&mut writer)?;
+        }
+        Ok(())
+    }
+
+    /// Read a registry written by serialize.
+    /// Note: the keys are not verified again, so this
+    /// should only be used to read a registry from trusted storage
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let len = u64::deserialize_compressed(&mut reader)?;
+        let mut registry = Self::new();
+        for _ in 0..len {
+            let pid = u32::deserialize_compressed(&mut reader)?;
+            let com = VC::Commitment::deserialize_compressed(&mut reader)?;
+            if !registry.insert(pid, PublicKey { com }) {
+                return Err(SerializationError::InvalidData);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Write the registry to the file at the given path
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SerializationError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.serialize(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a registry from the file at the given path, see deserialize
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SerializationError> {
+        let reader = BufReader::new(File::open(path)?);
+        Self::deserialize(reader)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>> Default
+    for KeyRegistry<F, VC, H>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Aggregate tickets tickets[j] of registered users with identifiers pids[j]
+    /// for the ith lottery. Outputs the bitmap of the winners together with the
+    /// aggregated ticket, which verifies with verify_by_bitmap
+    pub fn aggregate_by_ids(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        tickets: &[&Ticket<F, VC>],
+    ) -> Option<(WinnerBitmap, Ticket<F, VC>)> {
+        if pids.len() != tickets.len() {
+            return None;
+        }
+        let bitmap = registry.bitmap(pids)?;
+        if bitmap.count() != pids.len() {
+            // some user is contained twice
+            return None;
+        }
+        // tickets are aggregated in the order of the identifiers, as in aggregate
+        let mut order: Vec<usize> = (0..pids.len()).collect();
+        order.sort_by_key(|j| pids[*j]);
+
+        // compute the challenge for each party
+        // and collect commitments and openings for each party
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        let mut openings = Vec::new();
+        for j in order {
+            let pk = registry.get(pids[j])?;
+            xs.push(get_challenge::<F, VC, H>(par.log_k, pk, pids[j], i, lseed));
+            coms.push(&pk.com);
+            openings.push(&tickets[j].opening);
+        }
+
+        // let the vector commitment aggregate
+        let agg_op = VC::aggregate(&par.ck, i, &xs, &coms, &openings)?;
+        Some((bitmap, Ticket { opening: agg_op }))
+    }
+
+    /// Verify ticket for the ith lottery with lottery seed lseed
+    /// for registered users with identifiers pids[j]
+    pub fn verify_by_ids(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        ticket: &Ticket<F, VC>,
+    ) -> bool {
+        Self::verify_with_context(par, registry.context(), i, lseed, pids, ticket)
+    }
+
+    /// Verify ticket for the ith lottery with lottery seed lseed
+    /// for the registered users contained in the bitmap,
+    /// as output by aggregate_by_ids
+    pub fn verify_by_bitmap(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        bitmap: &WinnerBitmap,
+        ticket: &Ticket<F, VC>,
+    ) -> bool {
+        match registry.pids_of(bitmap) {
+            Some(pids) => Self::verify_by_ids(par, registry, i, lseed, &pids, ticket),
+            None => false,
+        }
+    }
+
+    /// Aggregate tickets tickets[j] of registered users with identifiers pids[j]
+    /// for the ith lottery into a proof that carries the shortest
+    /// encoding of the set of winners
+    pub fn aggregate_winners(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        tickets: &[&Ticket<F, VC>],
+    ) -> Option<WinningProof<F, VC>> {
+        let (bitmap, ticket) = Self::aggregate_by_ids(par, registry, i, lseed, pids, tickets)?;
+        Some(WinningProof {
+            winners: WinnerSet::from_indices(&bitmap.indices()),
+            ticket,
+        })
+    }
+
+    /// Verify a proof output by aggregate_winners
+    /// for the ith lottery with lottery seed lseed
+    pub fn verify_winners(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        proof: &WinningProof<F, VC>,
+    ) -> bool {
+        // the winners are decoded without building a bitmap,
+        // whose size would be given by an untrusted index
+        let pids: Option<Vec<u32>> =
+            proof
+                .winners
+                .indices_bounded(registry.len())
+                .and_then(|indices| {
+                    indices
+                        .iter()
+                        .map(|index| registry.pid_at(*index))
+                        .collect()
+                });
+        match pids {
+            Some(pids) => Self::verify_by_ids(par, registry, i, lseed, &pids, &proof.ticket),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_ec::pairing::Pairing;
+    use ark_poly::Radix2EvaluationDomain;
+
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    use super::{KeyRegistry, WinnerBitmap, WinnerSet, WinningProof};
+    use crate::lotteryscheme::jack::Jack;
+    use crate::lotteryscheme::vcbased::PublicKey;
+    use crate::lotteryscheme::LotteryScheme;
+    use crate::vectorcommitment::kzg::VcKZG;
+
+    type F = <Bls12_381 as Pairing>::ScalarField;
+    type D = Radix2EvaluationDomain<F>;
+    type VC = VcKZG<Bls12_381, D>;
+
+    /// test registration, persistence and verification by bitmap
+    #[test]
+    fn registry_test_registry() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let mut registry = KeyRegistry::<F, VC>::new();
+        let mut sks = Vec::new();
+        let pids = [5, 2, 9, 4];
+        for pid in pids {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            assert!(registry.register(&par, pid, pk));
+            sks.push(sk);
+        }
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.index_of(9), Some(2));
+        assert_eq!(registry.pid_at(3), Some(4));
+
+        // pids and keys can only be registered once, and invalid keys are rejected
+        let (pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        assert!(!registry.register(&par, 5, pk));
+        let mut com_bytes = Vec::new();
+        let com = &registry.get(2).unwrap().com;
+        com.serialize_compressed(&mut com_bytes).unwrap();
+        let pk = PublicKey {
+            com: CanonicalDeserialize::deserialize_compressed(com_bytes.as_slice()).unwrap(),
+        };
+        assert!(!registry.register(&par, 6, pk));
+        let (mut pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        pk.com.y0 += F::from(1u64);
+        assert!(!registry.register(&par, 6, pk));
+        assert_eq!(registry.len(), 4);
+
+        // persist the registry and read it again
+        let path = std::env::temp_dir().join(format!("registry-{}.bin", std::process::id()));
+        registry.save(&path).unwrap();
+        let registry = KeyRegistry::<F, VC>::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.index_of(4), Some(3));
+
+        // users 9 and 5 win, and we refer to them by bitmap
+        let i = 1;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let winners = [9, 5];
+        let mut tickets = Vec::new();
+        for (pid, sk) in [(9, &sks[2]), (5, &sks[0])] {
+            let pk = registry.get(pid).unwrap();
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, sk, pk);
+            tickets.push(ticket.unwrap());
+        }
+        let tickets_r: Vec<_> = tickets.iter().collect();
+        let (bitmap, ticket) =
+            Jack::aggregate_by_ids(&par, &registry, i, &lseed, &winners, &tickets_r).unwrap();
+        assert_eq!(bitmap.as_bytes(), &[0b101]);
+        assert!(Jack::verify_by_bitmap(
+            &par, &registry, i, &lseed, &bitmap, &ticket
+        ));
+        assert!(Jack::verify_by_ids(
+            &par,
+            &registry,
+            i,
+            &lseed,
+            &[5, 9],
+            &ticket
+        ));
+
+        // other sets of winners are rejected
+        let other = WinnerBitmap::from_indices(&[0, 1]);
+        assert!(!Jack::verify_by_bitmap(
+            &par, &registry, i, &lseed, &other, &ticket
+        ));
+        let unknown = WinnerBitmap::from_indices(&[0, 2, 4]);
+        assert!(!Jack::verify_by_bitmap(
+            &par, &registry, i, &lseed, &unknown, &ticket
+        ));
+    }
+
+    /// test aggregation and verification of winning proofs
+    #[test]
+    fn registry_test_winning_proof() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let mut registry = KeyRegistry::<F, VC>::new();
+        let mut sks = Vec::new();
+        for pid in 0..20 {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            assert!(registry.register(&par, pid, pk));
+            sks.push(sk);
+        }
+
+        let i = 2;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let winners = [17, 1];
+        let mut tickets = Vec::new();
+        for pid in winners {
+            let pk = registry.get(pid).unwrap();
+            let sk = &sks[pid as usize];
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, sk, pk);
+            tickets.push(ticket.unwrap());
+        }
+        let tickets_r: Vec<_> = tickets.iter().collect();
+        let proof =
+            Jack::aggregate_winners(&par, &registry, i, &lseed, &winners, &tickets_r).unwrap();
+        assert!(matches!(proof.winners, WinnerSet::Deltas(_)));
+        assert!(Jack::verify_winners(&par, &registry, i, &lseed, &proof));
+
+        // serialize and read the proof again
+        let mut bytes = Vec::new();
+        proof.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), proof.serialized_size());
+        let proof = WinningProof::<F, VC>::deserialize(&bytes[..]).unwrap();
+        assert!(Jack::verify_winners(&par, &registry, i, &lseed, &proof));
+
+        // a proof for a different set of winners is rejected
+        let forged = WinningProof::<F, VC> {
+            winners: WinnerSet::from_indices(&[1, 16]),
+            ticket: proof.ticket,
+        };
+        assert!(!Jack::verify_winners(&par, &registry, i, &lseed, &forged));
+
+        // as are indices beyond the registry, even if they are huge
+        let beyond = WinningProof::<F, VC> {
+            winners: WinnerSet::from_indices(&[1, 20]),
+            ticket: forged.ticket,
+        };
+        assert!(!Jack::verify_winners(&par, &registry, i, &lseed, &beyond));
+        let huge = WinningProof::<F, VC> {
+            winners: WinnerSet::Deltas(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]),
+            ticket: beyond.ticket,
+        };
+        assert!(!Jack::verify_winners(&par, &registry, i, &lseed, &huge));
+    }
+}
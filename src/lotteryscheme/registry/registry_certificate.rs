@@ -0,0 +1,286 @@
+use ark_ff::Field;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Write,
+};
+use sha2::{Digest as _, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{KeyRegistry, WinningProof};
+use crate::lotteryscheme::challenge::ChallengeHasher;
+use crate::lotteryscheme::vcbased::{
+    serialize_key, LotterySeed, Parameters, PublicKey, Ticket, VCLotteryScheme, VerifierParameters,
+};
+use crate::vectorcommitment::merkle::merkle_types::Digest;
+use crate::vectorcommitment::merkle::merkle_utils::{compute_root, multiproof_nodes, read_digests};
+use crate::vectorcommitment::VectorCommitmentScheme;
+
+/// Certificate that a set of registered users won a lottery, which can be
+/// verified by light clients that only know the root of the registry.
+/// Besides the winning proof, it contains the identifiers and keys of
+/// the winners, by increasing index, and a Merkle multiproof for them
+pub struct WinnerCertificate<F: Field, VC: VectorCommitmentScheme<F>> {
+    pub proof: WinningProof<F, VC>,
+    /// number of registered keys, which determines the shape of the tree
+    pub num_keys: u64,
+    pub pids: Vec<u32>,
+    pub pks: Vec<PublicKey<F, VC>>,
+    /// the nodes needed to recompute the root, as in merkle::MultiProof
+    pub nodes: Vec<Digest>,
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> WinnerCertificate<F, VC> {
+    /// Write the certificate in compressed form
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+        self.proof.serialize(&mut writer)?;
+        self.num_keys.serialize_compressed(&mut writer)?;
+        self.pids.serialize_compressed(&mut writer)?;
+        for pk in &self.pks {
+            pk.com.serialize_compressed(&mut writer)?;
+        }
+        self.nodes.serialize_compressed(&mut writer)
+    }
+
+    /// Read a certificate written by serialize
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let proof = WinningProof::deserialize(&mut reader)?;
+        let num_keys = u64::deserialize_compressed(&mut reader)?;
+        let pids = Vec::<u32>::deserialize_compressed(&mut reader)?;
+        let mut pks = Vec::new();
+        for _ in 0..pids.len() {
+            let com = VC::Commitment::deserialize_compressed(&mut reader)?;
+            pks.push(PublicKey { com });
+        }
+        let nodes = read_digests(&mut reader, Compress::Yes)?;
+        Ok(WinnerCertificate {
+            proof,
+            num_keys,
+            pids,
+            pks,
+            nodes,
+        })
+    }
+
+    /// Size of the serialized certificate in bytes
+    pub fn serialized_size(&self) -> usize {
+        self.proof.serialized_size()
+            + self.num_keys.compressed_size()
+            + self.pids.compressed_size()
+            + self
+                .pks
+                .iter()
+                .map(|pk| pk.com.compressed_size())
+                .sum::<usize>()
+            + self.nodes.compressed_size()
+    }
+}
+
+/// Computes the leaf of the registry tree for the key with the
+/// given index, identifier and serialized commitment
+pub(super) fn registry_leaf(index: usize, pid: u32, com_ser: &[u8]) -> Digest {
+    let mut hasher = Sha256::new_with_prefix("REGISTRY-LEAF//".as_bytes());
+    hasher.update((index as u64).to_be_bytes());
+    hasher.update(pid.to_be_bytes());
+    hasher.update(com_ser);
+    hasher.finalize().into()
+}
+
+/// Computes the root of the registry from the number
+/// of keys and the root of the tree over their leaves
+pub(super) fn registry_root(num_keys: u64, tree_root: &Digest) -> Digest {
+    let mut hasher = Sha256::new_with_prefix("REGISTRY-ROOT//".as_bytes());
+    hasher.update(num_keys.to_be_bytes());
+    hasher.update(tree_root);
+    hasher.finalize().into()
+}
+
+/// Depth of the registry tree for the given number of keys
+pub(super) fn registry_depth(num_keys: u64) -> Option<usize> {
+    let n = usize::try_from(num_keys.max(1)).ok()?;
+    Some(n.checked_next_power_of_two()?.trailing_zeros() as usize)
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Aggregate tickets tickets[j] of registered users with identifiers
+    /// pids[j] for the ith lottery into a certificate, which can be verified
+    /// with verify_certificate given only the root of the registry
+    pub fn certify_winners(
+        par: &Parameters<F, VC>,
+        registry: &KeyRegistry<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        tickets: &[&Ticket<F, VC>],
+    ) -> Option<WinnerCertificate<F, VC>> {
+        let proof = Self::aggregate_winners(par, registry, i, lseed, pids, tickets)?;
+        let indices = proof.winners.indices()?;
+        let tree = registry.tree();
+        let positions: BTreeSet<usize> = indices.iter().copied().collect();
+        let nodes = multiproof_nodes(tree.len() - 1, &positions, |level, index| {
+            tree[level].get(index).copied()
+        })?;
+
+        // keys are not Clone, so we copy them from their serialization
+        let mut winner_pids = Vec::with_capacity(indices.len());
+        let mut pks = Vec::with_capacity(indices.len());
+        for index in indices {
+            let pid = registry.pid_at(index)?;
+            let com_ser = registry.ctx.serialized(pid)?;
+            let com = VC::Commitment::deserialize_uncompressed_unchecked(com_ser).ok()?;
+            winner_pids.push(pid);
+            pks.push(PublicKey { com });
+        }
+        Some(WinnerCertificate {
+            proof,
+            num_keys: registry.len() as u64,
+            pids: winner_pids,
+            pks,
+            nodes,
+        })
+    }
+
+    /// Verify that at least threshold users of the registry with the given
+    /// root won the ith lottery with lottery seed lseed, as certified by cert.
+    /// Light clients only need the verifier parameters and the root
+    pub fn verify_certificate(
+        vpar: &VerifierParameters<F, VC>,
+        root: &Digest,
+        i: u32,
+        lseed: &LotterySeed,
+        threshold: usize,
+        cert: &WinnerCertificate<F, VC>,
+    ) -> bool {
+        let num_keys = usize::try_from(cert.num_keys).unwrap_or(usize::MAX);
+        let indices = match cert.proof.winners.indices_bounded(num_keys) {
+            Some(indices) => indices,
+            None => return false,
+        };
+        if indices.len() != cert.pids.len() || indices.len() != cert.pks.len() {
+            return false;
+        }
+        if indices.is_empty() || indices.len() < threshold {
+            return false;
+        }
+        let depth = match registry_depth(cert.num_keys) {
+            Some(depth) => depth,
+            None => return false,
+        };
+
+        // the winners must be registered at their indices
+        let leaves: BTreeMap<usize, Digest> = indices
+            .iter()
+            .zip(cert.pids.iter().zip(&cert.pks))
+            .map(|(index, (pid, pk))| (*index, registry_leaf(*index, *pid, &serialize_key(pk))))
+            .collect();
+        match compute_root(depth, &leaves, &cert.nodes) {
+            Some(tree_root) if registry_root(cert.num_keys, &tree_root) == *root => {}
+            _ => return false,
+        }
+
+        // and the ticket must verify for them
+        Self::verify_light(vpar, i, lseed, &cert.pids, &cert.pks, &cert.proof.ticket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Bls12_381;
+    use ark_ec::pairing::Pairing;
+    use ark_poly::Radix2EvaluationDomain;
+
+    use super::WinnerCertificate;
+    use crate::lotteryscheme::jack::Jack;
+    use crate::lotteryscheme::registry::{KeyRegistry, WinnerSet};
+    use crate::lotteryscheme::LotteryScheme;
+    use crate::vectorcommitment::kzg::VcKZG;
+
+    type F = <Bls12_381 as Pairing>::ScalarField;
+    type D = Radix2EvaluationDomain<F>;
+    type VC = VcKZG<Bls12_381, D>;
+
+    /// test that light clients verify certificates against the registry root
+    #[test]
+    fn registry_test_winner_certificate() {
+        let mut rng = ark_std::rand::thread_rng();
+        let num_lotteries = (1 << 3) - 2;
+        let k = 1;
+        let par = <Jack as LotteryScheme>::setup(&mut rng, num_lotteries, k).unwrap();
+        let mut registry = KeyRegistry::<F, VC>::new();
+        let mut sks = Vec::new();
+        for pid in 0..11 {
+            let (pk, sk) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+            assert!(registry.register(&par, 100 + pid, pk));
+            sks.push(sk);
+        }
+        let root = registry.root();
+        let vpar = par.verifier_parameters();
+
+        let i = 4;
+        let lseed = <Jack as LotteryScheme>::sample_seed(&mut rng, &par, i);
+        let winners = [110, 103, 104];
+        let mut tickets = Vec::new();
+        for pid in winners {
+            let pk = registry.get(pid).unwrap();
+            let sk = &sks[pid as usize - 100];
+            let ticket = <Jack as LotteryScheme>::get_ticket(&par, i, &lseed, pid, sk, pk);
+            tickets.push(ticket.unwrap());
+        }
+        let tickets_r: Vec<_> = tickets.iter().collect();
+        let cert = Jack::certify_winners(&par, &registry, i, &lseed, &winners, &tickets_r).unwrap();
+        assert_eq!(cert.pids, vec![103, 104, 110]);
+        assert!(Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+        // at least, but not more than three users won
+        assert!(Jack::verify_certificate(&vpar, &root, i, &lseed, 1, &cert));
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 4, &cert));
+        assert!(!Jack::verify_certificate(
+            &vpar,
+            &root,
+            i + 1,
+            &lseed,
+            3,
+            &cert
+        ));
+
+        // serialize and read the certificate again
+        let mut bytes = Vec::new();
+        cert.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), cert.serialized_size());
+        let mut cert = WinnerCertificate::<F, VC>::deserialize(&bytes[..]).unwrap();
+        assert!(Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+
+        // the root changes when another key is registered
+        let (pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        assert!(registry.register(&par, 99, pk));
+        assert_ne!(registry.root(), root);
+        assert!(!Jack::verify_certificate(
+            &vpar,
+            &registry.root(),
+            i,
+            &lseed,
+            3,
+            &cert
+        ));
+
+        // winners must be registered with their identifier,
+        // key and index, and the number of keys must match
+        cert.pids[0] = 105;
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+        cert.pids[0] = 103;
+        cert.num_keys += 1;
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+        cert.num_keys -= 1;
+        cert.proof.winners = WinnerSet::from_indices(&[3, 4, 9]);
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+        cert.proof.winners = WinnerSet::from_indices(&[3, 4, 10]);
+        let (pk, _) = <Jack as LotteryScheme>::gen(&mut rng, &par);
+        cert.pks[2] = pk;
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 3, &cert));
+        cert.pks.pop();
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 2, &cert));
+        // indices beyond the number of keys are rejected before decoding them
+        cert.proof.winners =
+            WinnerSet::Deltas(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert!(!Jack::verify_certificate(&vpar, &root, i, &lseed, 2, &cert));
+    }
+}
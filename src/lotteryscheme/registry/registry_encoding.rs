@@ -0,0 +1,325 @@
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+
+/// Compact encoding of a set of registry indices.
+/// Bit j (in byte j/8, least significant bit first)
+/// is set if and only if index j is in the set
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct WinnerBitmap {
+    /// the bits, without trailing zero bytes
+    bits: Vec<u8>,
+}
+
+impl WinnerBitmap {
+    /// Encode the given set of indices
+    pub fn from_indices(indices: &[usize]) -> Self {
+        let len = indices.iter().map(|j| j / 8 + 1).max().unwrap_or(0);
+        let mut bits = vec![0x00; len];
+        for j in indices {
+            bits[j / 8] |= 1 << (j % 8);
+        }
+        WinnerBitmap { bits }
+    }
+
+    /// Decode the set of indices, in increasing order
+    pub fn indices(&self) -> Vec<usize> {
+        let mut indices = Vec::new();
+        for (b, byte) in self.bits.iter().enumerate() {
+            for t in 0..8 {
+                if byte & (1 << t) != 0 {
+                    indices.push(8 * b + t);
+                }
+            }
+        }
+        indices
+    }
+
+    /// Check if index j is in the set
+    pub fn contains(&self, j: usize) -> bool {
+        j / 8 < self.bits.len() && self.bits[j / 8] & (1 << (j % 8)) != 0
+    }
+
+    /// Number of indices in the set
+    pub fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// The raw bits
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+/// Encoding of a set of registry indices that is as short as possible.
+/// Sparse sets are encoded by their sorted deltas, dense sets by a bitmap
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum WinnerSet {
+    /// the set as a bitmap
+    Bitmap(WinnerBitmap),
+    /// the sorted indices, where each index is encoded as a
+    /// varint of its distance to the previous index, minus one
+    Deltas(Vec<u8>),
+}
+
+impl WinnerSet {
+    const TAG_BITMAP: u8 = 0;
+    const TAG_DELTAS: u8 = 1;
+
+    /// Encode the given set of indices,
+    /// choosing the shorter encoding
+    pub fn from_indices(indices: &[usize]) -> Self {
+        let bitmap = WinnerBitmap::from_indices(indices);
+        let mut deltas = Vec::new();
+        let mut next = 0;
+        for j in bitmap.indices() {
+            write_varint(&mut deltas, (j - next) as u64);
+            next = j + 1;
+        }
+        if deltas.len() < bitmap.as_bytes().len() {
+            WinnerSet::Deltas(deltas)
+        } else {
+            WinnerSet::Bitmap(bitmap)
+        }
+    }
+
+    /// Decode the set of indices, in increasing order.
+    /// Returns None if the encoding is malformed
+    pub fn indices(&self) -> Option<Vec<usize>> {
+        self.indices_bounded(usize::MAX)
+    }
+
+    /// Decode the set of indices, in increasing order, if all of them are
+    /// less than n, e.g., the number of registered keys. Returns None if the
+    /// encoding is malformed or contains a larger index. Untrusted sets should
+    /// be decoded this way, as a single delta may encode an index close to 2^64
+    pub fn indices_bounded(&self, n: usize) -> Option<Vec<usize>> {
+        match self {
+            WinnerSet::Bitmap(bitmap) => {
+                let indices = bitmap.indices();
+                match indices.last() {
+                    Some(j) if *j >= n => None,
+                    _ => Some(indices),
+                }
+            }
+            WinnerSet::Deltas(deltas) => {
+                let mut indices = Vec::new();
+                let mut next: usize = 0;
+                let mut bytes = &deltas[..];
+                while !bytes.is_empty() {
+                    let delta = usize::try_from(read_varint(&mut bytes)?).ok()?;
+                    let j = next.checked_add(delta)?;
+                    if j >= n {
+                        return None;
+                    }
+                    indices.push(j);
+                    next = j + 1;
+                }
+                Some(indices)
+            }
+        }
+    }
+
+    /// Convert into a bitmap, whose length is given by the largest index.
+    /// Returns None if the encoding is malformed. Untrusted sets should be
+    /// bounded with indices_bounded first, as the bitmap may be huge
+    pub fn to_bitmap(&self) -> Option<WinnerBitmap> {
+        match self {
+            WinnerSet::Bitmap(bitmap) => Some(bitmap.clone()),
+            WinnerSet::Deltas(_) => Some(WinnerBitmap::from_indices(&self.indices()?)),
+        }
+    }
+
+    fn payload(&self) -> (u8, &[u8]) {
+        match self {
+            WinnerSet::Bitmap(bitmap) => (Self::TAG_BITMAP, bitmap.as_bytes()),
+            WinnerSet::Deltas(deltas) => (Self::TAG_DELTAS, deltas),
+        }
+    }
+}
+
+/// Append x to buf in LEB128 encoding
+fn write_varint(buf: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        buf.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    buf.push(x as u8);
+}
+
+/// Read a LEB128 encoded integer from the front of bytes.
+/// Returns None if it is truncated, too long or not minimal
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut x: u64 = 0;
+    for t in 0..10 {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let bits = (byte & 0x7f) as u64;
+        if t == 9 && bits > 1 {
+            return None;
+        }
+        x |= bits << (7 * t);
+        if byte & 0x80 == 0 {
+            if t > 0 && byte == 0 {
+                return None;
+            }
+            return Some(x);
+        }
+    }
+    None
+}
+
+/// Serialized as a tag byte, the varint length
+/// of the payload and the payload itself
+impl CanonicalSerialize for WinnerSet {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        _compress: Compress,
+    ) -> Result<(), SerializationError> {
+        let (tag, payload) = self.payload();
+        let mut buf = vec![tag];
+        write_varint(&mut buf, payload.len() as u64);
+        buf.extend_from_slice(payload);
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    fn serialized_size(&self, _compress: Compress) -> usize {
+        let (_, payload) = self.payload();
+        let mut buf = Vec::new();
+        write_varint(&mut buf, payload.len() as u64);
+        1 + buf.len() + payload.len()
+    }
+}
+
+impl Valid for WinnerSet {
+    fn check(&self) -> Result<(), SerializationError> {
+        let canonical = match self {
+            WinnerSet::Bitmap(bitmap) => bitmap.as_bytes().last() != Some(&0),
+            WinnerSet::Deltas(_) => self.indices().is_some(),
+        };
+        if canonical {
+            Ok(())
+        } else {
+            Err(SerializationError::InvalidData)
+        }
+    }
+}
+
+impl CanonicalDeserialize for WinnerSet {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        _compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        let tag = byte[0];
+        // read the length byte by byte
+        let mut len_bytes = Vec::new();
+        loop {
+            reader.read_exact(&mut byte)?;
+            len_bytes.push(byte[0]);
+            if byte[0] & 0x80 == 0 || len_bytes.len() == 10 {
+                break;
+            }
+        }
+        let len = read_varint(&mut &len_bytes[..]).ok_or(SerializationError::InvalidData)?;
+        let mut payload = Vec::new();
+        reader.take(len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != len {
+            return Err(SerializationError::InvalidData);
+        }
+        let set = match tag {
+            Self::TAG_BITMAP => WinnerSet::Bitmap(WinnerBitmap { bits: payload }),
+            Self::TAG_DELTAS => WinnerSet::Deltas(payload),
+            _ => return Err(SerializationError::InvalidData),
+        };
+        if validate == Validate::Yes {
+            set.check()?;
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+    use super::{WinnerBitmap, WinnerSet};
+
+    /// test that bitmaps encode and decode sets of indices
+    #[test]
+    fn registry_test_bitmap() {
+        let indices = vec![0, 3, 8, 9, 17];
+        let bitmap = WinnerBitmap::from_indices(&indices);
+        assert_eq!(bitmap.as_bytes().len(), 3);
+        assert_eq!(bitmap.indices(), indices);
+        assert_eq!(bitmap.count(), 5);
+        assert!(bitmap.contains(9));
+        assert!(!bitmap.contains(10));
+        assert!(!bitmap.contains(100));
+        // order and duplicates do not matter
+        assert_eq!(WinnerBitmap::from_indices(&[17, 9, 8, 3, 0, 3]), bitmap);
+        assert_eq!(
+            WinnerBitmap::from_indices(&[]).indices(),
+            Vec::<usize>::new()
+        );
+    }
+
+    /// test that winner sets choose the shorter encoding
+    /// and that malformed encodings are rejected
+    #[test]
+    fn registry_test_winner_set() {
+        // sparse sets are encoded by deltas
+        let sparse = vec![3, 200, 201, 5000];
+        let set = WinnerSet::from_indices(&sparse);
+        assert!(matches!(set, WinnerSet::Deltas(_)));
+        assert_eq!(set.indices().unwrap(), sparse);
+        assert_eq!(
+            set.to_bitmap().unwrap(),
+            WinnerBitmap::from_indices(&sparse)
+        );
+        // dense sets are encoded by bitmaps
+        let dense: Vec<usize> = (0..64).filter(|j| j % 3 != 0).collect();
+        let set = WinnerSet::from_indices(&dense);
+        assert!(matches!(set, WinnerSet::Bitmap(_)));
+        assert_eq!(set.indices().unwrap(), dense);
+        // serialization round trip
+        for indices in [sparse, dense, vec![]] {
+            let set = WinnerSet::from_indices(&indices);
+            let mut bytes = Vec::new();
+            set.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), set.compressed_size());
+            let read = WinnerSet::deserialize_compressed(&bytes[..]).unwrap();
+            assert_eq!(read, set);
+        }
+        // truncated and non-minimal varints, unknown tags, trailing zeros
+        assert!(WinnerSet::Deltas(vec![0x80]).indices().is_none());
+        assert!(WinnerSet::Deltas(vec![0x80, 0x00]).indices().is_none());
+        assert!(WinnerSet::deserialize_compressed(&[2u8, 0][..]).is_err());
+        assert!(WinnerSet::deserialize_compressed(&[0u8, 2, 1, 0][..]).is_err());
+        assert!(WinnerSet::deserialize_compressed(&[1u8, 1, 0x80][..]).is_err());
+        assert!(WinnerSet::deserialize_compressed(&[1u8, 3, 0][..]).is_err());
+
+        // indices can be bounded, e.g., by the number of registered keys
+        let set = WinnerSet::from_indices(&[3, 200, 201, 5000]);
+        assert_eq!(set.indices_bounded(5001).unwrap(), vec![3, 200, 201, 5000]);
+        assert!(set.indices_bounded(5000).is_none());
+        let dense: Vec<usize> = (0..64).filter(|j| j % 3 != 0).collect();
+        let set = WinnerSet::from_indices(&dense);
+        assert_eq!(set.indices_bounded(63).unwrap(), dense);
+        assert!(set.indices_bounded(62).is_none());
+        // a single delta that encodes an index close to 2^63
+        let huge = WinnerSet::Deltas(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert!(huge.indices_bounded(1 << 20).is_none());
+        assert!(WinnerSet::Deltas(vec![0x00, 0xff, 0x7f])
+            .indices_bounded(100)
+            .is_none());
+    }
+}
@@ -1,19 +1,40 @@
 use ark_ff::Field;
-use ark_serialize::CanonicalSerialize;
-use sha2::{Digest, Sha256};
-use std::marker::PhantomData;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::collections::{BTreeMap, BTreeSet};
+use ark_std::fmt;
+use ark_std::hash::{Hash, Hasher};
+use ark_std::marker::PhantomData;
+use ark_std::ops::Range;
+use ark_std::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::path::Path;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
+use super::challenge::{ChallengeHasher, Sha256Hasher};
 use super::LotteryScheme;
-use crate::vectorcommitment::VectorCommitmentScheme;
+#[cfg(feature = "std")]
+use crate::keystore::{self, KdfParams};
+use crate::memlock::MemoryLock;
+use crate::vectorcommitment::{CrossAggregation, MergeableAggregation, VectorCommitmentScheme};
 
 /// Implementation of a lottery scheme from
 /// any vector commitment (VC).
 /// Note: Paper showed that if the VC is
 /// simulation-extractable, than this
-/// gives us a secure lottery protocol
-pub struct VCLotteryScheme<F: Field, VC: VectorCommitmentScheme<F>> {
+/// gives us a secure lottery protocol.
+/// Challenges are computed using hash function H
+pub struct VCLotteryScheme<
+    F: Field,
+    VC: VectorCommitmentScheme<F>,
+    H: ChallengeHasher<F, VC::Commitment> = Sha256Hasher,
+> {
     _f: PhantomData<F>,
     _vc: PhantomData<VC>,
+    _h: PhantomData<H>,
 }
 pub struct Parameters<F: Field, VC: VectorCommitmentScheme<F>> {
     pub ck: VC::CommitmentKey,
@@ -21,55 +42,358 @@ pub struct Parameters<F: Field, VC: VectorCommitmentScheme<F>> {
     pub k: u32,
     pub log_k: u32,
 }
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Parameters<F, VC> {
+    /// Returns the parameters that are needed to verify keys
+    /// and tickets, with a verifier key that is as small as possible
+    pub fn verifier_parameters(&self) -> VerifierParameters<F, VC> {
+        VerifierParameters {
+            vk: VC::light_verifier_key(&self.ck),
+            num_lotteries: self.num_lotteries,
+            k: self.k,
+            log_k: self.log_k,
+        }
+    }
+}
+
+/// Parameters that only allow to verify keys and tickets,
+/// e.g., for light clients that never generate keys or tickets
+pub struct VerifierParameters<F: Field, VC: VectorCommitmentScheme<F>> {
+    pub vk: VC::VerifierKey,
+    pub num_lotteries: usize,
+    pub k: u32,
+    pub log_k: u32,
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalSerialize for VerifierParameters<F, VC> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.vk.serialize_with_mode(&mut writer, compress)?;
+        self.num_lotteries
+            .serialize_with_mode(&mut writer, compress)?;
+        self.k.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.vk.serialized_size(compress)
+            + self.num_lotteries.serialized_size(compress)
+            + self.k.serialized_size(compress)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Valid for VerifierParameters<F, VC> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.vk.check()
+    }
+}
+
+// log_k is not serialized, but recomputed from k
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalDeserialize for VerifierParameters<F, VC> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let vk = VC::VerifierKey::deserialize_with_mode(&mut reader, compress, validate)?;
+        let num_lotteries = usize::deserialize_with_mode(&mut reader, compress, validate)?;
+        let k = u32::deserialize_with_mode(&mut reader, compress, validate)?;
+        if !k.is_power_of_two() {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self {
+            vk,
+            num_lotteries,
+            k,
+            log_k: k.trailing_zeros(),
+        })
+    }
+}
+
 pub struct PublicKey<F: Field, VC: VectorCommitmentScheme<F>> {
     pub com: VC::Commitment,
 }
+/// secret key, which is zeroized on drop and does not
+/// reveal its content when formatted with Debug
+#[derive(Zeroize, ZeroizeOnDrop)]
 pub struct SecretKey<F: Field, VC: VectorCommitmentScheme<F>> {
+    #[zeroize(skip)]
+    pub(crate) lock: MemoryLock,
     pub v: Vec<F>,
     pub state: VC::State,
 }
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> fmt::Debug for SecretKey<F, VC> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+/// kind of keystores containing secret keys of VC-based lotteries
+pub const SECRET_KEY_KIND: &str = "vc-lottery-secret-key";
+
+// the secret key is serialized without the lock, which
+// is acquired again for v when deserializing
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalSerialize for SecretKey<F, VC>
+where
+    VC::State: CanonicalSerialize,
+{
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.v.serialize_with_mode(&mut writer, compress)?;
+        self.state.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.v.serialized_size(compress) + self.state.serialized_size(compress)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Valid for SecretKey<F, VC>
+where
+    VC::State: Valid,
+{
+    fn check(&self) -> Result<(), SerializationError> {
+        self.v.check()?;
+        self.state.check()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalDeserialize for SecretKey<F, VC>
+where
+    VC::State: CanonicalDeserialize,
+{
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let v = Vec::<F>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let state = VC::State::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            lock: MemoryLock::new(&v),
+            v,
+            state,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: Field, VC: VectorCommitmentScheme<F>> SecretKey<F, VC>
+where
+    VC::State: CanonicalSerialize + CanonicalDeserialize,
+{
+    /// Encrypt the secret key, including precomputed openings
+    /// if there are any, under the given password and write
+    /// it to the file at path. Returns whether this succeeded
+    pub fn save_encrypted<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+        path: &Path,
+        password: &str,
+        params: &KdfParams,
+    ) -> bool {
+        keystore::save_encrypted(rng, self, SECRET_KEY_KIND, path, password, params)
+    }
+
+    /// Read a secret key from the file at path and decrypt it
+    /// with the given password. Returns None if this fails
+    pub fn load_encrypted(path: &Path, password: &str) -> Option<Self> {
+        keystore::load_encrypted(SECRET_KEY_KIND, path, password)
+    }
+}
+/// A (possibly aggregated) ticket. As winners are aggregated in the order
+/// of their identifiers, the encoding of an aggregated ticket only depends
+/// on the set of winners. Tickets are equal if their encodings are equal,
+/// and hash accordingly, so that identical proofs can be deduplicated
 pub struct Ticket<F: Field, VC: VectorCommitmentScheme<F>> {
     pub opening: VC::Opening,
 }
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Ticket<F, VC> {
+    /// Returns the canonical encoding of the ticket,
+    /// which is the compressed serialization of the opening
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.opening.compressed_size());
+        self.opening
+            .serialize_compressed(&mut bytes)
+            .expect("Failed to serialize ticket");
+        bytes
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> PartialEq for Ticket<F, VC> {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Eq for Ticket<F, VC> {}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Hash for Ticket<F, VC> {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.to_bytes().hash(state);
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> fmt::Debug for Ticket<F, VC> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ticket({})", hex::encode(self.to_bytes()))
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalSerialize for Ticket<F, VC> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.opening.serialize_with_mode(writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.opening.serialized_size(compress)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> Valid for Ticket<F, VC> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.opening.check()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>> CanonicalDeserialize for Ticket<F, VC> {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let opening = VC::Opening::deserialize_with_mode(reader, compress, validate)?;
+        Ok(Self { opening })
+    }
+}
+/// A ticket that aggregates winning tickets
+/// of several (possibly different) lotteries
+pub struct CrossTicket<F: Field, VC: CrossAggregation<F>> {
+    pub opening: VC::CrossOpening,
+}
+/// A ticket that aggregates winning tickets of the same lottery
+/// such that it can be merged with other such tickets later.
+/// It keeps track of the identifiers of the winners in the order
+/// in which they have been aggregated, i.e., sorted within each
+/// aggregate, and the aggregates in the order of merging
+pub struct MergeableTicket<F: Field, VC: MergeableAggregation<F>> {
+    pub pids: Vec<u32>,
+    pub opening: VC::MergeableOpening,
+}
+/// Output of aggregate_checked: the aggregate of all valid tickets, if any,
+/// the sorted identifiers of their owners, and the sorted identifiers
+/// of the users whose tickets are invalid
+pub struct CheckedAggregate<F: Field, VC: VectorCommitmentScheme<F>> {
+    pub ticket: Option<Ticket<F, VC>>,
+    pub pids: Vec<u32>,
+    pub invalid: Vec<u32>,
+}
 pub type LotterySeed = [u8; 32];
 
+/// A public key together with the data that
+/// verifiers cache when the key is registered
+struct PreparedKey<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>> {
+    pk: PublicKey<F, VC>,
+    /// uncompressed serialization of the commitment
+    com_ser: Vec<u8>,
+    /// hash state after absorbing the public key
+    midstate: H::Midstate,
+}
+
+/// Context for verifying tickets of registered users.
+/// It caches serialized public keys and hash states at
+/// registration time, so that verifying large aggregates
+/// does not serialize and hash all public keys again
+pub struct VerifierContext<
+    F: Field,
+    VC: VectorCommitmentScheme<F>,
+    H: ChallengeHasher<F, VC::Commitment> = Sha256Hasher,
+> {
+    keys: BTreeMap<u32, PreparedKey<F, VC, H>>,
+}
+
 /// outputs the challenge x = H(pk,pid,i,lseed)
 /// for a specific user and lottery round
 #[inline]
-fn get_challenge<F: Field, VC: VectorCommitmentScheme<F>>(
+pub(crate) fn get_challenge<
+    F: Field,
+    VC: VectorCommitmentScheme<F>,
+    H: ChallengeHasher<F, VC::Commitment>,
+>(
     log_k: u32,
     pk: &PublicKey<F, VC>,
     pid: u32,
     i: u32,
     lseed: &LotterySeed,
 ) -> F {
-    // x = H(pk,pid,i,lseed)
-    let mut hasher = Sha256::new_with_prefix("Chall//".as_bytes());
+    H::challenge(log_k, &H::midstate(&pk.com), pid, i, lseed)
+}
+
+/// outputs the uncompressed serialization of the commitment of pk
+pub(crate) fn serialize_key<F: Field, VC: VectorCommitmentScheme<F>>(
+    pk: &PublicKey<F, VC>,
+) -> Vec<u8> {
     let mut pk_ser = Vec::new();
     pk.com
         .serialize_uncompressed(&mut pk_ser)
-        .expect("Failed to serialize public key in get_challenge");
-    hasher.update(pk_ser);
-    hasher.update(pid.to_be_bytes());
-    hasher.update(i.to_be_bytes());
-    hasher.update(lseed);
-
-    let digest = hasher.finalize();
-    // we take the first log_k bits and interpret as an integer
-    // for that, first find out how many bytes we use entirely
-    assert!(log_k <= 32 * 8);
-    let num_fullbytes = (log_k >> 3) as usize;
-    let mut hashbytes: Vec<u8> = vec![0x00; num_fullbytes + 1];
-    for j in 0..num_fullbytes {
-        hashbytes[j] = digest[j];
-    }
-    // for the final byte we only need a part of it
-    let nextbyte = digest[num_fullbytes];
-    let expected = log_k & 0x07;
-    let mask = (1 << expected) - 1;
-    hashbytes[num_fullbytes] = nextbyte & mask;
-    // interpret as field element
-    F::from_random_bytes(&hashbytes).unwrap()
+        .expect("Failed to serialize public key");
+    pk_ser
+}
+
+/// returns the order in which the users with identifiers pids[j] and
+/// serialized public keys pks_ser[j] are aggregated, i.e., sorted by
+/// identifier, so that aggregates do not depend on the order of the input.
+/// Returns None if an identifier or a public key occurs twice,
+/// as the same user must not be counted as a winner twice
+fn canonical_order<S: AsRef<[u8]>>(pids: &[u32], pks_ser: &[S]) -> Option<Vec<usize>> {
+    let mut order: Vec<usize> = (0..pids.len()).collect();
+    order.sort_unstable_by_key(|j| pids[*j]);
+    if order.windows(2).any(|w| pids[w[0]] == pids[w[1]]) {
+        return None;
+    }
+    let mut keys = BTreeSet::new();
+    if !pks_ser.iter().all(|pk_ser| keys.insert(pk_ser.as_ref())) {
+        return None;
+    }
+    Some(order)
+}
+
+/// returns true if, within each lottery, no identifier and no public
+/// key occurs twice, where the jth user takes part in the is[j]th lottery.
+/// The same user may win several lotteries, though
+fn distinct_per_lottery<S: AsRef<[u8]>>(is: &[u32], pids: &[u32], pks_ser: &[S]) -> bool {
+    let mut lotteries: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (j, i) in is.iter().enumerate() {
+        lotteries.entry(*i).or_default().push(j);
+    }
+    lotteries.values().all(|js| {
+        let lottery_pids: Vec<u32> = js.iter().map(|j| pids[*j]).collect();
+        let lottery_pks: Vec<&[u8]> = js.iter().map(|j| pks_ser[*j].as_ref()).collect();
+        canonical_order(&lottery_pids, &lottery_pks).is_some()
+    })
+}
+
+/// compares field elements in constant time, based on their
+/// serialization, so that this does not leak whether they are equal
+fn ct_eq_field<F: Field>(a: &F, b: &F) -> Choice {
+    let mut a_ser = Zeroizing::new(Vec::new());
+    let mut b_ser = Zeroizing::new(Vec::new());
+    a.serialize_uncompressed(&mut *a_ser)
+        .expect("Failed to serialize field element in ct_eq_field");
+    b.serialize_uncompressed(&mut *b_ser)
+        .expect("Failed to serialize field element in ct_eq_field");
+    a_ser.as_slice().ct_eq(b_ser.as_slice())
 }
 
 /// returns a random vector of length n of F where
@@ -84,7 +408,9 @@ fn get_random_field_vec<R: rand::Rng, F: Field>(rng: &mut R, k: u32, n: usize) -
         .collect()
 }
 
-impl<F: Field, VC: VectorCommitmentScheme<F>> LotteryScheme for VCLotteryScheme<F, VC> {
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>> LotteryScheme
+    for VCLotteryScheme<F, VC, H>
+{
     type Parameters = Parameters<F, VC>;
     type PublicKey = PublicKey<F, VC>;
     type SecretKey = SecretKey<F, VC>;
@@ -119,12 +445,16 @@ impl<F: Field, VC: VectorCommitmentScheme<F>> LotteryScheme for VCLotteryScheme<
         let v = get_random_field_vec(rng, par.k, par.num_lotteries);
         let (com, state) = VC::commit(rng, &par.ck, &v);
         let pk = Self::PublicKey { com };
-        let sk = Self::SecretKey { v, state };
+        let sk = Self::SecretKey {
+            lock: MemoryLock::new(&v),
+            v,
+            state,
+        };
         (pk, sk)
     }
 
     fn verify_key(par: &Self::Parameters, pk: &Self::PublicKey) -> bool {
-        VC::verify_commitment(&par.ck, &pk.com)
+        VC::verify_commitment(VC::verifier_key(&par.ck), &pk.com)
     }
 
     fn sample_seed<R: rand::Rng>(
@@ -146,9 +476,12 @@ impl<F: Field, VC: VectorCommitmentScheme<F>> LotteryScheme for VCLotteryScheme<
         pk: &Self::PublicKey,
     ) -> bool {
         // get a challenge
-        let x = get_challenge(par.log_k, pk, pid, i, lseed);
-        // we win if x = v_i
-        i as usize <= sk.v.len() && sk.v[i as usize] == x
+        let x = get_challenge::<F, VC, H>(par.log_k, pk, pid, i, lseed);
+        // we win if x = v_i, which we check in constant time
+        match sk.v.get(i as usize) {
+            Some(vi) => ct_eq_field(vi, &x).into(),
+            None => false,
+        }
     }
 
     fn get_ticket(
@@ -168,22 +501,29 @@ impl<F: Field, VC: VectorCommitmentScheme<F>> LotteryScheme for VCLotteryScheme<
         par: &Self::Parameters,
         i: u32,
         lseed: &Self::LotterySeed,
-        pids: &Vec<u32>,
-        pks: &Vec<Self::PublicKey>,
-        tickets: &Vec<Self::Ticket>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
+        tickets: &[Self::Ticket],
     ) -> Option<Self::Ticket> {
         if pids.len() != pks.len() || pids.len() != tickets.len() {
             return None;
         }
-        let l = pids.len();
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(serialize_key).collect();
+        let order = canonical_order(pids, &pks_ser)?;
 
         // compute the challenge for each party
         // and collect commitments and openings for each party
         let mut xs = Vec::new();
         let mut coms = Vec::new();
         let mut openings = Vec::new();
-        for j in 0..l {
-            xs.push(get_challenge(par.log_k, &pks[j], pids[j], i, lseed));
+        for j in order {
+            xs.push(H::challenge(
+                par.log_k,
+                &H::midstate(&pks[j].com),
+                pids[j],
+                i,
+                lseed,
+            ));
             coms.push(&pks[j].com);
             openings.push(&tickets[j].opening);
         }
@@ -197,25 +537,500 @@ impl<F: Field, VC: VectorCommitmentScheme<F>> LotteryScheme for VCLotteryScheme<
         par: &Self::Parameters,
         i: u32,
         lseed: &Self::LotterySeed,
-        pids: &Vec<u32>,
-        pks: &Vec<Self::PublicKey>,
+        pids: &[u32],
+        pks: &[Self::PublicKey],
         ticket: &Self::Ticket,
+    ) -> bool {
+        let vk = VC::verifier_key(&par.ck);
+        Self::verify_with_key(vk, par.log_k, i, lseed, pids, pks, ticket)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Verify that the public key pk is well-formed,
+    /// given only the verifier parameters
+    pub fn verify_key_light(vpar: &VerifierParameters<F, VC>, pk: &PublicKey<F, VC>) -> bool {
+        VC::verify_commitment(&vpar.vk, &pk.com)
+    }
+
+    /// Verify a ticket like verify, given only the verifier parameters
+    pub fn verify_light(
+        vpar: &VerifierParameters<F, VC>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &[PublicKey<F, VC>],
+        ticket: &Ticket<F, VC>,
+    ) -> bool {
+        Self::verify_with_key(&vpar.vk, vpar.log_k, i, lseed, pids, pks, ticket)
+    }
+
+    /// verifies a ticket using only the verifier key vk
+    fn verify_with_key(
+        vk: &VC::VerifierKey,
+        log_k: u32,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &[PublicKey<F, VC>],
+        ticket: &Ticket<F, VC>,
     ) -> bool {
         if pids.len() != pks.len() {
             return false;
         }
-        let l = pids.len();
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(serialize_key).collect();
+        let order = match canonical_order(pids, &pks_ser) {
+            Some(order) => order,
+            None => return false,
+        };
 
         // compute the challenge for each party
         // and collect commitments for each party
         let mut xs = Vec::new();
         let mut coms = Vec::new();
+        for j in order {
+            xs.push(H::challenge(
+                log_k,
+                &H::midstate(&pks[j].com),
+                pids[j],
+                i,
+                lseed,
+            ));
+            coms.push(&pks[j].com);
+        }
+
+        // verify the aggregate opening
+        VC::verify(vk, i, &xs, &coms, &ticket.opening)
+    }
+}
+
+impl<F: Field, VC: CrossAggregation<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Aggregate tickets tickets[j] of users with identifiers pids[j]
+    /// and public keys pks[j] for the is[j]th lottery with seed lseeds[j].
+    /// In contrast to aggregate, the tickets may be for different lotteries.
+    /// For KZG, the size of the output grows linearly with the number
+    /// of distinct lotteries, but not with the number of tickets
+    pub fn aggregate_cross(
+        par: &Parameters<F, VC>,
+        is: &[u32],
+        lseeds: &[LotterySeed],
+        pids: &[u32],
+        pks: &[&PublicKey<F, VC>],
+        tickets: &[&Ticket<F, VC>],
+    ) -> Option<CrossTicket<F, VC>> {
+        let l = is.len();
+        if l != lseeds.len() || l != pids.len() || l != pks.len() || l != tickets.len() {
+            return None;
+        }
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(|pk| serialize_key(pk)).collect();
+        if !distinct_per_lottery(is, pids, &pks_ser) {
+            return None;
+        }
+
+        // compute the challenge for each party in its lottery
+        // and collect commitments and openings for each party
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        let mut openings = Vec::new();
         for j in 0..l {
-            xs.push(get_challenge(par.log_k, &pks[j], pids[j], i, lseed));
+            xs.push(get_challenge::<F, VC, H>(
+                par.log_k, pks[j], pids[j], is[j], &lseeds[j],
+            ));
             coms.push(&pks[j].com);
+            openings.push(&tickets[j].opening);
+        }
+
+        // let the vector commitment aggregate
+        let agg_op = VC::aggregate_cross(&par.ck, is, &xs, &coms, &openings);
+        agg_op.map(|tau| CrossTicket { opening: tau })
+    }
+
+    /// Verify a ticket output by aggregate_cross, i.e., verify that
+    /// users with identifiers pids[j] and public keys pks[j]
+    /// won the is[j]th lottery with seed lseeds[j]
+    pub fn verify_cross(
+        par: &Parameters<F, VC>,
+        is: &[u32],
+        lseeds: &[LotterySeed],
+        pids: &[u32],
+        pks: &[&PublicKey<F, VC>],
+        ticket: &CrossTicket<F, VC>,
+    ) -> bool {
+        let l = is.len();
+        if l != lseeds.len() || l != pids.len() || l != pks.len() {
+            return false;
+        }
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(|pk| serialize_key(pk)).collect();
+        if !distinct_per_lottery(is, pids, &pks_ser) {
+            return false;
+        }
+
+        // compute the challenge for each party in its lottery
+        // and collect commitments for each party
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        for j in 0..l {
+            xs.push(get_challenge::<F, VC, H>(
+                par.log_k, pks[j], pids[j], is[j], &lseeds[j],
+            ));
+            coms.push(&pks[j].com);
+        }
+
+        // verify the cross-aggregated opening
+        VC::verify_cross(VC::verifier_key(&par.ck), is, &xs, &coms, &ticket.opening)
+    }
+}
+
+/// returns the positions of all invalid openings within the given range.
+/// Openings are verified in a batch, and if the batch
+/// does not verify, both halves are searched recursively
+fn find_invalid<F: Field, VC: VectorCommitmentScheme<F>, R: rand::Rng>(
+    rng: &mut R,
+    vk: &VC::VerifierKey,
+    i: u32,
+    mis: &[F],
+    coms: &[&VC::Commitment],
+    openings: &[&VC::Opening],
+    range: Range<usize>,
+) -> Vec<usize> {
+    if range.is_empty() {
+        return Vec::new();
+    }
+    let (start, end) = (range.start, range.end);
+    if VC::batch_verify(
+        rng,
+        vk,
+        i,
+        &mis[start..end],
+        &coms[start..end],
+        &openings[start..end],
+    ) {
+        return Vec::new();
+    }
+    if range.len() == 1 {
+        return vec![range.start];
+    }
+    let mid = range.start + range.len() / 2;
+    let mut invalid = find_invalid::<F, VC, R>(rng, vk, i, mis, coms, openings, range.start..mid);
+    invalid.extend(find_invalid::<F, VC, R>(
+        rng,
+        vk,
+        i,
+        mis,
+        coms,
+        openings,
+        mid..range.end,
+    ));
+    invalid
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Aggregate tickets tickets[j] of users with identifiers pids[j]
+    /// and public keys pks[j] for the ith lottery, like aggregate, but
+    /// only the valid tickets. All tickets are verified in a batch,
+    /// and if the batch fails, the invalid ones are found by binary search.
+    /// Outputs None if a pid or a pk occurs twice
+    pub fn aggregate_checked<R: rand::Rng>(
+        rng: &mut R,
+        par: &Parameters<F, VC>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &[PublicKey<F, VC>],
+        tickets: &[Ticket<F, VC>],
+    ) -> Option<CheckedAggregate<F, VC>> {
+        if pids.len() != pks.len() || pids.len() != tickets.len() {
+            return None;
+        }
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(serialize_key).collect();
+        let order = canonical_order(pids, &pks_ser)?;
+
+        // compute the challenge for each party
+        // and collect commitments and openings for each party
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        let mut openings = Vec::new();
+        for j in &order {
+            let midstate = H::midstate(&pks[*j].com);
+            xs.push(H::challenge(par.log_k, &midstate, pids[*j], i, lseed));
+            coms.push(&pks[*j].com);
+            openings.push(&tickets[*j].opening);
+        }
+
+        // find the invalid tickets, which are sorted by their position
+        let invalid = find_invalid::<F, VC, R>(
+            rng,
+            VC::verifier_key(&par.ck),
+            i,
+            &xs,
+            &coms,
+            &openings,
+            0..xs.len(),
+        );
+
+        // aggregate the remaining tickets
+        let mut checked = CheckedAggregate {
+            ticket: None,
+            pids: Vec::new(),
+            invalid: invalid.iter().map(|p| pids[order[*p]]).collect(),
+        };
+        let mut valid_xs = Vec::new();
+        let mut valid_coms = Vec::new();
+        let mut valid_openings = Vec::new();
+        for (p, j) in order.iter().enumerate() {
+            if invalid.binary_search(&p).is_err() {
+                checked.pids.push(pids[*j]);
+                valid_xs.push(xs[p]);
+                valid_coms.push(coms[p]);
+                valid_openings.push(openings[p]);
+            }
+        }
+        if !checked.pids.is_empty() {
+            let agg_op = VC::aggregate(&par.ck, i, &valid_xs, &valid_coms, &valid_openings)?;
+            checked.ticket = Some(Ticket { opening: agg_op });
+        }
+        Some(checked)
+    }
+}
+
+/// returns the sorted identifiers, or None if there are duplicates
+fn sorted_pids(pids: &[u32]) -> Option<Vec<u32>> {
+    let mut sorted = pids.to_vec();
+    sorted.sort_unstable();
+    if sorted.windows(2).any(|w| w[0] == w[1]) {
+        return None;
+    }
+    Some(sorted)
+}
+
+impl<F: Field, VC: MergeableAggregation<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Aggregate tickets tickets[j] of users with identifiers pids[j]
+    /// and public keys pks[j] for the ith lottery, such that the
+    /// result can be merged with other aggregates for the same lottery.
+    /// In particular, a single ticket can be turned into such an aggregate
+    pub fn aggregate_mergeable(
+        par: &Parameters<F, VC>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &[&PublicKey<F, VC>],
+        tickets: &[&Ticket<F, VC>],
+    ) -> Option<MergeableTicket<F, VC>> {
+        if pids.len() != pks.len() || pids.len() != tickets.len() {
+            return None;
+        }
+        // every user can only be counted once
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(|pk| serialize_key(pk)).collect();
+        let order = canonical_order(pids, &pks_ser)?;
+
+        // compute the challenge for each party
+        // and collect commitments and openings for each party
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        let mut openings = Vec::new();
+        for j in order.iter().copied() {
+            xs.push(get_challenge::<F, VC, H>(
+                par.log_k, pks[j], pids[j], i, lseed,
+            ));
+            coms.push(&pks[j].com);
+            openings.push(&tickets[j].opening);
+        }
+
+        // let the vector commitment aggregate
+        let agg_op = VC::aggregate_mergeable(&par.ck, i, &xs, &coms, &openings);
+        agg_op.map(|tau| MergeableTicket {
+            pids: order.iter().map(|j| pids[*j]).collect(),
+            opening: tau,
+        })
+    }
+
+    /// Merge two aggregates for the same lottery.
+    /// Outputs None if the sets of winners are not disjoint
+    pub fn merge(
+        par: &Parameters<F, VC>,
+        agg1: &MergeableTicket<F, VC>,
+        agg2: &MergeableTicket<F, VC>,
+    ) -> Option<MergeableTicket<F, VC>> {
+        let pids = [agg1.pids.as_slice(), agg2.pids.as_slice()].concat();
+        sorted_pids(&pids)?;
+        let agg_op = VC::merge(&par.ck, &agg1.opening, &agg2.opening);
+        agg_op.map(|tau| MergeableTicket { pids, opening: tau })
+    }
+
+    /// Verify an aggregate output by aggregate_mergeable or merge
+    /// for the ith lottery with lottery seed lseed
+    /// For users with identifiers pids[j] and public keys pks[j]
+    pub fn verify_mergeable(
+        par: &Parameters<F, VC>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        pks: &[&PublicKey<F, VC>],
+        ticket: &MergeableTicket<F, VC>,
+    ) -> bool {
+        if pids.len() != pks.len() || pids.len() != ticket.pids.len() {
+            return false;
+        }
+        // the users must be exactly those that have been aggregated
+        let pks_ser: Vec<Vec<u8>> = pks.iter().map(|pk| serialize_key(pk)).collect();
+        let order = match canonical_order(pids, &pks_ser) {
+            Some(order) => order,
+            None => return false,
+        };
+        let sorted: Vec<u32> = order.iter().map(|j| pids[*j]).collect();
+        if sorted_pids(&ticket.pids).as_ref() != Some(&sorted) {
+            return false;
+        }
+
+        // compute the challenge for each party and collect
+        // commitments for each party, in the order of the ticket
+        let mut xs = Vec::new();
+        let mut coms = Vec::new();
+        for pid in &ticket.pids {
+            let j = match sorted.binary_search(pid) {
+                Ok(t) => order[t],
+                Err(_) => return false,
+            };
+            xs.push(get_challenge::<F, VC, H>(
+                par.log_k, pks[j], pids[j], i, lseed,
+            ));
+            coms.push(&pks[j].com);
+        }
+
+        // verify the aggregate opening
+        VC::verify_mergeable(VC::verifier_key(&par.ck), i, &xs, &coms, &ticket.opening)
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VerifierContext<F, VC, H>
+{
+    pub fn new() -> Self {
+        Self {
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Verify public key pk and register it for the user with identifier pid,
+    /// replacing the key that was registered for pid before, if any.
+    /// Returns false if the key is invalid, in which case the context is not changed
+    pub fn register(&mut self, par: &Parameters<F, VC>, pid: u32, pk: PublicKey<F, VC>) -> bool {
+        if !<VCLotteryScheme<F, VC, H> as LotteryScheme>::verify_key(par, &pk) {
+            return false;
+        }
+        self.insert(pid, pk);
+        true
+    }
+
+    /// Register a key without verifying it. It must have been
+    /// verified before or come from trusted storage.
+    /// Returns the key that was registered for pid before, if any
+    pub(super) fn insert(&mut self, pid: u32, pk: PublicKey<F, VC>) -> Option<PublicKey<F, VC>> {
+        let mut com_ser = Vec::new();
+        pk.com
+            .serialize_uncompressed(&mut com_ser)
+            .expect("Failed to serialize public key in insert");
+        let midstate = H::midstate(&pk.com);
+        let key = PreparedKey {
+            pk,
+            com_ser,
+            midstate,
+        };
+        self.keys.insert(pid, key).map(|old| old.pk)
+    }
+
+    /// Returns the public key registered for pid, if any
+    pub fn get(&self, pid: u32) -> Option<&PublicKey<F, VC>> {
+        self.keys.get(&pid).map(|key| &key.pk)
+    }
+
+    /// Returns the uncompressed serialization of
+    /// the commitment registered for pid, if any
+    #[cfg(feature = "std")]
+    pub(crate) fn serialized(&self, pid: u32) -> Option<&[u8]> {
+        self.keys.get(&pid).map(|key| key.com_ser.as_slice())
+    }
+
+    /// Returns the number of registered keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if no key is registered
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>> Default
+    for VerifierContext<F, VC, H>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Field, VC: VectorCommitmentScheme<F>, H: ChallengeHasher<F, VC::Commitment>>
+    VCLotteryScheme<F, VC, H>
+{
+    /// Verify ticket for the ith lottery with lottery seed lseed
+    /// for the users with identifiers pids[j], whose public
+    /// keys have been registered in the context ctx.
+    /// As registered keys have been verified, this is
+    /// equivalent to verify, but uses the cached data
+    pub fn verify_with_context(
+        par: &Parameters<F, VC>,
+        ctx: &VerifierContext<F, VC, H>,
+        i: u32,
+        lseed: &LotterySeed,
+        pids: &[u32],
+        ticket: &Ticket<F, VC>,
+    ) -> bool {
+        let keys: Option<Vec<&PreparedKey<F, VC, H>>> =
+            pids.iter().map(|pid| ctx.keys.get(pid)).collect();
+        let keys = match keys {
+            Some(keys) => keys,
+            None => return false,
+        };
+        let all_ser: Vec<&[u8]> = keys.iter().map(|key| key.com_ser.as_slice()).collect();
+        let order = match canonical_order(pids, &all_ser) {
+            Some(order) => order,
+            None => return false,
+        };
+
+        // compute the challenge for each party
+        // and collect (serialized) commitments for each party
+        let mut xs = Vec::with_capacity(pids.len());
+        let mut coms = Vec::with_capacity(pids.len());
+        let mut coms_ser = Vec::with_capacity(pids.len());
+        for j in order {
+            xs.push(H::challenge(
+                par.log_k,
+                &keys[j].midstate,
+                pids[j],
+                i,
+                lseed,
+            ));
+            coms.push(&keys[j].pk.com);
+            coms_ser.push(all_ser[j]);
         }
 
         // verify the aggregate opening
-        VC::verify(&par.ck, i, &xs, &coms, &ticket.opening)
+        VC::verify_serialized(
+            VC::verifier_key(&par.ck),
+            i,
+            &xs,
+            &coms,
+            &coms_ser,
+            &ticket.opening,
+        )
     }
 }
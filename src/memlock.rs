@@ -0,0 +1,56 @@
+/// Guard that keeps the memory pages of a secret buffer locked,
+/// so that they are not swapped to disk, and unlocks them on drop.
+/// With the feature "mlock" disabled, this does nothing.
+/// The buffer must not be moved or reallocated while the guard lives,
+/// which holds for vectors that are never resized. Locking is best-effort,
+/// e.g., it fails silently if the limit of locked memory is exceeded
+#[derive(Default)]
+pub struct MemoryLock {
+    #[cfg(feature = "mlock")]
+    region: Option<(usize, usize)>,
+}
+
+impl MemoryLock {
+    /// Lock the pages containing the given slice
+    #[cfg(feature = "mlock")]
+    pub fn new<T>(secret: &[T]) -> Self {
+        let size = std::mem::size_of_val(secret);
+        if size == 0 {
+            return Self::default();
+        }
+        // we only keep the address, so that the guard can be sent
+        // to other threads, in contrast to region::LockGuard
+        let region = region::lock(secret.as_ptr(), size).ok().map(|guard| {
+            std::mem::forget(guard);
+            (secret.as_ptr() as usize, size)
+        });
+        Self { region }
+    }
+
+    /// Lock the pages containing the given slice
+    #[cfg(not(feature = "mlock"))]
+    pub fn new<T>(_secret: &[T]) -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the pages are locked
+    #[cfg(feature = "mlock")]
+    pub fn is_locked(&self) -> bool {
+        self.region.is_some()
+    }
+
+    /// Returns whether the pages are locked
+    #[cfg(not(feature = "mlock"))]
+    pub fn is_locked(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "mlock")]
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        if let Some((address, size)) = self.region.take() {
+            let _ = region::unlock(address as *const u8, size);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use ark_ff::field_hashers::{DefaultFieldHasher, HashToField};
+use ark_ff::Field;
+use ark_serialize::CanonicalSerialize;
+use ark_std::{vec, vec::Vec};
+use sha2::digest::DynDigest;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// trait representing hash functions that can be used in transcripts,
+/// i.e., those that are supported by the IETF hash-to-field
+pub trait TranscriptHash: Digest + DynDigest + Default + Clone + 'static {}
+
+impl<H: Digest + DynDigest + Default + Clone + 'static> TranscriptHash for H {}
+
+/// security parameter of hash-to-field, so that
+/// each field element is reduced from 48+ bytes
+pub const HASH_TO_FIELD_SEC_PARAM: usize = 128;
+
+/// Fiat-Shamir transcript with labelled appends, in the style of merlin.
+/// Every message is absorbed together with its label and both lengths,
+/// so different sequences of messages never lead to the same hash input.
+/// Challenges are derived from the current state and then absorbed,
+/// so that all later challenges depend on them
+#[derive(Clone)]
+pub struct Transcript<H: TranscriptHash = Sha256> {
+    hasher: H,
+}
+
+/// transcript based on SHA-256, which is used by default
+pub type Sha256Transcript = Transcript<Sha256>;
+/// transcript based on Keccak-256
+pub type Keccak256Transcript = Transcript<Keccak256>;
+/// transcript based on BLAKE3
+pub type Blake3Transcript = Transcript<blake3::Hasher>;
+
+impl<H: TranscriptHash> Transcript<H> {
+    /// Start a new transcript for the protocol with the given label
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut transcript = Self {
+            hasher: <H as Digest>::new(),
+        };
+        transcript.append_message(b"dom-sep", label);
+        transcript
+    }
+
+    /// Append a message with the given label
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        Digest::update(&mut self.hasher, (label.len() as u32).to_le_bytes());
+        Digest::update(&mut self.hasher, label);
+        Digest::update(&mut self.hasher, (message.len() as u64).to_le_bytes());
+        Digest::update(&mut self.hasher, message);
+    }
+
+    /// Append an integer with the given label, as 4 bytes big-endian
+    pub fn append_u32(&mut self, label: &'static [u8], x: u32) {
+        self.append_message(label, &x.to_be_bytes());
+    }
+
+    /// Append the uncompressed serialization of x with the given label
+    pub fn append_serializable<T: CanonicalSerialize + ?Sized>(
+        &mut self,
+        label: &'static [u8],
+        x: &T,
+    ) {
+        let mut x_ser = Vec::new();
+        x.serialize_uncompressed(&mut x_ser)
+            .expect("Failed to serialize message in transcript");
+        self.append_message(label, &x_ser);
+    }
+
+    /// Fill dest with challenge bytes for the given label,
+    /// and append them to the transcript
+    pub fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        self.append_message(label, &(dest.len() as u64).to_le_bytes());
+        let blocks = dest.chunks_mut(<H as Digest>::output_size());
+        for (cnt, block) in blocks.enumerate() {
+            let mut hasher = self.hasher.clone();
+            Digest::update(&mut hasher, (cnt as u32).to_le_bytes());
+            let digest = Digest::finalize(hasher);
+            block.copy_from_slice(&digest[..block.len()]);
+        }
+        self.append_message(b"challenge", dest);
+    }
+
+    /// Compute a field element for the given label, and append it to the
+    /// transcript. The element is derived from fresh challenge bytes using
+    /// the IETF hash-to-field with expand_message_xmd, as DefaultFieldHasher
+    /// does, which takes constant time and is statistically close to uniform.
+    /// It is zero only with negligible probability
+    pub fn challenge_field<F: Field>(&mut self, label: &'static [u8]) -> F {
+        let mut seed = vec![0u8; <H as Digest>::output_size()];
+        self.challenge_bytes(label, &mut seed);
+        let hasher = <DefaultFieldHasher<H, HASH_TO_FIELD_SEC_PARAM> as HashToField<F>>::new(label);
+        hasher.hash_to_field(&seed, 1)[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Fr;
+    use ark_ff::field_hashers::{DefaultFieldHasher, HashToField};
+    use ark_ff::MontFp;
+    use sha2::Sha256;
+
+    use super::{
+        Blake3Transcript, Keccak256Transcript, Sha256Transcript, Transcript, TranscriptHash,
+    };
+
+    /// computes a challenge after appending the given messages
+    fn challenge<H: TranscriptHash>(messages: &[(&'static [u8], &[u8])]) -> [u8; 32] {
+        let mut transcript = Transcript::<H>::new(b"test");
+        for (label, message) in messages {
+            transcript.append_message(label, message);
+        }
+        let mut bytes = [0u8; 32];
+        transcript.challenge_bytes(b"x", &mut bytes);
+        bytes
+    }
+
+    /// test that labels and message boundaries are separated
+    #[test]
+    fn transcript_test_separation() {
+        let a = challenge::<sha2::Sha256>(&[(b"a", b"bc")]);
+        assert_ne!(a, challenge::<sha2::Sha256>(&[(b"ab", b"c")]));
+        assert_ne!(a, challenge::<sha2::Sha256>(&[(b"a", b"b"), (b"", b"c")]));
+        assert_ne!(a, challenge::<sha2::Sha256>(&[(b"b", b"bc")]));
+        assert_eq!(a, challenge::<sha2::Sha256>(&[(b"a", b"bc")]));
+        assert_ne!(a, challenge::<sha3::Keccak256>(&[(b"a", b"bc")]));
+        assert_ne!(a, challenge::<blake3::Hasher>(&[(b"a", b"bc")]));
+    }
+
+    /// test that challenges depend on earlier challenges
+    /// and that long challenges are not repetitive
+    #[test]
+    fn transcript_test_challenges() {
+        let mut transcript = Sha256Transcript::new(b"test");
+        let mut x = [0u8; 80];
+        let mut y = [0u8; 80];
+        transcript.challenge_bytes(b"x", &mut x);
+        transcript.challenge_bytes(b"x", &mut y);
+        assert_ne!(x, y);
+        assert_ne!(x[..32], x[32..64]);
+
+        for _ in 0..10 {
+            let a: Fr = Keccak256Transcript::new(b"test").challenge_field(b"a");
+            let b: Fr = Blake3Transcript::new(b"test").challenge_field(b"a");
+            assert_ne!(a, b);
+        }
+    }
+
+    /// known-answer test for challenge bytes and field elements.
+    /// Note that DefaultFieldHasher in ark-ff 0.4 pads with 48 zero bytes
+    /// instead of the block size of SHA-256 when hashing to the scalar
+    /// field, so the values differ from the RFC 9380 test vectors
+    #[test]
+    fn transcript_test_known_answer() {
+        let mut transcript = Sha256Transcript::new(b"test");
+        transcript.append_message(b"a", b"bc");
+        let mut bytes = [0u8; 8];
+        transcript.challenge_bytes(b"x", &mut bytes);
+        assert_eq!(bytes, [0xda, 0x95, 0x13, 0x86, 0x23, 0x56, 0x0e, 0xe2]);
+        let y: Fr = transcript.challenge_field(b"y");
+        let expected: Fr = MontFp!(
+            "35414826935111950781998219265415206582812664876510279629304438441151140816293"
+        );
+        assert_eq!(y, expected);
+    }
+
+    /// test that field elements are derived from challenge
+    /// bytes exactly as DefaultFieldHasher does it
+    #[test]
+    fn transcript_test_hash_to_field() {
+        let mut transcript = Sha256Transcript::new(b"test");
+        let mut expected_transcript = transcript.clone();
+        let y: Fr = transcript.challenge_field(b"y");
+        let mut seed = [0u8; 32];
+        expected_transcript.challenge_bytes(b"y", &mut seed);
+        let hasher = <DefaultFieldHasher<Sha256, 128> as HashToField<Fr>>::new(b"y");
+        let expected: Vec<Fr> = hasher.hash_to_field(&seed, 1);
+        assert_eq!(y, expected[0]);
+    }
+}
@@ -1,18 +1,32 @@
 use ark_ff::Field;
-use ark_serialize::CanonicalSerialize;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::rand::Rng;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// module that contains a KZG-based
 /// simulation-extractable (aggregatable)
 /// vector commitment scheme
 pub mod kzg;
 
+/// module that contains a simulation-extractable
+/// vector commitment scheme based on Pedersen commitments
+/// and inner product arguments, with a transparent setup
+pub mod ipa;
+
+/// module that contains a hiding vector commitment
+/// scheme based on salted Merkle trees, which
+/// only relies on hash functions
+pub mod merkle;
+
 /// trait representing vector commitment schemes
 pub trait VectorCommitmentScheme<F: Field> {
     type CommitmentKey;
-    type Commitment: CanonicalSerialize;
-    type Opening;
-    type State;
+    /// the part of the commitment key that is needed for verification
+    type VerifierKey: CanonicalSerialize + CanonicalDeserialize;
+    type Commitment: CanonicalSerialize + CanonicalDeserialize;
+    type Opening: CanonicalSerialize + CanonicalDeserialize;
+    /// secret state, which should be zeroized on drop
+    type State: Zeroize + ZeroizeOnDrop;
 
     /// Set up commitment key. Ideally, this should be implemented by a distributed protocol
     fn setup<R: Rng>(rng: &mut R, message_length: usize) -> Option<Self::CommitmentKey>;
@@ -21,11 +35,18 @@ pub trait VectorCommitmentScheme<F: Field> {
     fn commit<R: Rng>(
         rng: &mut R,
         ck: &Self::CommitmentKey,
-        m: &Vec<F>,
+        m: &[F],
     ) -> (Self::Commitment, Self::State);
 
+    /// Returns the verifier key contained in the commitment key
+    fn verifier_key(ck: &Self::CommitmentKey) -> &Self::VerifierKey;
+
+    /// Returns a copy of the verifier key without precomputed
+    /// values, which is as small as possible, e.g., for light clients
+    fn light_verifier_key(ck: &Self::CommitmentKey) -> Self::VerifierKey;
+
     /// Verify that a given commitment com is well-formed
-    fn verify_commitment(ck: &Self::CommitmentKey, com: &Self::Commitment) -> bool;
+    fn verify_commitment(vk: &Self::VerifierKey, com: &Self::Commitment) -> bool;
 
     /// Open a commitment at position i, using the state output by function commit
     fn open(ck: &Self::CommitmentKey, st: &Self::State, i: u32) -> Option<Self::Opening>;
@@ -36,27 +57,132 @@ pub trait VectorCommitmentScheme<F: Field> {
     fn aggregate(
         ck: &Self::CommitmentKey,
         i: u32,
-        mis: &Vec<F>,
-        coms: &Vec<&Self::Commitment>,
-        openings: &Vec<&Self::Opening>,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
     ) -> Option<Self::Opening>;
 
     /// Verify an (aggregated) opening
     /// The opening is meant to open each
     /// coms[j] to mis[j] at position i
     fn verify(
-        ck: &Self::CommitmentKey,
+        vk: &Self::VerifierKey,
         i: u32,
-        mis: &Vec<F>,
-        coms: &Vec<&Self::Commitment>,
+        mis: &[F],
+        coms: &[&Self::Commitment],
         opening: &Self::Opening,
     ) -> bool;
+
+    /// Same as verify, but additionally gets the uncompressed
+    /// serializations coms_ser[j] of the commitments coms[j],
+    /// so that they do not have to be serialized again
+    fn verify_serialized(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        _coms_ser: &[&[u8]],
+        opening: &Self::Opening,
+    ) -> bool {
+        Self::verify(vk, i, mis, coms, opening)
+    }
+
+    /// Verify openings[j] for coms[j] and mis[j] at position i, i.e.,
+    /// outputs true if and only if every single opening verifies.
+    /// Implementors may batch this using random linear combinations
+    fn batch_verify<R: Rng>(
+        _rng: &mut R,
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> bool {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != openings.len() {
+            return false;
+        }
+        (0..mis.len()).all(|j| Self::verify(vk, i, &mis[j..j + 1], &coms[j..j + 1], openings[j]))
+    }
+}
+
+/// trait representing vector commitment schemes for which
+/// openings at different positions and for different
+/// commitments can be aggregated into a single opening.
+/// Its size may grow with the number of distinct positions
+pub trait CrossAggregation<F: Field>: VectorCommitmentScheme<F> {
+    type CrossOpening;
+
+    /// Aggregate some openings at (possibly) different positions
+    /// Assuming that openings[j] is an opening for commitment
+    /// coms[j] at position is[j] to value mis[j]
+    fn aggregate_cross(
+        ck: &Self::CommitmentKey,
+        is: &[u32],
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::CrossOpening>;
+
+    /// Verify a cross-aggregated opening
+    /// The opening is meant to open each
+    /// coms[j] to mis[j] at position is[j]
+    fn verify_cross(
+        vk: &Self::VerifierKey,
+        is: &[u32],
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        opening: &Self::CrossOpening,
+    ) -> bool;
+}
+
+/// trait representing vector commitment schemes for which openings
+/// at the same position can be aggregated incrementally. That is,
+/// partial aggregates for disjoint sets of openings can be merged
+/// later on. Merged openings may grow with the number of aggregates
+/// they contain, as the coefficients that combine them must depend
+/// on all of them: otherwise, an adversary with many commitments can
+/// search for a subset of invalid openings whose errors cancel out
+pub trait MergeableAggregation<F: Field>: VectorCommitmentScheme<F> {
+    type MergeableOpening;
+
+    /// Aggregate some openings at the same position i
+    /// Assuming that openings[j] is an opening for commitment
+    /// coms[j] at position i to value mis[j]
+    fn aggregate_mergeable(
+        ck: &Self::CommitmentKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::MergeableOpening>;
+
+    /// Merge two aggregated openings for the same position.
+    /// The sets of openings that have been aggregated
+    /// into agg1 and agg2 should be disjoint. The result
+    /// opens the values of agg1 followed by those of agg2
+    fn merge(
+        ck: &Self::CommitmentKey,
+        agg1: &Self::MergeableOpening,
+        agg2: &Self::MergeableOpening,
+    ) -> Option<Self::MergeableOpening>;
+
+    /// Verify an opening output by aggregate_mergeable or merge
+    /// The opening is meant to open each coms[j] to mis[j] at
+    /// position i, in the order in which they have been merged
+    fn verify_mergeable(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        opening: &Self::MergeableOpening,
+    ) -> bool;
 }
 
 // Test functions for this trait, which can
 // be used by implementors of this trait
 
 /// test that setup works
+#[cfg(feature = "std")]
 fn _vc_test_setup<F: Field, VC: VectorCommitmentScheme<F>>() {
     let mut rng = ark_std::rand::thread_rng();
 
@@ -70,6 +196,7 @@ fn _vc_test_setup<F: Field, VC: VectorCommitmentScheme<F>>() {
 }
 
 /// test that honestly committing yields a valid commitment
+#[cfg(feature = "std")]
 fn _vc_test_com_ver<F: Field, VC: VectorCommitmentScheme<F>>() {
     let mut rng = ark_std::rand::thread_rng();
 
@@ -87,12 +214,13 @@ fn _vc_test_com_ver<F: Field, VC: VectorCommitmentScheme<F>>() {
             // commit to it
             let (com, _) = VC::commit(&mut rng, &ck, &m);
             // verify the commitment
-            assert!(VC::verify_commitment(&ck, &com));
+            assert!(VC::verify_commitment(VC::verifier_key(&ck), &com));
         }
     }
 }
 
 /// test that honestly committing and opening makes ver accept
+#[cfg(feature = "std")]
 fn _vc_test_opening<F: Field, VC: VectorCommitmentScheme<F>>() {
     let mut rng = ark_std::rand::thread_rng();
 
@@ -112,12 +240,18 @@ fn _vc_test_opening<F: Field, VC: VectorCommitmentScheme<F>>() {
             let (com, st) = VC::commit(&mut rng, &ck, &m);
 
             // open the commitment at every position and verify the opening
-            for i in 0..message_length {
+            for (i, mi) in m.iter().enumerate() {
                 let op = VC::open(&ck, &st, i as u32);
                 assert!(op.is_some());
                 let op = op.unwrap();
                 // now verify
-                assert!(VC::verify(&ck, i as u32, &vec![m[i]], &vec![&com], &op));
+                assert!(VC::verify(
+                    VC::verifier_key(&ck),
+                    i as u32,
+                    &[*mi],
+                    &[&com],
+                    &op
+                ));
             }
 
             // make sure that opening outside of the range
@@ -128,7 +262,127 @@ fn _vc_test_opening<F: Field, VC: VectorCommitmentScheme<F>>() {
     }
 }
 
+/// test that malformed inputs, e.g., positions out of range or
+/// inputs of different lengths, are rejected and do not panic
+#[cfg(feature = "std")]
+fn _vc_test_malformed_inputs<F: Field, VC: VectorCommitmentScheme<F>>() {
+    let mut rng = ark_std::rand::thread_rng();
+    let message_length = 5;
+    let ck = VC::setup(&mut rng, message_length).unwrap();
+    let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+    let (com, st) = VC::commit(&mut rng, &ck, &m);
+    let op = VC::open(&ck, &st, 0).unwrap();
+    assert!(VC::verify(VC::verifier_key(&ck), 0, &[m[0]], &[&com], &op));
+
+    // positions out of range
+    assert!(VC::open(&ck, &st, u32::MAX).is_none());
+    assert!(!VC::verify(
+        VC::verifier_key(&ck),
+        message_length as u32,
+        &[m[0]],
+        &[&com],
+        &op
+    ));
+    assert!(!VC::verify(
+        VC::verifier_key(&ck),
+        u32::MAX,
+        &[m[0]],
+        &[&com],
+        &op
+    ));
+
+    // inputs of different lengths
+    assert!(!VC::verify(VC::verifier_key(&ck), 0, &[], &[], &op));
+    assert!(!VC::verify(
+        VC::verifier_key(&ck),
+        0,
+        &[m[0], m[0]],
+        &[&com],
+        &op
+    ));
+    assert!(!VC::verify(
+        VC::verifier_key(&ck),
+        0,
+        &[m[0]],
+        &[&com, &com],
+        &op
+    ));
+    assert!(VC::aggregate(&ck, 0, &[], &[], &[]).is_none());
+    assert!(VC::aggregate(&ck, 0, &[m[0]], &[&com], &[&op, &op]).is_none());
+    assert!(VC::aggregate(&ck, 0, &[m[0], m[0]], &[&com], &[&op, &op]).is_none());
+}
+
+/// test that batch verification accepts honest openings,
+/// but rejects if a single opening is invalid
+#[cfg(feature = "std")]
+fn _vc_test_batch_verify<F: Field, VC: VectorCommitmentScheme<F>>() {
+    let mut rng = ark_std::rand::thread_rng();
+    let message_length = 6;
+    let ck = VC::setup(&mut rng, message_length).unwrap();
+    let i = 2;
+
+    let numcoms = 5;
+    let mut mis = Vec::new();
+    let mut coms = Vec::new();
+    let mut ops = Vec::new();
+    for _ in 0..numcoms {
+        let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+        ops.push(VC::open(&ck, &st, i).unwrap());
+        mis.push(m[i as usize]);
+        coms.push(com);
+    }
+    let coms_r: Vec<_> = coms.iter().collect();
+    let mut ops_r: Vec<_> = ops.iter().collect();
+    assert!(VC::batch_verify(
+        &mut rng,
+        VC::verifier_key(&ck),
+        i,
+        &mis,
+        &coms_r,
+        &ops_r
+    ));
+    assert!(!VC::batch_verify(
+        &mut rng,
+        VC::verifier_key(&ck),
+        i,
+        &[],
+        &[],
+        &[]
+    ));
+    assert!(!VC::batch_verify(
+        &mut rng,
+        VC::verifier_key(&ck),
+        i,
+        &mis[1..],
+        &coms_r,
+        &ops_r
+    ));
+
+    // a wrong value, or an opening for another commitment
+    let mut wrong_mis = mis.clone();
+    wrong_mis[3] += F::one();
+    assert!(!VC::batch_verify(
+        &mut rng,
+        VC::verifier_key(&ck),
+        i,
+        &wrong_mis,
+        &coms_r,
+        &ops_r
+    ));
+    ops_r.swap(0, 1);
+    assert!(!VC::batch_verify(
+        &mut rng,
+        VC::verifier_key(&ck),
+        i,
+        &mis,
+        &coms_r,
+        &ops_r
+    ));
+}
+
 /// test that honestly committing, opening, and aggregating makes ver accept
+#[cfg(feature = "std")]
 fn _vc_test_agg_opening<F: Field, VC: VectorCommitmentScheme<F>>() {
     let mut rng = ark_std::rand::thread_rng();
 
@@ -156,19 +410,185 @@ fn _vc_test_agg_opening<F: Field, VC: VectorCommitmentScheme<F>>() {
         // Open, aggregate, verify
         for i in 0..message_length {
             let mut ops = Vec::new();
-            for j in 0..numcoms {
-                let op = VC::open(&ck, &sts[j], i as u32).unwrap();
+            for st in &sts {
+                let op = VC::open(&ck, st, i as u32).unwrap();
                 ops.push(op);
             }
             // aggregate
-            let mis = (0..numcoms).map(|j| ms[j][i]).collect();
-            let coms_r = (0..numcoms).map(|j| &coms[j]).collect();
-            let ops_r = (0..numcoms).map(|j| &ops[j]).collect();
+            let mis: Vec<F> = ms.iter().map(|m| m[i]).collect();
+            let coms_r: Vec<_> = coms.iter().collect();
+            let ops_r: Vec<_> = ops.iter().collect();
             let op_agg = VC::aggregate(&ck, i as u32, &mis, &coms_r, &ops_r);
             assert!(op_agg.is_some());
             let op_agg = op_agg.unwrap();
             // verify
-            assert!(VC::verify(&ck, i as u32, &mis, &coms_r, &op_agg));
+            assert!(VC::verify(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis,
+                &coms_r,
+                &op_agg
+            ));
+        }
+    }
+}
+
+/// test that honestly committing, opening at different positions,
+/// and aggregating across positions makes ver accept
+#[cfg(feature = "std")]
+fn _vc_test_cross_agg_opening<F: Field, VC: CrossAggregation<F>>() {
+    let mut rng = ark_std::rand::thread_rng();
+
+    // we test for a bunch of message lengths
+    let lrange = 1..15;
+    for message_length in lrange {
+        // setup commitment key
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+
+        // commit to a bunch of vectors, giving us a bunch of commitments
+        let mut coms = Vec::new();
+        let mut sts = Vec::new();
+        let mut ms = Vec::new();
+        let numcoms = 4;
+        for _ in 0..numcoms {
+            // sample random vector
+            let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+            // commit to it
+            let (com, st) = VC::commit(&mut rng, &ck, &m);
+            ms.push(m);
+            coms.push(com);
+            sts.push(st);
+        }
+
+        // open every commitment at every position
+        let mut is = Vec::new();
+        let mut mis = Vec::new();
+        let mut coms_r = Vec::new();
+        let mut ops = Vec::new();
+        for ((m, com), st) in ms.iter().zip(&coms).zip(&sts) {
+            for (i, mi) in m.iter().enumerate() {
+                is.push(i as u32);
+                mis.push(*mi);
+                coms_r.push(com);
+                ops.push(VC::open(&ck, st, i as u32).unwrap());
+            }
+        }
+        let ops_r: Vec<_> = ops.iter().collect();
+
+        // aggregate across positions and verify
+        let op_agg = VC::aggregate_cross(&ck, &is, &mis, &coms_r, &ops_r);
+        assert!(op_agg.is_some());
+        let op_agg = op_agg.unwrap();
+        assert!(VC::verify_cross(
+            VC::verifier_key(&ck),
+            &is,
+            &mis,
+            &coms_r,
+            &op_agg
+        ));
+
+        // changing a single value should make ver reject
+        let mut mis_wrong = mis.clone();
+        mis_wrong[0] += F::one();
+        assert!(!VC::verify_cross(
+            VC::verifier_key(&ck),
+            &is,
+            &mis_wrong,
+            &coms_r,
+            &op_agg
+        ));
+    }
+}
+
+/// test that aggregating openings one by one and merging
+/// the partial aggregates makes ver accept
+#[cfg(feature = "std")]
+fn _vc_test_merge_opening<F: Field, VC: MergeableAggregation<F>>() {
+    let mut rng = ark_std::rand::thread_rng();
+
+    // we test for a bunch of message lengths
+    let lrange = 1..15;
+    for message_length in lrange {
+        // setup commitment key
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+
+        // commit to a bunch of vectors, giving us a bunch of commitments
+        let mut coms = Vec::new();
+        let mut sts = Vec::new();
+        let mut ms = Vec::new();
+        let numcoms = 5;
+        for _ in 0..numcoms {
+            // sample random vector
+            let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+            // commit to it
+            let (com, st) = VC::commit(&mut rng, &ck, &m);
+            ms.push(m);
+            coms.push(com);
+            sts.push(st);
+        }
+
+        for i in 0..message_length {
+            let ops: Vec<_> = sts
+                .iter()
+                .map(|st| VC::open(&ck, st, i as u32).unwrap())
+                .collect();
+            let mis: Vec<F> = ms.iter().map(|m| m[i]).collect();
+            let coms_r: Vec<_> = coms.iter().collect();
+            let ops_r: Vec<_> = ops.iter().collect();
+
+            // aggregate the first two and the remaining ones separately
+            let agg1 = VC::aggregate_mergeable(&ck, i as u32, &mis[..2], &coms_r[..2], &ops_r[..2]);
+            let agg2 = VC::aggregate_mergeable(&ck, i as u32, &mis[2..], &coms_r[2..], &ops_r[2..]);
+            assert!(agg1.is_some());
+            assert!(agg2.is_some());
+            let agg1 = agg1.unwrap();
+            let agg2 = agg2.unwrap();
+            assert!(VC::verify_mergeable(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis[..2],
+                &coms_r[..2],
+                &agg1
+            ));
+            assert!(VC::verify_mergeable(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis[2..],
+                &coms_r[2..],
+                &agg2
+            ));
+
+            // merge the partial aggregates
+            let agg = VC::merge(&ck, &agg1, &agg2);
+            assert!(agg.is_some());
+            let agg = agg.unwrap();
+            assert!(VC::verify_mergeable(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis,
+                &coms_r,
+                &agg
+            ));
+
+            // the values must be given in the order of merging
+            let mis_rev: Vec<F> = mis.iter().rev().copied().collect();
+            let coms_rev: Vec<_> = coms_r.iter().rev().copied().collect();
+            assert!(!VC::verify_mergeable(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis_rev,
+                &coms_rev,
+                &agg
+            ));
+
+            // the merged opening should not verify for a subset
+            assert!(!VC::verify_mergeable(
+                VC::verifier_key(&ck),
+                i as u32,
+                &mis[1..],
+                &coms_r[1..],
+                &agg
+            ));
         }
     }
 }
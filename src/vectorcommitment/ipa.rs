@@ -0,0 +1,305 @@
+use ark_ec::CurveGroup;
+use ark_std::ops::Mul;
+use ark_std::UniformRand;
+use ark_std::{vec, vec::Vec};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// this module contains all types associated with
+/// the IPA-based sim-extractable vector commitment
+pub mod ipa_types;
+
+/// this module contains the inner product argument
+/// and several functions we use for our vector commitment
+mod ipa_utils;
+
+pub use self::ipa_types::Commitment;
+pub use self::ipa_types::CommitmentKey;
+pub use self::ipa_types::IpaProof;
+pub use self::ipa_types::Opening;
+pub use self::ipa_types::State;
+pub use self::ipa_types::VcIPA;
+
+use self::ipa_utils::get_generators;
+use self::ipa_utils::get_open_seed;
+use self::ipa_utils::get_z0;
+use self::ipa_utils::inner_product;
+use self::ipa_utils::ipa_prove;
+use self::ipa_utils::powers;
+use self::ipa_utils::unit_vector;
+use self::ipa_utils::IpaChecker;
+
+use super::VectorCommitmentScheme;
+use crate::memlock::MemoryLock;
+use crate::transcript::Sha256Transcript;
+
+/// transcript for the opening contained in a commitment
+fn commitment_transcript<G: CurveGroup>(
+    com_ped: &G::Affine,
+    y0: &G::ScalarField,
+) -> Sha256Transcript {
+    let mut transcript = Sha256Transcript::new(b"IPA-COM//");
+    transcript.append_serializable(b"com", com_ped);
+    transcript.append_serializable(b"y0", y0);
+    transcript
+}
+
+/// transcript for an opening at position i to value mi
+fn opening_transcript<G: CurveGroup>(
+    com_ped: &G::Affine,
+    i: u32,
+    mi: &G::ScalarField,
+) -> Sha256Transcript {
+    let mut transcript = Sha256Transcript::new(b"IPA-OPEN//");
+    transcript.append_serializable(b"com", com_ped);
+    transcript.append_u32(b"i", i);
+    transcript.append_serializable(b"mi", mi);
+    transcript
+}
+
+impl<G: CurveGroup> VectorCommitmentScheme<G::ScalarField> for VcIPA<G> {
+    type CommitmentKey = CommitmentKey<G>;
+    type VerifierKey = CommitmentKey<G>;
+    type Commitment = Commitment<G>;
+    type Opening = Opening<G>;
+    type State = State<G>;
+
+    fn setup<R: rand::Rng>(_rng: &mut R, message_length: usize) -> Option<Self::CommitmentKey> {
+        if message_length < 1 {
+            return None;
+        }
+
+        // we need at least one random element in the committed
+        // vector, so that y0 does not leak anything about the message
+        let n = (message_length + 1).checked_next_power_of_two()?;
+
+        // the setup is transparent: all generators are
+        // derived by hashing, so no randomness is needed
+        let g = get_generators::<G>("g", n);
+        let h = get_generators::<G>("h", 1)[0];
+        let u = get_generators::<G>("u", 1)[0];
+        Some(CommitmentKey {
+            message_length,
+            g,
+            h,
+            u,
+        })
+    }
+
+    fn commit<R: rand::Rng>(
+        rng: &mut R,
+        ck: &Self::CommitmentKey,
+        m: &[G::ScalarField],
+    ) -> (Self::Commitment, Self::State) {
+        // the committed vector a is m, padded with random elements
+        let n = ck.g.len();
+        let mut a = Vec::with_capacity(n);
+        a.extend_from_slice(m);
+        for _ in m.len()..n {
+            a.push(G::ScalarField::rand(rng));
+        }
+        let rho = G::ScalarField::rand(rng);
+        let seed = rng.gen();
+
+        // com_ped = <a,g> + rho*h
+        let com_ped = G::msm(&ck.g, &a).unwrap() + ck.h.mul(rho);
+        let com_ped = com_ped.into_affine();
+
+        // open <a,(1,z0,z0^2,...)> for the random point z0
+        let z0 = get_z0::<G>(&com_ped);
+        let b0 = powers(z0, n);
+        let y0 = inner_product(&a, &b0);
+        let transcript = commitment_transcript::<G>(&com_ped, &y0);
+        let tau0 = ipa_prove(rng, ck, transcript, &a, rho, &b0);
+
+        let com = Commitment { com_ped, y0, tau0 };
+        let state = State {
+            lock: MemoryLock::new(&a),
+            a,
+            rho,
+            seed,
+        };
+        (com, state)
+    }
+
+    // all of the commitment key is needed for verification
+    fn verifier_key(ck: &Self::CommitmentKey) -> &Self::VerifierKey {
+        ck
+    }
+
+    fn light_verifier_key(ck: &Self::CommitmentKey) -> Self::VerifierKey {
+        ck.clone()
+    }
+
+    fn verify_commitment(vk: &Self::VerifierKey, com: &Self::Commitment) -> bool {
+        // compute the 'challenge' z0 at which the commitment has to be opened
+        let z0 = get_z0::<G>(&com.com_ped);
+        let b0 = powers(z0, vk.g.len());
+        // check opening
+        let mut checker = IpaChecker::new(vk);
+        let transcript = commitment_transcript::<G>(&com.com_ped, &com.y0);
+        checker.add(
+            G::ScalarField::from(1u64),
+            transcript,
+            &com.com_ped,
+            com.y0,
+            &b0,
+            &com.tau0,
+        ) && checker.check()
+    }
+
+    fn open(ck: &Self::CommitmentKey, st: &Self::State, i: u32) -> Option<Self::Opening> {
+        if i as usize >= ck.message_length {
+            return None;
+        }
+
+        // the randomness for the proof is derived from the state,
+        // so that opening does not need a random number generator
+        let mut rng = StdRng::from_seed(get_open_seed(&st.seed, i));
+        let com_ped = (G::msm(&ck.g, &st.a).unwrap() + ck.h.mul(st.rho)).into_affine();
+        let mi = st.a[i as usize];
+        let b = unit_vector(i as usize, ck.g.len());
+        let transcript = opening_transcript::<G>(&com_ped, i, &mi);
+        let proof = ipa_prove(&mut rng, ck, transcript, &st.a, st.rho, &b);
+        Some(Opening {
+            proofs: vec![proof],
+        })
+    }
+
+    fn aggregate(
+        ck: &Self::CommitmentKey,
+        i: u32,
+        mis: &[G::ScalarField],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::Opening> {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != openings.len() {
+            return None;
+        }
+        if i as usize >= ck.message_length {
+            return None;
+        }
+
+        // inner product arguments can not be combined without
+        // knowing the committed vectors, so we just collect them.
+        // Verification of the result is still batched
+        let proofs: Vec<_> = openings
+            .iter()
+            .flat_map(|opening| opening.proofs.iter().cloned())
+            .collect();
+        if proofs.len() != mis.len() {
+            return None;
+        }
+        Some(Opening { proofs })
+    }
+
+    fn verify(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[G::ScalarField],
+        coms: &[&Self::Commitment],
+        opening: &Self::Opening,
+    ) -> bool {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != opening.proofs.len() {
+            return false;
+        }
+        if i as usize >= vk.message_length {
+            return false;
+        }
+
+        // combine all checks using powers of a coefficient delta,
+        // which depends on everything the verifier gets
+        let mut transcript = Sha256Transcript::new(b"IPA-BATCH//");
+        transcript.append_u32(b"i", i);
+        for (mi, com) in mis.iter().zip(coms) {
+            transcript.append_serializable(b"mi", mi);
+            transcript.append_serializable(b"com", &com.com_ped);
+        }
+        transcript.append_serializable(b"opening", opening);
+        let delta = transcript.challenge_field(b"delta");
+        let deltas = powers(delta, mis.len());
+
+        let b = unit_vector(i as usize, vk.g.len());
+        let mut checker = IpaChecker::new(vk);
+        for j in 0..mis.len() {
+            let transcript = opening_transcript::<G>(&coms[j].com_ped, i, &mis[j]);
+            let proof = &opening.proofs[j];
+            if !checker.add(deltas[j], transcript, &coms[j].com_ped, mis[j], &b, proof) {
+                return false;
+            }
+        }
+        checker.check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::G1Projective;
+    use ark_ec::Group;
+    use ark_std::One;
+
+    use super::VcIPA;
+    use crate::vectorcommitment::{
+        _vc_test_agg_opening, _vc_test_batch_verify, _vc_test_com_ver, _vc_test_malformed_inputs,
+        _vc_test_opening, _vc_test_setup, VectorCommitmentScheme,
+    };
+
+    type F = <G1Projective as Group>::ScalarField;
+    type VC = VcIPA<G1Projective>;
+
+    #[test]
+    fn ipa_vc_test_setup() {
+        _vc_test_setup::<F, VC>();
+    }
+
+    #[test]
+    fn ipa_vc_test_com_ver() {
+        _vc_test_com_ver::<F, VC>();
+    }
+
+    #[test]
+    fn ipa_vc_test_opening() {
+        _vc_test_opening::<F, VC>();
+    }
+
+    #[test]
+    fn ipa_vc_test_malformed_inputs() {
+        _vc_test_malformed_inputs::<F, VC>();
+    }
+
+    #[test]
+    fn ipa_vc_test_batch_verify() {
+        _vc_test_batch_verify::<F, VC>();
+    }
+
+    #[test]
+    fn ipa_vc_test_agg_opening() {
+        _vc_test_agg_opening::<F, VC>();
+    }
+
+    /// test that the setup is deterministic and that
+    /// openings for wrong values or positions are rejected
+    #[test]
+    fn ipa_vc_test_reject() {
+        let mut rng = ark_std::rand::thread_rng();
+        let message_length = 6;
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+        assert_eq!(ck, VC::setup(&mut rng, message_length).unwrap());
+        assert_eq!(ck.g.len(), 8);
+
+        let m: Vec<F> = (0..message_length as u64).map(F::from).collect();
+        let (mut com, st) = VC::commit(&mut rng, &ck, &m);
+        let op = VC::open(&ck, &st, 2).unwrap();
+        assert!(VC::verify(&ck, 2, &[m[2]], &[&com], &op));
+        assert!(!VC::verify(&ck, 2, &[m[3]], &[&com], &op));
+        assert!(!VC::verify(&ck, 3, &[m[2]], &[&com], &op));
+
+        // tampering with the proof makes ver reject
+        let mut op_wrong = op.clone();
+        op_wrong.proofs[0].z1 += F::one();
+        assert!(!VC::verify(&ck, 2, &[m[2]], &[&com], &op_wrong));
+
+        // tampering with the commitment makes verify_commitment reject
+        com.y0 += F::one();
+        assert!(!VC::verify_commitment(&ck, &com));
+    }
+}
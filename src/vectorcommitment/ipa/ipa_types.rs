@@ -0,0 +1,88 @@
+use ark_ec::CurveGroup;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::marker::PhantomData;
+use ark_std::vec::Vec;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::memlock::MemoryLock;
+
+// This module contains types for the Pedersen vector commitment with inner product arguments
+
+/// Simulation-Extractable vector commitment based on
+/// Pedersen commitments and inner product arguments.
+/// It has a transparent setup, i.e., no trusted setup is needed
+pub struct VcIPA<G: CurveGroup> {
+    _g: PhantomData<G>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct CommitmentKey<G: CurveGroup> {
+    /// length of messages to which we commit
+    pub message_length: usize,
+
+    /// generators for the committed vector, which is the
+    /// message padded with random elements. Its length is the
+    /// smallest power of two that is at least message_length + 1
+    pub g: Vec<G::Affine>,
+
+    /// generator for the randomness of the commitment
+    pub h: G::Affine,
+
+    /// generator for the inner product
+    pub u: G::Affine,
+}
+
+/// zero-knowledge inner product argument, showing that a
+/// commitment p = <a,g> + rho*h satisfies <a,b> = y for public b, y
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct IpaProof<G: CurveGroup> {
+    /// blinded cross terms of each round
+    pub ls: Vec<G::Affine>,
+    pub rs: Vec<G::Affine>,
+
+    /// commitment of the final round
+    pub r_fin: G::Affine,
+
+    /// responses of the final round
+    pub z1: G::ScalarField,
+    pub z2: G::ScalarField,
+}
+
+/// opening for one or several commitments. Aggregation
+/// does not compress, but aggregated openings are
+/// verified using a single multi-scalar multiplication
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Opening<G: CurveGroup> {
+    /// proofs[j] opens the jth commitment
+    pub proofs: Vec<IpaProof<G>>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct Commitment<G: CurveGroup> {
+    /// actual pedersen commitment, <a,g> + rho*h
+    pub com_ped: G::Affine,
+
+    /// value of <a,(1,z0,z0^2,...)> for z0 = Hash(com_ped)
+    pub y0: G::ScalarField,
+
+    /// proof for y0
+    pub tau0: IpaProof<G>,
+}
+
+/// secret state of a commitment, which is zeroized on drop
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct State<G: CurveGroup> {
+    /// keeps the pages of vector a locked, if enabled
+    #[zeroize(skip)]
+    pub lock: MemoryLock,
+
+    /// the committed vector, i.e., the
+    /// message padded with random elements
+    pub a: Vec<G::ScalarField>,
+
+    /// randomness of the commitment
+    pub rho: G::ScalarField,
+
+    /// seed from which the randomness of openings is derived
+    pub seed: [u8; 32],
+}
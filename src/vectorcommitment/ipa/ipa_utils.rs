@@ -0,0 +1,299 @@
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Field;
+use ark_std::iter::zip;
+use ark_std::ops::Mul;
+use ark_std::{vec, vec::Vec};
+use ark_std::{One, UniformRand, Zero};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+use super::CommitmentKey;
+use super::IpaProof;
+use crate::transcript::Sha256Transcript;
+
+// This module contains helper functions for the Pedersen vector commitment with inner product arguments
+
+/// Derives num generators from the label by hashing to the curve,
+/// so that nobody knows discrete logarithms between them
+pub fn get_generators<G: CurveGroup>(label: &str, num: usize) -> Vec<G::Affine> {
+    (0..num as u64)
+        .map(|idx| hash_to_curve::<G>(label, idx))
+        .collect()
+}
+
+/// Hashes the label and the index to a non-zero
+/// point in the prime order subgroup, using try-and-increment
+fn hash_to_curve<G: CurveGroup>(label: &str, idx: u64) -> G::Affine {
+    let mut cnt = 0u64;
+    loop {
+        cnt += 1;
+        // we use two digests, so that we have
+        // enough bytes for the coordinate
+        let mut bytes = Vec::with_capacity(64);
+        for half in 0u8..2 {
+            let mut hasher = Sha256::new_with_prefix("IPA-GEN//".as_bytes());
+            hasher.update(label.as_bytes());
+            hasher.update(idx.to_le_bytes());
+            hasher.update(cnt.to_le_bytes());
+            hasher.update([half]);
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        if let Some(p) = G::Affine::from_random_bytes(&bytes) {
+            let p = p.clear_cofactor();
+            if !p.is_zero() {
+                return p;
+            }
+        }
+    }
+}
+
+/// Computes the challenge for a commitment
+#[inline]
+pub fn get_z0<G: CurveGroup>(com_ped: &G::Affine) -> G::ScalarField {
+    // z0 = Hash(com_ped)
+    let mut transcript = Sha256Transcript::new(b"IPA-SIM-EXT//");
+    transcript.append_serializable(b"com", com_ped);
+    transcript.challenge_field(b"z0")
+}
+
+/// Computes the vector (1,x,x^2,...,x^{le-1})
+pub fn powers<F: Field>(x: F, le: usize) -> Vec<F> {
+    let mut res = Vec::with_capacity(le);
+    let mut curr = F::one();
+    for _ in 0..le {
+        res.push(curr);
+        curr *= x;
+    }
+    res
+}
+
+/// Computes the inner product <a,b>
+pub fn inner_product<F: Field>(a: &[F], b: &[F]) -> F {
+    zip(a, b).map(|(x, y)| *x * y).sum()
+}
+
+/// Proves that p = <a,g> + rho*h satisfies <a,b> = y.
+/// The transcript should already contain p, b and y
+pub fn ipa_prove<G: CurveGroup, R: Rng>(
+    rng: &mut R,
+    ck: &CommitmentKey<G>,
+    mut transcript: Sha256Transcript,
+    a: &[G::ScalarField],
+    rho: G::ScalarField,
+    b: &[G::ScalarField],
+) -> IpaProof<G> {
+    // bind the inner product to a fresh generator
+    let w: G::ScalarField = transcript.challenge_field(b"w");
+    let u = ck.u.mul(w).into_affine();
+
+    // instead of folding the generators g, we keep track of
+    // their coefficients: the kth generator contributes with
+    // coefficient coeffs[k] to the current generator k mod len(a)
+    let n = ck.g.len();
+    let mut coeffs = vec![G::ScalarField::one(); n];
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    let mut rho = rho;
+    let mut ls = Vec::new();
+    let mut rs = Vec::new();
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_lo, a_hi) = a.split_at(half);
+        let (b_lo, b_hi) = b.split_at(half);
+
+        // <a_lo,g_hi> and <a_hi,g_lo> in terms of the original generators
+        let mut l_bases = Vec::with_capacity(n / 2 + 2);
+        let mut l_scalars = Vec::with_capacity(n / 2 + 2);
+        let mut r_bases = Vec::with_capacity(n / 2 + 2);
+        let mut r_scalars = Vec::with_capacity(n / 2 + 2);
+        for (k, (g, coeff)) in ck.g.iter().zip(&coeffs).enumerate().take(n) {
+            let t = k % a.len();
+            if t < half {
+                r_bases.push(*g);
+                r_scalars.push(a_hi[t] * coeff);
+            } else {
+                l_bases.push(*g);
+                l_scalars.push(a_lo[t - half] * coeff);
+            }
+        }
+
+        // cross terms, blinded so that they do not leak anything
+        // l = <a_lo,g_hi> + <a_lo,b_hi>*u + l_blind*h
+        // r = <a_hi,g_lo> + <a_hi,b_lo>*u + r_blind*h
+        let l_blind = G::ScalarField::rand(rng);
+        let r_blind = G::ScalarField::rand(rng);
+        l_bases.extend([u, ck.h]);
+        l_scalars.extend([inner_product(a_lo, b_hi), l_blind]);
+        r_bases.extend([u, ck.h]);
+        r_scalars.extend([inner_product(a_hi, b_lo), r_blind]);
+        let l = G::msm(&l_bases, &l_scalars).unwrap().into_affine();
+        let r = G::msm(&r_bases, &r_scalars).unwrap().into_affine();
+        l_scalars.zeroize();
+        r_scalars.zeroize();
+        transcript.append_serializable(b"l", &l);
+        transcript.append_serializable(b"r", &r);
+        let x: G::ScalarField = transcript.challenge_field(b"x");
+        let x_inv = x.inverse().unwrap();
+
+        // fold everything in half
+        // a' = x*a_lo + x^{-1}*a_hi
+        // b' = x^{-1}*b_lo + x*b_hi
+        // g' = x^{-1}*g_lo + x*g_hi
+        let a_new = zip(a_lo, a_hi)
+            .map(|(lo, hi)| x * lo + x_inv * hi)
+            .collect();
+        let b_new = zip(b_lo, b_hi)
+            .map(|(lo, hi)| x_inv * lo + x * hi)
+            .collect();
+        for (k, coeff) in coeffs.iter_mut().enumerate().take(n) {
+            *coeff *= if k % a.len() < half { x_inv } else { x };
+        }
+        let x_sq = x.square();
+        rho += x_sq * l_blind + x_sq.inverse().unwrap() * r_blind;
+        a.zeroize();
+        a = a_new;
+        b = b_new;
+        ls.push(l);
+        rs.push(r);
+    }
+
+    // now, the folded commitment is a*(g_f+b*u) + rho*h
+    // with g_f = <coeffs,g>. We prove knowledge
+    // of a and rho in zero-knowledge
+    let d = G::ScalarField::rand(rng);
+    let s = G::ScalarField::rand(rng);
+    let mut fin_scalars: Vec<_> = coeffs.iter().map(|c| *c * d).collect();
+    fin_scalars.extend([b[0] * d, s]);
+    let mut fin_bases = ck.g.clone();
+    fin_bases.extend([u, ck.h]);
+    let r_fin = G::msm(&fin_bases, &fin_scalars).unwrap().into_affine();
+    transcript.append_serializable(b"r_fin", &r_fin);
+    let c: G::ScalarField = transcript.challenge_field(b"c");
+    let z1 = a[0] * c + d;
+    let z2 = rho * c + s;
+    a.zeroize();
+    fin_scalars.zeroize();
+    IpaProof {
+        ls,
+        rs,
+        r_fin,
+        z1,
+        z2,
+    }
+}
+
+/// Collects the verification equations of several inner
+/// product arguments, combined with coefficients, so that
+/// they can be checked using a single MSM
+pub struct IpaChecker<'a, G: CurveGroup> {
+    ck: &'a CommitmentKey<G>,
+    /// coefficients of the generators g, h, and u
+    g_scalars: Vec<G::ScalarField>,
+    h_scalar: G::ScalarField,
+    u_scalar: G::ScalarField,
+    /// all other bases and their coefficients
+    bases: Vec<G::Affine>,
+    scalars: Vec<G::ScalarField>,
+}
+
+impl<'a, G: CurveGroup> IpaChecker<'a, G> {
+    pub fn new(ck: &'a CommitmentKey<G>) -> Self {
+        Self {
+            ck,
+            g_scalars: vec![G::ScalarField::zero(); ck.g.len()],
+            h_scalar: G::ScalarField::zero(),
+            u_scalar: G::ScalarField::zero(),
+            bases: Vec::new(),
+            scalars: Vec::new(),
+        }
+    }
+
+    /// Adds the check that proof shows that p = <a,g> + rho*h satisfies
+    /// <a,b> = y, multiplied by delta. The transcript should be the same
+    /// as the one given to the prover. Returns false if the proof is malformed
+    pub fn add(
+        &mut self,
+        delta: G::ScalarField,
+        mut transcript: Sha256Transcript,
+        p: &G::Affine,
+        y: G::ScalarField,
+        b: &[G::ScalarField],
+        proof: &IpaProof<G>,
+    ) -> bool {
+        let n = self.ck.g.len();
+        let rounds = n.trailing_zeros() as usize;
+        if b.len() != n || proof.ls.len() != rounds || proof.rs.len() != rounds {
+            return false;
+        }
+
+        // recompute all challenges
+        let w: G::ScalarField = transcript.challenge_field(b"w");
+        let mut xs = Vec::with_capacity(rounds);
+        for (l, r) in zip(&proof.ls, &proof.rs) {
+            transcript.append_serializable(b"l", l);
+            transcript.append_serializable(b"r", r);
+            xs.push(transcript.challenge_field::<G::ScalarField>(b"x"));
+        }
+        transcript.append_serializable(b"r_fin", &proof.r_fin);
+        let c: G::ScalarField = transcript.challenge_field(b"c");
+
+        // the folded generator is g_f = <s,g>, where s[k] is the
+        // product of x^{-1} (lower half) or x (upper half) over all rounds.
+        // The first round determines the most significant bit of k
+        let mut s = vec![G::ScalarField::one()];
+        for x in &xs {
+            let x_inv = x.inverse().unwrap();
+            s = s.iter().flat_map(|v| [*v * x_inv, *v * x]).collect();
+        }
+        let b_f = inner_product(&s, b);
+
+        // we check
+        // c*(p + y*w*u + sum_j x_j^2*l_j + x_j^{-2}*r_j) + r_fin
+        //     == z1*(g_f + b_f*w*u) + z2*h
+        self.bases.push(*p);
+        self.scalars.push(delta * c);
+        for (x, (l, r)) in zip(&xs, zip(&proof.ls, &proof.rs)) {
+            let x_sq = x.square();
+            self.bases.push(*l);
+            self.scalars.push(delta * c * x_sq);
+            self.bases.push(*r);
+            self.scalars.push(delta * c * x_sq.inverse().unwrap());
+        }
+        self.bases.push(proof.r_fin);
+        self.scalars.push(delta);
+        for (g_scalar, s_k) in zip(&mut self.g_scalars, &s) {
+            *g_scalar -= delta * proof.z1 * s_k;
+        }
+        self.u_scalar += delta * w * (c * y - proof.z1 * b_f);
+        self.h_scalar -= delta * proof.z2;
+        true
+    }
+
+    /// Checks all collected equations at once
+    pub fn check(mut self) -> bool {
+        self.bases.extend_from_slice(&self.ck.g);
+        self.scalars.extend_from_slice(&self.g_scalars);
+        self.bases.push(self.ck.h);
+        self.scalars.push(self.h_scalar);
+        self.bases.push(self.ck.u);
+        self.scalars.push(self.u_scalar);
+        G::msm(&self.bases, &self.scalars).unwrap().is_zero()
+    }
+}
+
+/// Computes the unit vector of length n with a one at position i
+pub fn unit_vector<F: Field>(i: usize, n: usize) -> Vec<F> {
+    let mut b = vec![F::zero(); n];
+    b[i] = F::one();
+    b
+}
+
+/// Derives the seed for the randomness used
+/// when opening at position i from the state seed
+pub fn get_open_seed(seed: &[u8; 32], i: u32) -> [u8; 32] {
+    let mut hasher = Sha256::new_with_prefix("IPA-OPEN-RNG//".as_bytes());
+    hasher.update(seed);
+    hasher.update(i.to_be_bytes());
+    hasher.finalize().into()
+}
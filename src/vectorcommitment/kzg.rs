@@ -1,8 +1,11 @@
 use ark_ec::{pairing::Pairing, CurveGroup, VariableBaseMSM};
 use ark_poly::EvaluationDomain;
-use ark_std::{One, UniformRand, Zero};
-use std::iter::zip;
-use std::ops::Mul;
+use ark_std::collections::BTreeMap;
+use ark_std::iter::zip;
+use ark_std::ops::Mul;
+use ark_std::{vec, vec::Vec};
+use ark_std::{UniformRand, Zero};
+use zeroize::Zeroize;
 
 /// this module contains all types associated with
 /// the KZG-based sim-extractable vector commitment
@@ -12,6 +15,21 @@ pub mod kzg_types;
 /// we use often for our vector commitment
 mod kzg_utils;
 
+/// this module contains the hash functions that compute
+/// evaluation points and coefficients for aggregating openings
+pub mod kzg_hasher;
+pub use kzg_hasher::Blake3KzgHasher;
+pub use kzg_hasher::Keccak256KzgHasher;
+pub use kzg_hasher::KzgHasher;
+pub use kzg_hasher::PoseidonKzgHasher;
+pub use kzg_hasher::Sha256KzgHasher;
+pub use kzg_hasher::TranscriptKzgHasher;
+
+/// this module contains gadgets that verify
+/// aggregated openings inside of circuits
+#[cfg(feature = "r1cs")]
+pub mod kzg_constraints;
+
 /// this module allows to compute all
 /// openings in a fast amortized way
 pub mod kzg_fk_open;
@@ -20,31 +38,44 @@ pub use kzg_fk_open::all_openings;
 use self::kzg_fk_open::precompute_y;
 pub use self::kzg_types::Commitment;
 pub use self::kzg_types::CommitmentKey;
+pub use self::kzg_types::CrossOpening;
+pub use self::kzg_types::MergeableOpening;
 pub use self::kzg_types::Opening;
 pub use self::kzg_types::State;
 pub use self::kzg_types::VcKZG;
+pub use self::kzg_types::VerifierKey;
 
+use self::kzg_utils::combine_commitments;
 use self::kzg_utils::evaluate_outside;
 use self::kzg_utils::find_in_domain;
 use self::kzg_utils::get_chi;
-use self::kzg_utils::get_z0;
+use self::kzg_utils::get_merge_rho;
+use self::kzg_utils::get_rho;
 use self::kzg_utils::inv_diffs;
+use self::kzg_utils::is_valid_commitment;
+use self::kzg_utils::is_valid_element;
 use self::kzg_utils::plain_kzg_com;
 use self::kzg_utils::plain_kzg_verify;
 use self::kzg_utils::plain_kzg_verify_inside;
+use self::kzg_utils::powers;
 use self::kzg_utils::witness_evals_inside;
 use self::kzg_utils::witness_evals_outside;
 
+use super::CrossAggregation;
+use super::MergeableAggregation;
 use super::VectorCommitmentScheme;
+use crate::memlock::MemoryLock;
+use crate::transcript::TranscriptHash;
 
 /* Note:
     - message length + 2 should probably be power of two, to make use of roots of unity
 */
 
-impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::ScalarField>
-    for VcKZG<E, D>
+impl<E: Pairing, D: EvaluationDomain<E::ScalarField>, A: KzgHasher<E>>
+    VectorCommitmentScheme<E::ScalarField> for VcKZG<E, D, A>
 {
     type CommitmentKey = CommitmentKey<E, D>;
+    type VerifierKey = VerifierKey<E, D>;
     type Commitment = Commitment<E>;
     type Opening = Opening<E>;
     type State = State<E>;
@@ -56,11 +87,7 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
 
         // generate an evaluation domain
         // should support polynomials to degree >= message_length + 1
-        let domain = D::new(message_length + 2);
-        if domain.is_none() {
-            return None;
-        }
-        let domain = domain.unwrap();
+        let domain = D::new(message_length + 2)?;
 
         // sample generators g1 and g2
         let g1 = E::G1::rand(rng);
@@ -96,11 +123,11 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         // this can be publicly computed from u and hat_u
         let lf = domain.evaluate_all_lagrange_coefficients(alpha);
         let mut lagranges = Vec::with_capacity(2 * deg);
-        for i in 0..=deg {
-            lagranges.push(u[0].mul(lf[i]).into_affine());
+        for l in &lf[..=deg] {
+            lagranges.push(u[0].mul(l).into_affine());
         }
-        for i in 0..=deg {
-            lagranges.push(hat_u[0].mul(lf[i]).into_affine());
+        for l in &lf[..=deg] {
+            lagranges.push(hat_u[0].mul(l).into_affine());
         }
 
         //compute r = g2^{alpha}
@@ -119,16 +146,22 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         let hat_y = precompute_y::<E, D>(&hat_u, &domain);
 
         // assemble commitment key
-        let g2 = g2.into_affine();
+        let vk = VerifierKey {
+            message_length,
+            domain,
+            g1: u[0],
+            h: hat_u[0],
+            g2: g2.into_affine(),
+            r,
+            d,
+        };
         Some(CommitmentKey {
             message_length,
             domain,
             u,
             hat_u,
             lagranges,
-            g2,
-            r,
-            d,
+            vk,
             y,
             hat_y,
         })
@@ -137,7 +170,7 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
     fn commit<R: rand::Rng>(
         rng: &mut R,
         ck: &Self::CommitmentKey,
-        m: &Vec<E::ScalarField>,
+        m: &[E::ScalarField],
     ) -> (Self::Commitment, Self::State) {
         // evals[0..domain.size] will store evaluations of our polynomial
         // over our evaluation domain, namely
@@ -149,9 +182,7 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         // we can easily do a single MSM later
         let dsize = ck.domain.size();
         let mut evals = Vec::with_capacity(2 * dsize);
-        for i in 0..m.len() {
-            evals.push(m[i]);
-        }
+        evals.extend_from_slice(m);
         for _ in m.len()..2 * ck.domain.size() {
             evals.push(E::ScalarField::rand(rng));
         }
@@ -166,7 +197,7 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
 
         // determine the random point at which we have to open,
         // and evaluate the polynomial at that point
-        let z0: E::ScalarField = get_z0::<E>(&com_kzg);
+        let z0: E::ScalarField = A::z0(&com_kzg);
         if find_in_domain::<E, D>(&ck.domain, z0).is_some() {
             // should happen with negl probability for poly size domain
             // we actually don't want to reveal our vector, so it is
@@ -181,13 +212,15 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         let mut witn_evals = Vec::with_capacity(2 * dsize);
         witness_evals_outside::<E, D>(&ck.domain, &evals, y0, &inv_diffs, &mut witn_evals);
         // do the same for the masking term
-        let hat_y0 = evaluate_outside::<E, D>(&ck.domain, &hat_evals, z0, &inv_diffs);
-        witness_evals_outside::<E, D>(&ck.domain, &hat_evals, hat_y0, &inv_diffs, &mut witn_evals);
+        let hat_y0 = evaluate_outside::<E, D>(&ck.domain, hat_evals, z0, &inv_diffs);
+        witness_evals_outside::<E, D>(&ck.domain, hat_evals, hat_y0, &inv_diffs, &mut witn_evals);
         // opening v is just a KZG commitment to the witness polys
         let v = plain_kzg_com(ck, &witn_evals);
+        witn_evals.zeroize();
         let tau0 = Opening { hat_y: hat_y0, v };
         // return composed commitment and state
         let state = State {
+            lock: MemoryLock::new(&evals),
             evals,
             precomputed_v: None,
         };
@@ -195,15 +228,30 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         (com, state)
     }
 
-    fn verify_commitment(ck: &Self::CommitmentKey, com: &Self::Commitment) -> bool {
+    fn verifier_key(ck: &Self::CommitmentKey) -> &Self::VerifierKey {
+        &ck.vk
+    }
+
+    fn light_verifier_key(ck: &Self::CommitmentKey) -> Self::VerifierKey {
+        ck.vk.light()
+    }
+
+    fn verify_commitment(vk: &Self::VerifierKey, com: &Self::Commitment) -> bool {
+        // the commitment may have been deserialized without validation
+        if !is_valid_commitment(com) {
+            return false;
+        }
         // compute the 'challenge' z0 at which the commitment has to be opened
-        let z0 = get_z0::<E>(&com.com_kzg);
+        let z0 = A::z0(&com.com_kzg);
         // check opening
-        plain_kzg_verify(ck, &com.com_kzg, z0, com.y0, &com.tau0)
+        plain_kzg_verify(vk, &com.com_kzg, z0, com.y0, &com.tau0)
     }
 
     fn open(ck: &Self::CommitmentKey, st: &Self::State, i: u32) -> Option<Self::Opening> {
-        if i as usize >= ck.message_length {
+        // the state may have been loaded from a file,
+        // so we check that it matches the commitment key
+        let deg = ck.domain.size();
+        if i as usize >= ck.message_length || st.evals.len() != 2 * deg {
             return None;
         }
 
@@ -211,9 +259,8 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
         // to the witness polynomial. Either we already have it
         // precomputed, or we compute it in evaluation form
         let v = if let Some(vs) = &st.precomputed_v {
-            vs[i as usize].into_affine()
+            vs.get(i as usize)?.into_affine()
         } else {
-            let deg = ck.domain.size();
             let mut witn_evals = Vec::new();
             witness_evals_inside::<E, D>(&ck.domain, &st.evals, i as usize, &mut witn_evals);
             witness_evals_inside::<E, D>(
@@ -222,92 +269,365 @@ impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VectorCommitmentScheme<E::
                 i as usize,
                 &mut witn_evals,
             );
-            plain_kzg_com(&ck, &witn_evals)
+            let v = plain_kzg_com(ck, &witn_evals);
+            witn_evals.zeroize();
+            v
         };
 
         // the opening is v and the evaluation of the masking polynomial
-        let hat_y = st.evals[i as usize + ck.domain.size()];
+        let hat_y = st.evals[i as usize + deg];
         Some(Opening { hat_y, v })
     }
 
     fn aggregate(
         _ck: &Self::CommitmentKey,
         i: u32,
-        mis: &Vec<E::ScalarField>,
-        coms: &Vec<&Self::Commitment>,
-        openings: &Vec<&Self::Opening>,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
     ) -> Option<Self::Opening> {
-        if mis.len() < 1 {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != openings.len() {
             return None;
         }
-        let le = mis.len();
 
-        // compute aggregation challenge chi
-        let chi = get_chi::<E>(i, mis, coms);
+        // compute aggregation coefficients, which
+        // are cj = chi^{j-1} for chi = Hash(i,(mi[j],com[j])_j) by default
+        let cs = A::coefficients(i, mis, coms);
 
         // compute aggregated opening
-        // hat_y = sum_{j=1}^L hat_yj * chi^{j-1}
-        // v = prod_{j=1}^L vj^{chi^{j-1}}
-        // we compute v using a MSM, and we compute mi
-        // naively, as we have the powers of chi anyway
-        let mut chi_powers = Vec::with_capacity(le);
-        chi_powers.push(E::ScalarField::one());
-        for j in 1..le {
-            chi_powers.push(chi_powers[j - 1] * chi);
-        }
+        // hat_y = sum_{j=1}^L hat_yj * cj
+        // v = prod_{j=1}^L vj^{cj}
         let vs: Vec<_> = openings.iter().map(|opening| opening.v).collect();
-        let v = <E::G1 as VariableBaseMSM>::msm(&vs, &chi_powers).unwrap();
+        let v = <E::G1 as VariableBaseMSM>::msm(&vs, &cs).unwrap();
         let v = v.into_affine();
-        let hat_y: <E as Pairing>::ScalarField = zip(openings, chi_powers)
+        let hat_y: <E as Pairing>::ScalarField = zip(openings, cs)
             .map(|(opening, c)| opening.hat_y * c)
             .sum();
-
-        // let mut hat_y = openings[le - 1].hat_y;
-        // let mut v = openings[le - 1].v.into_group();
-        // if le >= 2 {
-        //     for j in (0..=le - 2).rev() {
-        //         hat_y *= chi;
-        //         v *= chi;
-        //         hat_y += openings[j].hat_y;
-        //         v += openings[j].v.into_group();
-        //     }
-        // }
-        // let v = v.into_affine();
         Some(Opening { hat_y, v })
     }
 
     fn verify(
-        ck: &Self::CommitmentKey,
+        vk: &Self::VerifierKey,
         i: u32,
-        mis: &Vec<E::ScalarField>,
-        coms: &Vec<&Self::Commitment>,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
         opening: &Self::Opening,
     ) -> bool {
-        if mis.len() < 1 {
+        if mis.is_empty() || mis.len() != coms.len() || i as usize >= vk.message_length {
+            return false;
+        }
+        if !is_valid_element(&opening.v) || !coms.iter().all(|com| is_valid_commitment(com)) {
             return false;
         }
-        let le = mis.len();
 
-        // compute aggregation challenge chi
-        let chi = get_chi::<E>(i, mis, coms);
+        // compute aggregation coefficients
+        let cs = A::coefficients(i, mis, coms);
 
         // compute aggregated value and commitment
-        // com = prod_{j=1}^L comj^{chi^{j-1}}
-        // mi = sum_{j=1}^L mij * chi^{j-1}
-        // we compute com using a MSM, and we compute mi
-        // naively, as we have the powers of chi anyway
-        let mut chi_powers = Vec::with_capacity(le);
-        chi_powers.push(E::ScalarField::one());
-        for j in 1..le {
-            chi_powers.push(chi_powers[j - 1] * chi);
-        }
-        let com_kzgs: Vec<_> = coms.iter().map(|com| com.com_kzg).collect();
-        let com = <E::G1 as VariableBaseMSM>::msm(&com_kzgs, &chi_powers).unwrap();
-        let mi: <E as Pairing>::ScalarField = zip(mis, chi_powers).map(|(m, c)| *m * c).sum();
+        // com = prod_{j=1}^L comj^{cj}
+        // mi = sum_{j=1}^L mij * cj
+        let (com, mi) = combine_commitments::<E>(&cs, mis, coms);
 
         // verify the aggregated commitment using standard KZG
         let com = com.into_affine();
-        plain_kzg_verify_inside(ck, i as usize, &com, mi, opening)
+        plain_kzg_verify_inside(vk, i as usize, &com, mi, opening)
+    }
+
+    fn verify_serialized(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        coms_ser: &[&[u8]],
+        opening: &Self::Opening,
+    ) -> bool {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != coms_ser.len() {
+            return false;
+        }
+        if i as usize >= vk.message_length {
+            return false;
+        }
+        // commitments have been checked with verify_key when they
+        // were registered, so we only need to check the opening
+        if !is_valid_element(&opening.v) {
+            return false;
+        }
+
+        // same as verify, but we compute the coefficients
+        // from the given serialized commitments
+        let cs = A::coefficients_serialized(i, mis, coms, coms_ser);
+        let (com, mi) = combine_commitments::<E>(&cs, mis, coms);
+        let com = com.into_affine();
+        plain_kzg_verify_inside(vk, i as usize, &com, mi, opening)
+    }
+
+    fn batch_verify<R: rand::Rng>(
+        rng: &mut R,
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> bool {
+        if mis.is_empty() || mis.len() != coms.len() || mis.len() != openings.len() {
+            return false;
+        }
+        if i as usize >= vk.message_length {
+            return false;
+        }
+        if !openings.iter().all(|opening| is_valid_element(&opening.v))
+            || !coms.iter().all(|com| is_valid_commitment(com))
+        {
+            return false;
+        }
+
+        // in contrast to aggregation, the coefficients are random
+        // and unknown to the provers of the openings, so that invalid
+        // openings cannot cancel out. Then, we verify
+        // the combination of all openings using standard KZG
+        let cs: Vec<E::ScalarField> = (0..mis.len()).map(|_| E::ScalarField::rand(rng)).collect();
+        let (com, mi) = combine_commitments::<E>(&cs, mis, coms);
+        let vs: Vec<_> = openings.iter().map(|opening| opening.v).collect();
+        let v = <E::G1 as VariableBaseMSM>::msm(&vs, &cs).unwrap();
+        let hat_y = zip(openings, &cs)
+            .map(|(opening, c)| opening.hat_y * c)
+            .sum();
+        let opening = Opening {
+            hat_y,
+            v: v.into_affine(),
+        };
+        plain_kzg_verify_inside(vk, i as usize, &com.into_affine(), mi, &opening)
+    }
+}
+
+/// groups the indices j by position is[j]
+/// positions are sorted in increasing order
+fn group_by_position(is: &[u32]) -> BTreeMap<u32, Vec<usize>> {
+    let mut groups: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    for (j, i) in is.iter().enumerate() {
+        groups.entry(*i).or_default().push(j);
+    }
+    groups
+}
+
+impl<E: Pairing, D: EvaluationDomain<E::ScalarField>, H: TranscriptHash>
+    CrossAggregation<E::ScalarField> for VcKZG<E, D, TranscriptKzgHasher<H>>
+{
+    type CrossOpening = CrossOpening<E>;
+
+    /// The output is not constant-size: it contains one position and
+    /// one G1 element per distinct position in is, i.e., O(#positions)
+    /// group elements, plus a single field element. Further openings
+    /// at an already included position do not increase its size
+    fn aggregate_cross(
+        ck: &Self::CommitmentKey,
+        is: &[u32],
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::CrossOpening> {
+        if is.is_empty() || is.len() != mis.len() {
+            return None;
+        }
+        if is.len() != coms.len() || is.len() != openings.len() {
+            return None;
+        }
+
+        // KZG openings for different positions can not be combined
+        // into a single group element, as the pairing equations differ in
+        // the G2 element d[i]. Instead, we aggregate all openings for the
+        // same position as usual, and then combine the aggregated
+        // openings using a single field element hat_y
+        let mut positions = Vec::new();
+        let mut chis = Vec::new();
+        let mut vs = Vec::new();
+        let mut hat_ys = Vec::new();
+        for (i, js) in group_by_position(is) {
+            if i as usize >= ck.message_length {
+                return None;
+            }
+            let mis_i: Vec<_> = js.iter().map(|j| mis[*j]).collect();
+            let coms_i: Vec<_> = js.iter().map(|j| coms[*j]).collect();
+            let openings_i: Vec<_> = js.iter().map(|j| openings[*j]).collect();
+            let op = Self::aggregate(ck, i, &mis_i, &coms_i, &openings_i)?;
+            positions.push(i);
+            chis.push(get_chi::<E, H>(i, &mis_i, &coms_i));
+            vs.push(op.v);
+            hat_ys.push(op.hat_y);
+        }
+
+        // hat_y = sum_t hat_y[t] * rho^t
+        let rho = get_rho::<E, H>(&positions, &chis, &vs);
+        let rho_powers = powers(rho, positions.len());
+        let hat_y = zip(hat_ys, rho_powers).map(|(h, r)| h * r).sum();
+        Some(CrossOpening {
+            positions,
+            vs,
+            hat_y,
+        })
+    }
+
+    fn verify_cross(
+        vk: &Self::VerifierKey,
+        is: &[u32],
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        opening: &Self::CrossOpening,
+    ) -> bool {
+        if is.is_empty() || is.len() != mis.len() || is.len() != coms.len() {
+            return false;
+        }
+        let groups = group_by_position(is);
+        if groups.len() != opening.positions.len() || groups.len() != opening.vs.len() {
+            return false;
+        }
+        if !opening.vs.iter().all(is_valid_element)
+            || !coms.iter().all(|com| is_valid_commitment(com))
+        {
+            return false;
+        }
+
+        // for each position i, combine the commitments and values
+        // exactly as the verifier of aggregated openings does
+        let mut chis = Vec::new();
+        let mut com_is = Vec::new();
+        let mut m_is = Vec::new();
+        for (t, (i, js)) in groups.iter().enumerate() {
+            if *i != opening.positions[t] || *i as usize >= vk.message_length {
+                return false;
+            }
+            let mis_i: Vec<_> = js.iter().map(|j| mis[*j]).collect();
+            let coms_i: Vec<_> = js.iter().map(|j| coms[*j]).collect();
+            let chi = get_chi::<E, H>(*i, &mis_i, &coms_i);
+            let chi_powers = powers(chi, mis_i.len());
+            let (com, mi) = combine_commitments::<E>(&chi_powers, &mis_i, &coms_i);
+            chis.push(chi);
+            com_is.push(com);
+            m_is.push(mi);
+        }
+
+        // the check for position positions[t] would be
+        // e(com[t]*g1^{-m[t]}*h^{-hat_y[t]},g2) == e(v[t],d[positions[t]])
+        // we combine them using powers of rho, i.e., we check
+        // e(prod_t (com[t]*g1^{-m[t]})^{rho^t} * h^{-hat_y}, g2)
+        //     == prod_t e(v[t]^{rho^t}, d[positions[t]])
+        let rho = get_rho::<E, H>(&opening.positions, &chis, &opening.vs);
+        let rho_powers = powers(rho, opening.positions.len());
+        let com_is = E::G1::normalize_batch(&com_is);
+        let com = <E::G1 as VariableBaseMSM>::msm(&com_is, &rho_powers).unwrap();
+        let m: E::ScalarField = zip(&m_is, &rho_powers).map(|(m, r)| *m * r).sum();
+        let mut lhs_left = com;
+        lhs_left -= vk.g1.mul(m);
+        lhs_left -= vk.h.mul(opening.hat_y);
+        let mut left = vec![E::G1Prepared::from(-lhs_left)];
+        let mut right = vec![E::G2Prepared::from(vk.g2)];
+        for ((v, rho), pos) in opening.vs.iter().zip(&rho_powers).zip(&opening.positions) {
+            left.push(E::G1Prepared::from(v.mul(rho)));
+            right.push(E::G2Prepared::from(vk.d(*pos as usize)));
+        }
+        let q = E::multi_pairing(left, right);
+        q.is_zero()
+    }
+}
+
+impl<E: Pairing, D: EvaluationDomain<E::ScalarField>, H: TranscriptHash>
+    MergeableAggregation<E::ScalarField> for VcKZG<E, D, TranscriptKzgHasher<H>>
+{
+    type MergeableOpening = MergeableOpening<E>;
+
+    fn aggregate_mergeable(
+        ck: &Self::CommitmentKey,
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::MergeableOpening> {
+        // the coefficients depend on all openings of the aggregate,
+        // as coefficients that only depend on a single opening allow
+        // to find invalid openings whose errors cancel out
+        let size = u32::try_from(mis.len()).ok()?;
+        let op = Self::aggregate(ck, i, mis, coms, openings)?;
+        Some(MergeableOpening {
+            sizes: vec![size],
+            parts: vec![op],
+        })
+    }
+
+    fn merge(
+        _ck: &Self::CommitmentKey,
+        agg1: &Self::MergeableOpening,
+        agg2: &Self::MergeableOpening,
+    ) -> Option<Self::MergeableOpening> {
+        // the aggregates are kept side by side, and only
+        // combined by the verifier, who knows all of them
+        let sizes = [agg1.sizes.as_slice(), agg2.sizes.as_slice()].concat();
+        let parts = agg1
+            .parts
+            .iter()
+            .chain(&agg2.parts)
+            .map(|op| Opening {
+                hat_y: op.hat_y,
+                v: op.v,
+            })
+            .collect();
+        Some(MergeableOpening { sizes, parts })
+    }
+
+    fn verify_mergeable(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Self::Commitment],
+        opening: &Self::MergeableOpening,
+    ) -> bool {
+        if mis.is_empty() || mis.len() != coms.len() || i as usize >= vk.message_length {
+            return false;
+        }
+        let (sizes, parts) = (&opening.sizes, &opening.parts);
+        if parts.is_empty() || sizes.len() != parts.len() || sizes.contains(&0) {
+            return false;
+        }
+        let total = sizes
+            .iter()
+            .try_fold(0usize, |acc, size| acc.checked_add(*size as usize));
+        if total != Some(mis.len()) {
+            return false;
+        }
+        if !parts.iter().all(|op| is_valid_element(&op.v))
+            || !coms.iter().all(|com| is_valid_commitment(com))
+        {
+            return false;
+        }
+
+        // compute chi for each aggregate as aggregate does
+        let mut chis = Vec::with_capacity(parts.len());
+        let mut start = 0;
+        for size in sizes {
+            let end = start + *size as usize;
+            chis.push(get_chi::<E, H>(i, &mis[start..end], &coms[start..end]));
+            start = end;
+        }
+
+        // combine the aggregates using powers of rho, which depends
+        // on all of them. Then, the jth opening of the tth aggregate
+        // has coefficient rho^t * chis[t]^j
+        let rho = get_merge_rho::<E, H>(i, sizes, &chis, parts);
+        let rho_powers = powers(rho, parts.len());
+        let mut cs = Vec::with_capacity(mis.len());
+        for ((size, chi), r) in zip(zip(sizes, chis), &rho_powers) {
+            cs.extend(powers(chi, *size as usize).into_iter().map(|c| c * r));
+        }
+        let (com, mi) = combine_commitments::<E>(&cs, mis, coms);
+        let vs: Vec<_> = parts.iter().map(|op| op.v).collect();
+        let v = <E::G1 as VariableBaseMSM>::msm(&vs, &rho_powers).unwrap();
+        let hat_y = zip(parts, &rho_powers).map(|(op, r)| op.hat_y * r).sum();
+        let opening = Opening {
+            hat_y,
+            v: v.into_affine(),
+        };
+
+        // verify the combination using standard KZG
+        plain_kzg_verify_inside(vk, i as usize, &com.into_affine(), mi, &opening)
     }
 }
 
@@ -316,8 +636,9 @@ mod tests {
     use std::fs::File;
     use std::ops::Mul;
 
-    use ark_bls12_381::Bls12_381;
-    use ark_ec::{bls12::Bls12, pairing::Pairing, CurveGroup};
+    use ark_bls12_381::{Bls12_381, Fq, G1Affine};
+    use ark_ec::{bls12::Bls12, pairing::Pairing, AffineRepr, CurveGroup};
+    use ark_ff::{One, PrimeField, UniformRand};
     use ark_poly::{univariate::DensePolynomial, Radix2EvaluationDomain};
     use ark_poly::{DenseUVPolynomial, EvaluationDomain};
     use ark_serialize::CanonicalSerialize;
@@ -326,10 +647,13 @@ mod tests {
 
     use super::kzg_types::CommitmentKey;
 
-    use super::VcKZG;
+    use super::{
+        Blake3KzgHasher, Commitment, Keccak256KzgHasher, MergeableOpening, Opening, VcKZG,
+    };
     use crate::vectorcommitment::{
-        VectorCommitmentScheme, _vc_test_agg_opening, _vc_test_com_ver, _vc_test_opening,
-        _vc_test_setup,
+        _vc_test_agg_opening, _vc_test_batch_verify, _vc_test_com_ver, _vc_test_cross_agg_opening,
+        _vc_test_malformed_inputs, _vc_test_merge_opening, _vc_test_opening, _vc_test_setup,
+        MergeableAggregation, VectorCommitmentScheme,
     };
 
     type F = <Bls12_381 as Pairing>::ScalarField;
@@ -405,6 +729,38 @@ mod tests {
         assert_eq!(lhs.into_affine(), rhs.into_affine());
     }
 
+    /// test that the light verifier key computes the
+    /// precomputed denominators d and verifies openings
+    #[test]
+    fn kzg_vc_test_light_verifier_key() {
+        let mut rng = ark_std::rand::thread_rng();
+        let message_length = 14;
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+        let vk = VC::light_verifier_key(&ck);
+        assert!(vk.d.is_empty());
+        assert_eq!(vk.g1, ck.u[0]);
+        assert_eq!(vk.h, ck.hat_u[0]);
+        for i in 0..message_length {
+            assert_eq!(vk.d(i), ck.vk.d[i]);
+            assert_eq!(ck.vk.d(i), ck.vk.d[i]);
+        }
+        assert!(vk.compressed_size() < ck.vk.compressed_size());
+
+        let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+        assert!(VC::verify_commitment(&vk, &com));
+        let op = VC::open(&ck, &st, 3).unwrap();
+        assert!(VC::verify(&vk, 3, &m[3..4], &[&com], &op));
+        assert!(!VC::verify(&vk, 4, &m[3..4], &[&com], &op));
+        assert!(!VC::verify(
+            &vk,
+            message_length as u32,
+            &m[3..4],
+            &[&com],
+            &op
+        ));
+    }
+
     #[test]
     fn kzg_vc_test_setup() {
         _vc_test_setup::<F, VC>();
@@ -419,8 +775,181 @@ mod tests {
         _vc_test_opening::<F, VC>();
     }
 
+    #[test]
+    fn kzg_vc_test_malformed_inputs() {
+        _vc_test_malformed_inputs::<F, VC>();
+    }
+
+    #[test]
+    fn kzg_vc_test_batch_verify() {
+        _vc_test_batch_verify::<F, VC>();
+    }
+
+    /// returns a point of low order, i.e., on the curve but
+    /// outside of the prime-order subgroup, and not the identity
+    fn low_order_point() -> G1Affine {
+        let mut x = Fq::one();
+        loop {
+            if let Some(p) = G1Affine::get_point_from_x_unchecked(x, true) {
+                // r * p has order dividing the cofactor
+                let t = p.mul_bigint(F::MODULUS).into_affine();
+                if !t.is_zero() {
+                    return t;
+                }
+            }
+            x += Fq::one();
+        }
+    }
+
+    /// build a commitment from its components
+    fn commitment(com_kzg: G1Affine, y0: F, hat_y: F, v: G1Affine) -> Commitment<Bls12_381> {
+        Commitment {
+            com_kzg,
+            y0,
+            tau0: Opening { hat_y, v },
+        }
+    }
+
+    /// test that commitments and openings containing the identity or
+    /// points outside of the prime-order subgroup are rejected
+    #[test]
+    fn kzg_vc_test_invalid_elements() {
+        let mut rng = ark_std::rand::thread_rng();
+        let message_length = 5;
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+        let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+        let op = VC::open(&ck, &st, 0).unwrap();
+        assert!(VC::verify_commitment(&ck.vk, &com));
+        assert!(VC::verify(&ck.vk, 0, &m[..1], &[&com], &op));
+
+        let t = low_order_point();
+        assert!(t.is_on_curve() && !t.is_in_correct_subgroup_assuming_on_curve());
+        let zero = G1Affine::zero();
+        let (c, y0, hat_y0, v0) = (com.com_kzg, com.y0, com.tau0.hat_y, com.tau0.v);
+
+        // such commitments are rejected
+        let shifted_c = (c + t).into_affine();
+        let shifted_v0 = (v0 + t).into_affine();
+        for bad in [
+            commitment(zero, y0, hat_y0, v0),
+            commitment(t, y0, hat_y0, v0),
+            commitment(shifted_c, y0, hat_y0, v0),
+            commitment(c, y0, hat_y0, zero),
+            commitment(c, y0, hat_y0, t),
+            commitment(c, y0, hat_y0, shifted_v0),
+        ] {
+            assert!(!VC::verify_commitment(&ck.vk, &bad));
+            assert!(!VC::verify(&ck.vk, 0, &m[..1], &[&bad], &op));
+        }
+
+        // such openings are rejected
+        for v in [zero, t, (op.v + t).into_affine()] {
+            let bad = Opening { hat_y: op.hat_y, v };
+            assert!(!VC::verify(&ck.vk, 0, &m[..1], &[&com], &bad));
+        }
+
+        // validated deserialization already rejects low-order points,
+        // while unchecked deserialization relies on verify_commitment
+        let bad = commitment(shifted_c, y0, hat_y0, v0);
+        let mut bytes = Vec::new();
+        bad.serialize_uncompressed(&mut bytes).unwrap();
+        assert!(Commitment::<Bls12_381>::deserialize_uncompressed(&bytes[..]).is_err());
+        let bad = Commitment::<Bls12_381>::deserialize_uncompressed_unchecked(&bytes[..]).unwrap();
+        assert!(!VC::verify_commitment(&ck.vk, &bad));
+    }
+
     #[test]
     fn kzg_vc_test_agg_opening() {
         _vc_test_agg_opening::<F, VC>();
     }
+
+    #[test]
+    fn kzg_vc_test_cross_agg_opening() {
+        _vc_test_cross_agg_opening::<F, VC>();
+    }
+
+    #[test]
+    fn kzg_vc_test_merge_opening() {
+        _vc_test_merge_opening::<F, VC>();
+    }
+
+    /// test that invalid aggregates can not cancel out when merged,
+    /// e.g., when values are shifted by errors whose sum is zero
+    #[test]
+    fn kzg_vc_test_merge_cancel() {
+        let mut rng = ark_std::rand::thread_rng();
+        let message_length = 5;
+        let i = 3;
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+        let mut coms = Vec::new();
+        let mut ops = Vec::new();
+        let mut mis = Vec::new();
+        for _ in 0..4 {
+            let m: Vec<F> = (0..message_length).map(|_| F::rand(&mut rng)).collect();
+            let (com, st) = VC::commit(&mut rng, &ck, &m);
+            ops.push(VC::open(&ck, &st, i).unwrap());
+            mis.push(m[i as usize]);
+            coms.push(com);
+        }
+        let coms_r: Vec<_> = coms.iter().collect();
+        let ops_r: Vec<_> = ops.iter().collect();
+        let agg1 = VC::aggregate_mergeable(&ck, i, &mis[..2], &coms_r[..2], &ops_r[..2]).unwrap();
+        let agg2 = VC::aggregate_mergeable(&ck, i, &mis[2..], &coms_r[2..], &ops_r[2..]).unwrap();
+        let agg = VC::merge(&ck, &agg1, &agg2).unwrap();
+        assert_eq!(agg.sizes, vec![2, 2]);
+        assert!(VC::verify_mergeable(&ck.vk, i, &mis, &coms_r, &agg));
+
+        // shifting the witnesses of the aggregates by opposite
+        // errors does not change their sum, but makes ver reject
+        let e = G1Affine::rand(&mut rng);
+        let shifted = MergeableOpening {
+            sizes: agg.sizes.clone(),
+            parts: vec![
+                Opening {
+                    hat_y: agg.parts[0].hat_y,
+                    v: (agg.parts[0].v + e).into_affine(),
+                },
+                Opening {
+                    hat_y: agg.parts[1].hat_y,
+                    v: (agg.parts[1].v - e).into_affine(),
+                },
+            ],
+        };
+        assert!(!VC::verify_mergeable(&ck.vk, i, &mis, &coms_r, &shifted));
+
+        // the same holds for opposite errors in the values
+        let mut mis_wrong = mis.clone();
+        mis_wrong[0] += F::one();
+        mis_wrong[2] -= F::one();
+        assert!(!VC::verify_mergeable(&ck.vk, i, &mis_wrong, &coms_r, &agg));
+
+        // the aggregates can not be regrouped
+        let mut regrouped = VC::merge(&ck, &agg1, &agg2).unwrap();
+        regrouped.sizes = vec![1, 3];
+        assert!(!VC::verify_mergeable(&ck.vk, i, &mis, &coms_r, &regrouped));
+        let swapped = [mis[2], mis[1], mis[0], mis[3]];
+        let coms_swapped = [coms_r[2], coms_r[1], coms_r[0], coms_r[3]];
+        assert!(!VC::verify_mergeable(
+            &ck.vk,
+            i,
+            &swapped,
+            &coms_swapped,
+            &agg
+        ));
+    }
+
+    #[test]
+    fn kzg_vc_test_keccak_agg_opening() {
+        _vc_test_com_ver::<F, VcKZG<Bls12_381, D, Keccak256KzgHasher>>();
+        _vc_test_agg_opening::<F, VcKZG<Bls12_381, D, Keccak256KzgHasher>>();
+        _vc_test_cross_agg_opening::<F, VcKZG<Bls12_381, D, Keccak256KzgHasher>>();
+    }
+
+    #[test]
+    fn kzg_vc_test_blake3_agg_opening() {
+        _vc_test_com_ver::<F, VcKZG<Bls12_381, D, Blake3KzgHasher>>();
+        _vc_test_agg_opening::<F, VcKZG<Bls12_381, D, Blake3KzgHasher>>();
+        _vc_test_merge_opening::<F, VcKZG<Bls12_381, D, Blake3KzgHasher>>();
+    }
 }
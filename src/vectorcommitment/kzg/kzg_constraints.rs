@@ -0,0 +1,328 @@
+use ark_crypto_primitives::sponge::constraints::CryptographicSpongeVar;
+use ark_crypto_primitives::sponge::poseidon::constraints::PoseidonSpongeVar;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::{pairing::Pairing, AffineRepr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_poly::EvaluationDomain;
+use ark_r1cs_std::alloc::{AllocVar, AllocationMode};
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_r1cs_std::ToConstraintFieldGadget;
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, SynthesisError};
+use std::borrow::Borrow;
+
+use super::kzg_hasher::{POSEIDON_AGG_DST, POSEIDON_COEFF_BITS};
+use super::{Opening, VerifierKey};
+use crate::lotteryscheme::challenge::poseidon_config;
+
+// This module contains gadgets that verify aggregated openings of the
+// Simulation Extractable KZG Vector commitment inside of a circuit
+// over the base field of G1, using PoseidonKzgHasher
+
+/// the part of the commitment key that is needed to
+/// verify (aggregated) openings for a fixed position i
+pub struct VerifierKeyVar<E: Pairing, PV: PairingVar<E, E::BaseField>> {
+    /// generator g1 = u[0]
+    pub g1: PV::G1Var,
+    /// hiding generator h = hat_u[0]
+    pub h: PV::G1Var,
+    /// generator g2
+    pub g2: PV::G2Var,
+    /// d[i] = g2^{alpha - zi}
+    pub d: PV::G2Var,
+}
+
+/// opening allocated inside of a circuit. The scalar
+/// hat_y is represented by its little-endian bits
+pub struct OpeningVar<E: Pairing, PV: PairingVar<E, E::BaseField>> {
+    pub hat_y: Vec<Boolean<E::BaseField>>,
+    pub v: PV::G1Var,
+}
+
+/// Computes the little-endian bits of a scalar
+pub fn scalar_to_bits_le<F: PrimeField>(x: &F) -> Vec<bool> {
+    let mut bits = x.into_bigint().to_bits_le();
+    bits.truncate(F::MODULUS_BIT_SIZE as usize);
+    bits
+}
+
+impl<E: Pairing, PV: PairingVar<E, E::BaseField>> VerifierKeyVar<E, PV> {
+    /// Allocates the verifier key for position i
+    pub fn new<D: EvaluationDomain<E::ScalarField>>(
+        cs: impl Into<Namespace<E::BaseField>>,
+        vk: &VerifierKey<E, D>,
+        i: u32,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        if i as usize >= vk.message_length {
+            return Err(SynthesisError::AssignmentMissing);
+        }
+        let d = vk.d(i as usize);
+        let g1 = PV::G1Var::new_variable(cs.clone(), || Ok(vk.g1.into_group()), mode)?;
+        let h = PV::G1Var::new_variable(cs.clone(), || Ok(vk.h.into_group()), mode)?;
+        let g2 = PV::G2Var::new_variable(cs.clone(), || Ok(vk.g2.into_group()), mode)?;
+        let d = PV::G2Var::new_variable(cs, || Ok(d.into_group()), mode)?;
+        Ok(Self { g1, h, g2, d })
+    }
+}
+
+impl<E: Pairing, PV: PairingVar<E, E::BaseField>> AllocVar<Opening<E>, E::BaseField>
+    for OpeningVar<E, PV>
+{
+    fn new_variable<T: Borrow<Opening<E>>>(
+        cs: impl Into<Namespace<E::BaseField>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let opening = f().map(|op| {
+            let op = op.borrow();
+            (scalar_to_bits_le(&op.hat_y), op.v.into_group())
+        });
+        let hat_y = Vec::<Boolean<E::BaseField>>::new_variable(
+            cs.clone(),
+            || {
+                opening
+                    .as_ref()
+                    .map(|op| op.0.clone())
+                    .map_err(|_| SynthesisError::AssignmentMissing)
+            },
+            mode,
+        )?;
+        let v = PV::G1Var::new_variable(
+            cs,
+            || {
+                opening
+                    .as_ref()
+                    .map(|op| op.1)
+                    .map_err(|_| SynthesisError::AssignmentMissing)
+            },
+            mode,
+        )?;
+        Ok(Self { hat_y, v })
+    }
+}
+
+/// Computes the aggregation coefficients exactly as PoseidonKzgHasher,
+/// given the little-endian bits of the values mis[j]. Each coefficient
+/// is output as its POSEIDON_COEFF_BITS little-endian bits
+pub fn coefficients_gadget<E: Pairing, PV: PairingVar<E, E::BaseField>>(
+    cs: ConstraintSystemRef<E::BaseField>,
+    i: u32,
+    mis: &[Vec<Boolean<E::BaseField>>],
+    coms: &[PV::G1Var],
+) -> Result<Vec<Vec<Boolean<E::BaseField>>>, SynthesisError>
+where
+    E::BaseField: Absorb,
+    PV::G1Var: ToConstraintFieldGadget<E::BaseField>,
+{
+    // input = (DST, i, (mi[j], com[j])_j)
+    let mut input: Vec<FpVar<E::BaseField>> = POSEIDON_AGG_DST
+        .to_sponge_field_elements_as_vec::<E::BaseField>()
+        .into_iter()
+        .map(FpVar::constant)
+        .collect();
+    input.push(FpVar::constant(E::BaseField::from(i as u64)));
+    for j in 0..mis.len() {
+        input.push(Boolean::le_bits_to_fp_var(&mis[j])?);
+        input.extend(coms[j].to_constraint_field()?);
+    }
+    let mut sponge = PoseidonSpongeVar::new(cs, &poseidon_config::<E::BaseField>());
+    sponge.absorb(&input)?;
+    let cs = sponge.squeeze_field_elements(mis.len())?;
+    let mut res = Vec::with_capacity(cs.len());
+    for c in cs {
+        let mut bits = c.to_bits_le()?;
+        bits.truncate(POSEIDON_COEFF_BITS);
+        res.push(bits);
+    }
+    Ok(res)
+}
+
+/// Verifies an aggregated opening for position i of commitments
+/// coms[j] to values mis[j], where values are given by their
+/// little-endian bits. Mirrors VcKZG::verify with PoseidonKzgHasher
+pub fn verify_aggregated_gadget<E: Pairing, PV: PairingVar<E, E::BaseField>>(
+    cs: ConstraintSystemRef<E::BaseField>,
+    vk: &VerifierKeyVar<E, PV>,
+    i: u32,
+    mis: &[Vec<Boolean<E::BaseField>>],
+    coms: &[PV::G1Var],
+    opening: &OpeningVar<E, PV>,
+) -> Result<Boolean<E::BaseField>, SynthesisError>
+where
+    E::BaseField: Absorb,
+    PV::G1Var: ToConstraintFieldGadget<E::BaseField>,
+{
+    if mis.is_empty() || mis.len() != coms.len() {
+        return Ok(Boolean::FALSE);
+    }
+
+    // compute aggregated value and commitment
+    // com = prod_{j=1}^L comj^{cj}
+    // g1^{mi} = prod_{j=1}^L (g1^{cj})^{mij}
+    // we never compute mi itself, as this would
+    // require arithmetic in the scalar field.
+    // Note that we multiply by cj first, as mij may be zero
+    // and scalar_mul_le is not satisfiable for the identity
+    let cs = coefficients_gadget::<E, PV>(cs, i, mis, coms)?;
+    let mut com = PV::G1Var::zero();
+    let mut g1_mi = PV::G1Var::zero();
+    for j in 0..mis.len() {
+        com += coms[j].scalar_mul_le(cs[j].iter())?;
+        let g1_cj = vk.g1.scalar_mul_le(cs[j].iter())?;
+        g1_mi += g1_cj.scalar_mul_le(mis[j].iter())?;
+    }
+
+    // check e(com*g1^{-mi}*h^{-hat_y},g2) == e(v,d[i])
+    let lhs_left = com - g1_mi - vk.h.scalar_mul_le(opening.hat_y.iter())?;
+    let left = vec![
+        PV::prepare_g1(&lhs_left.negate()?)?,
+        PV::prepare_g1(&opening.v)?,
+    ];
+    let right = vec![PV::prepare_g2(&vk.g2)?, PV::prepare_g2(&vk.d)?];
+    let q = PV::product_of_pairings(&left, &right)?;
+    q.is_eq(&PV::GTVar::one())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_377::{constraints::PairingVar as Bls12_377PairingVar, Bls12_377};
+    use ark_ec::pairing::Pairing;
+    use ark_poly::Radix2EvaluationDomain;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_r1cs_std::prelude::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::UniformRand;
+
+    use super::{coefficients_gadget, scalar_to_bits_le, OpeningVar, VerifierKeyVar};
+    use crate::vectorcommitment::kzg::{KzgHasher, PoseidonKzgHasher, VcKZG};
+    use crate::vectorcommitment::VectorCommitmentScheme;
+
+    type E = Bls12_377;
+    type F = <E as Pairing>::ScalarField;
+    type CF = <E as Pairing>::BaseField;
+    type PV = Bls12_377PairingVar;
+    type D = Radix2EvaluationDomain<F>;
+    type VC = VcKZG<E, D, PoseidonKzgHasher>;
+    type G1Var = <PV as PairingVar<E, CF>>::G1Var;
+
+    /// allocates the bits of the given scalars as witnesses
+    fn alloc_scalars(
+        cs: ark_relations::r1cs::ConstraintSystemRef<CF>,
+        xs: &[F],
+    ) -> Vec<Vec<Boolean<CF>>> {
+        xs.iter()
+            .map(|x| {
+                Vec::<Boolean<CF>>::new_witness(cs.clone(), || Ok(scalar_to_bits_le(x))).unwrap()
+            })
+            .collect()
+    }
+
+    /// test that the gadget computes the same
+    /// aggregation coefficients as PoseidonKzgHasher
+    #[test]
+    fn kzg_constraints_test_coefficients() {
+        let mut rng = ark_std::rand::thread_rng();
+        let ck = VC::setup(&mut rng, 6).unwrap();
+        let mut coms = Vec::new();
+        let mut mis = Vec::new();
+        for _ in 0..3 {
+            let m: Vec<F> = (0..6).map(|_| F::rand(&mut rng)).collect();
+            let (com, _) = VC::commit(&mut rng, &ck, &m);
+            mis.push(m[2]);
+            coms.push(com);
+        }
+        let coms: Vec<_> = coms.iter().collect();
+        let expected = PoseidonKzgHasher::coefficients(2, &mis, &coms);
+
+        let cs = ConstraintSystem::<CF>::new_ref();
+        let mis_var = alloc_scalars(cs.clone(), &mis);
+        let coms_var: Vec<_> = coms
+            .iter()
+            .map(|com| G1Var::new_witness(cs.clone(), || Ok(com.com_kzg)).unwrap())
+            .collect();
+        let cs_var = coefficients_gadget::<E, PV>(cs.clone(), 2, &mis_var, &coms_var).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        for j in 0..3 {
+            let bits: Vec<bool> = cs_var[j].iter().map(|b| b.value().unwrap()).collect();
+            let mut expected_bits = scalar_to_bits_le(&expected[j]);
+            expected_bits.resize(bits.len(), false);
+            assert_eq!(bits, expected_bits);
+        }
+    }
+
+    /// test that the gadget accepts exactly
+    /// when the native verifier accepts
+    #[test]
+    fn kzg_constraints_test_verify() {
+        let mut rng = ark_std::rand::thread_rng();
+        let i = 3;
+        let ck = VC::setup(&mut rng, 6).unwrap();
+        let mut coms = Vec::new();
+        let mut mis = Vec::new();
+        let mut openings = Vec::new();
+        for _ in 0..2 {
+            let m: Vec<F> = (0..6).map(|_| F::rand(&mut rng)).collect();
+            let (com, st) = VC::commit(&mut rng, &ck, &m);
+            mis.push(m[i as usize]);
+            openings.push(VC::open(&ck, &st, i).unwrap());
+            coms.push(com);
+        }
+        let coms: Vec<_> = coms.iter().collect();
+        let openings: Vec<_> = openings.iter().collect();
+        let agg = VC::aggregate(&ck, i, &mis, &coms, &openings).unwrap();
+
+        // a correct aggregate and one for a wrong value
+        let mut wrong_mis = mis.clone();
+        wrong_mis[1] += F::from(1u64);
+        for (mis, expected) in [(&mis, true), (&wrong_mis, false)] {
+            assert_eq!(VC::verify(&ck.vk, i, mis, &coms, &agg), expected);
+
+            let cs = ConstraintSystem::<CF>::new_ref();
+            let vk =
+                VerifierKeyVar::<E, PV>::new(cs.clone(), &ck.vk, i, AllocationMode::Input).unwrap();
+            let mis_var = alloc_scalars(cs.clone(), mis);
+            let coms_var: Vec<_> = coms
+                .iter()
+                .map(|com| G1Var::new_input(cs.clone(), || Ok(com.com_kzg)).unwrap())
+                .collect();
+            let op_var = OpeningVar::<E, PV>::new_witness(cs.clone(), || Ok(&agg)).unwrap();
+            let res =
+                super::verify_aggregated_gadget(cs.clone(), &vk, i, &mis_var, &coms_var, &op_var)
+                    .unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            assert_eq!(res.value().unwrap(), expected);
+        }
+    }
+
+    /// test that the circuit is satisfiable for zero values,
+    /// which are common, as challenges only have log_k bits
+    #[test]
+    fn kzg_constraints_test_zero_value() {
+        let mut rng = ark_std::rand::thread_rng();
+        let i = 3;
+        let ck = VC::setup(&mut rng, 6).unwrap();
+        let mut m: Vec<F> = (0..6).map(|_| F::rand(&mut rng)).collect();
+        m[i as usize] = F::from(0u64);
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+        let op = VC::open(&ck, &st, i).unwrap();
+        let mis = [m[i as usize]];
+        let agg = VC::aggregate(&ck, i, &mis, &[&com], &[&op]).unwrap();
+        assert!(VC::verify(&ck.vk, i, &mis, &[&com], &agg));
+
+        let cs = ConstraintSystem::<CF>::new_ref();
+        let vk =
+            VerifierKeyVar::<E, PV>::new(cs.clone(), &ck.vk, i, AllocationMode::Input).unwrap();
+        let mis_var = alloc_scalars(cs.clone(), &mis);
+        let coms_var = [G1Var::new_input(cs.clone(), || Ok(com.com_kzg)).unwrap()];
+        let op_var = OpeningVar::<E, PV>::new_witness(cs.clone(), || Ok(&agg)).unwrap();
+        let res = super::verify_aggregated_gadget(cs.clone(), &vk, i, &mis_var, &coms_var, &op_var)
+            .unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        assert!(res.value().unwrap());
+    }
+}
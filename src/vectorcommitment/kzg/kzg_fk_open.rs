@@ -1,7 +1,8 @@
 use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
 use ark_poly::EvaluationDomain;
+use ark_std::ops::Mul;
+use ark_std::vec::Vec;
 use ark_std::Zero;
-use std::ops::Mul;
 
 use super::{CommitmentKey, State};
 
@@ -50,7 +51,7 @@ pub fn all_openings<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
 
     // do a componentwise product to get the final openings
     for i in 0..dsize {
-        openings[i] = openings[i] + hat_openings[i];
+        openings[i] += hat_openings[i];
     }
 
     // write it into state
@@ -66,7 +67,7 @@ fn all_openings_single<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
     evals: &[E::ScalarField],
 ) -> Vec<E::G1> {
     // compute the base polynomial h
-    let coeffs = domain.ifft(&evals);
+    let coeffs = domain.ifft(evals);
     let mut h = base_poly::<E, D>(y, domain, &coeffs);
 
     // evaluate h in the exponent using FFT
@@ -121,9 +122,7 @@ fn base_poly<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
         hat_c.push(E::ScalarField::zero());
     }
     hat_c.push(coeffs[d]);
-    for i in 0..d {
-        hat_c.push(coeffs[i]);
-    }
+    hat_c.extend_from_slice(&coeffs[..d]);
     //let v = domain2.fft(&hat_c);
     domain2.fft_in_place(&mut hat_c);
     let v = hat_c;
@@ -138,8 +137,7 @@ fn base_poly<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
     //let hat_h = domain2.ifft(&u);
     domain2.ifft_in_place(&mut u);
     let hat_h = u;
-    let h = hat_h[0..d].to_vec();
-    h
+    hat_h[0..d].to_vec()
 }
 
 #[cfg(test)]
@@ -218,10 +216,10 @@ mod tests {
             let evals = ck.domain.fft(&f.coeffs);
             // precompute the openings naively using long division (very slow)
             let mut naive: Vec<<Bls12_381 as Pairing>::G1Affine> = Vec::new();
-            for i in 0..ck.domain.size() {
+            for (i, eval) in evals.iter().enumerate() {
                 // witness poly using long division
                 let z = ck.domain.element(i);
-                let fshift = &f - &DensePolynomial::from_coefficients_vec(vec![evals[i]]);
+                let fshift = &f - &DensePolynomial::from_coefficients_vec(vec![*eval]);
                 let div = DensePolynomial::from_coefficients_vec(vec![-z, F::one()]);
                 let witness_poly = &fshift / &div;
                 // commit to witness poly at alpha
@@ -254,7 +252,7 @@ mod tests {
             let ck = VcKZG::<Bls12_381, D>::setup(&mut rng, degree - 1).unwrap();
 
             // commit to something
-            let m = (0..degree - 1).map(|_| F::rand(&mut rng)).collect();
+            let m: Vec<F> = (0..degree - 1).map(|_| F::rand(&mut rng)).collect();
             let (_com, mut st) = VcKZG::<Bls12_381, D>::commit(&mut rng, &ck, &m);
 
             // compute all the openings freshly
@@ -268,7 +266,7 @@ mod tests {
             all_openings(&ck, &mut st);
 
             // check that all openings are the same
-            let precomputed = st.precomputed_v.unwrap();
+            let precomputed = st.precomputed_v.as_ref().unwrap();
             for i in 0..ck.message_length {
                 assert_eq!(precomputed[i], openings[i]);
             }
@@ -0,0 +1,147 @@
+use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
+use ark_crypto_primitives::sponge::{Absorb, CryptographicSponge};
+use ark_ec::pairing::Pairing;
+use ark_ff::{BigInteger, PrimeField, ToConstraintField};
+
+use ark_std::marker::PhantomData;
+use ark_std::vec::Vec;
+use sha2::Sha256;
+use sha3::Keccak256;
+
+use super::kzg_utils::{get_chi, get_chi_serialized, get_z0, powers};
+use super::Commitment;
+use crate::lotteryscheme::challenge::poseidon_config;
+use crate::transcript::TranscriptHash;
+
+/// trait representing the hash functions of the vector commitment,
+/// namely the one that computes the point z0 at which commitments
+/// are opened, and the one that computes the coefficients c_j with
+/// which openings for position i of commitments coms[j] to values
+/// mis[j] are aggregated, i.e., v = prod_{j=1}^L vj^{cj}
+pub trait KzgHasher<E: Pairing> {
+    /// Computes the evaluation point z0 for a commitment
+    fn z0(com_kzg: &E::G1Affine) -> E::ScalarField {
+        get_z0::<E, Sha256>(com_kzg)
+    }
+
+    /// Computes the aggregation coefficients
+    fn coefficients(i: u32, mis: &[E::ScalarField], coms: &[&Commitment<E>])
+        -> Vec<E::ScalarField>;
+
+    /// Same as coefficients, but may make use of the
+    /// uncompressed serializations of the commitments
+    fn coefficients_serialized(
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Commitment<E>],
+        _coms_ser: &[&[u8]],
+    ) -> Vec<E::ScalarField> {
+        Self::coefficients(i, mis, coms)
+    }
+}
+
+/// hashing based on a Fiat-Shamir transcript with backend H.
+/// Coefficients are the powers of chi = Hash(i,(mi[j],com[j])_j)
+pub struct TranscriptKzgHasher<H: TranscriptHash = Sha256> {
+    _h: PhantomData<H>,
+}
+
+/// hashing based on SHA-256, which is used by default
+pub type Sha256KzgHasher = TranscriptKzgHasher<Sha256>;
+/// hashing based on Keccak-256
+pub type Keccak256KzgHasher = TranscriptKzgHasher<Keccak256>;
+/// hashing based on BLAKE3
+pub type Blake3KzgHasher = TranscriptKzgHasher<blake3::Hasher>;
+
+impl<E: Pairing, H: TranscriptHash> KzgHasher<E> for TranscriptKzgHasher<H> {
+    fn z0(com_kzg: &E::G1Affine) -> E::ScalarField {
+        get_z0::<E, H>(com_kzg)
+    }
+
+    fn coefficients(
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Commitment<E>],
+    ) -> Vec<E::ScalarField> {
+        powers(get_chi::<E, H>(i, mis, coms), mis.len())
+    }
+
+    fn coefficients_serialized(
+        i: u32,
+        mis: &[E::ScalarField],
+        _coms: &[&Commitment<E>],
+        coms_ser: &[&[u8]],
+    ) -> Vec<E::ScalarField> {
+        powers(get_chi_serialized::<E, H>(i, mis, coms_ser), mis.len())
+    }
+}
+
+/// aggregation based on Poseidon over the base field of G1,
+/// which is the native field of circuits verifying pairings.
+/// The point z0 is computed using SHA-256, as commitments
+/// are not verified inside of circuits.
+/// Coefficient c_j is given by the lowest POSEIDON_COEFF_BITS bits
+/// of the jth squeezed element. Only the group element com_kzg of each
+/// commitment is hashed, as the verifier of aggregated openings
+/// does not use the remaining parts of the commitment
+pub struct PoseidonKzgHasher;
+
+/// domain separation tag for aggregation with Poseidon
+pub const POSEIDON_AGG_DST: &[u8] = b"KZG-AGG-POSEIDON//";
+
+/// bit length of the aggregation coefficients for Poseidon.
+/// This is enough for soundness and keeps scalar
+/// multiplications inside of circuits cheap
+pub const POSEIDON_COEFF_BITS: usize = 128;
+
+/// Computes the input to Poseidon for aggregation,
+/// namely (DST, i, (mi[j], com[j])_j) as base field elements
+pub fn poseidon_agg_input<E: Pairing>(
+    i: u32,
+    mis: &[E::ScalarField],
+    coms: &[&Commitment<E>],
+) -> Vec<E::BaseField>
+where
+    E::BaseField: Absorb,
+    E::G1Affine: ToConstraintField<E::BaseField>,
+{
+    let mut input = POSEIDON_AGG_DST.to_sponge_field_elements_as_vec::<E::BaseField>();
+    input.push(E::BaseField::from(i as u64));
+    for j in 0..mis.len() {
+        // the scalar field is smaller than the base field
+        // so the integer mis[j] is a base field element
+        let mi_bytes = mis[j].into_bigint().to_bytes_le();
+        input.push(E::BaseField::from_le_bytes_mod_order(&mi_bytes));
+        input.extend(
+            coms[j]
+                .com_kzg
+                .to_field_elements()
+                .expect("Failed to convert com in poseidon_agg_input"),
+        );
+    }
+    input
+}
+
+/// Truncates a base field element to its
+/// lowest POSEIDON_COEFF_BITS bits
+pub fn truncate_coeff<E: Pairing>(c: &E::BaseField) -> E::ScalarField {
+    let bytes = c.into_bigint().to_bytes_le();
+    E::ScalarField::from_le_bytes_mod_order(&bytes[..POSEIDON_COEFF_BITS / 8])
+}
+
+impl<E: Pairing> KzgHasher<E> for PoseidonKzgHasher
+where
+    E::BaseField: Absorb,
+    E::G1Affine: ToConstraintField<E::BaseField>,
+{
+    fn coefficients(
+        i: u32,
+        mis: &[E::ScalarField],
+        coms: &[&Commitment<E>],
+    ) -> Vec<E::ScalarField> {
+        let mut sponge = PoseidonSponge::new(&poseidon_config::<E::BaseField>());
+        sponge.absorb(&poseidon_agg_input::<E>(i, mis, coms));
+        let cs: Vec<E::BaseField> = sponge.squeeze_field_elements(mis.len());
+        cs.iter().map(truncate_coeff::<E>).collect()
+    }
+}
@@ -1,14 +1,76 @@
-use ark_ec::pairing::Pairing;
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
 use ark_poly::EvaluationDomain;
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use std::marker::PhantomData;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ark_std::marker::PhantomData;
+use ark_std::ops::Mul;
+use ark_std::vec::Vec;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::kzg_hasher::{KzgHasher, Sha256KzgHasher};
+use crate::memlock::MemoryLock;
 
 // This module contains types for the Simulation Extractable KZG Vector commitment
 
 /// Simulation-Extractable vector commitment based on KZG
-pub struct VcKZG<E: Pairing, D: EvaluationDomain<E::ScalarField>> {
+/// A determines how evaluation points and aggregation coefficients are computed
+pub struct VcKZG<E: Pairing, D: EvaluationDomain<E::ScalarField>, A: KzgHasher<E> = Sha256KzgHasher>
+{
     _e: PhantomData<E>,
     _d: PhantomData<D>,
+    _a: PhantomData<A>,
+}
+
+/// verifier key, which only contains the elements
+/// of the commitment key that are needed for verification
+#[derive(CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct VerifierKey<E: Pairing, D: EvaluationDomain<E::ScalarField>> {
+    /// length of messages to which we commit
+    pub message_length: usize,
+
+    /// evaluation domain that we use to represent vectors
+    pub domain: D,
+
+    /// generator of G1, i.e., u[0]
+    pub g1: E::G1Affine,
+
+    /// hiding generator of G1, i.e., hat_u[0]
+    pub h: E::G1Affine,
+
+    /// generator of G2
+    pub g2: E::G2Affine,
+
+    /// r = g2^{\alpha}, needed for verification
+    pub r: E::G2Affine,
+
+    /// precomputed denominators in the exponent
+    /// namely, d[i] = g2^{alpha - zi},
+    /// where zi is the ith evaluation point
+    /// i should range from 0 to message_length,
+    /// or d is empty if they are computed on the fly
+    pub d: Vec<E::G2Affine>,
+}
+
+impl<E: Pairing, D: EvaluationDomain<E::ScalarField>> VerifierKey<E, D> {
+    /// returns d[i] = g2^{alpha - zi}, either precomputed
+    /// or computed from r and g2
+    pub fn d(&self, i: usize) -> E::G2Affine {
+        match self.d.get(i) {
+            Some(d) => *d,
+            None => (self.r.into_group() - self.g2.mul(self.domain.element(i))).into_affine(),
+        }
+    }
+
+    /// returns a copy of the verifier key without
+    /// the precomputed denominators d
+    pub fn light(&self) -> Self {
+        Self {
+            d: Vec::new(),
+            ..self.clone()
+        }
+    }
 }
 
 #[derive(CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq, Debug)]
@@ -43,18 +105,8 @@ pub struct CommitmentKey<E: Pairing, D: EvaluationDomain<E::ScalarField>> {
     /// for i in 0..deg
     pub lagranges: Vec<E::G1Affine>,
 
-    /// generator of G2
-    pub g2: E::G2Affine,
-
-    /// r = g2^{\alpha}, needed for verification
-    pub r: E::G2Affine,
-
-    /// precomputed denominators in the exponent
-    /// all prepared for pairing
-    /// namely, d[i] = g2^{alpha - zi},
-    /// where zi is the ith evaluation point
-    /// i should range from 0 to deg
-    pub d: Vec<E::G2Affine>,
+    /// the part of the key that is needed for verification
+    pub vk: VerifierKey<E, D>,
 
     /// y = DFT_{2d}(hat_s) for
     /// hat_s = [u[d-1],...,u[0], d+2 neutral elements]
@@ -65,7 +117,7 @@ pub struct CommitmentKey<E: Pairing, D: EvaluationDomain<E::ScalarField>> {
     pub hat_y: Vec<E::G1Affine>,
 }
 
-#[derive(CanonicalSerialize)]
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct Opening<E: Pairing> {
     /// evaluation of the randomizer polynomial
     pub hat_y: E::ScalarField,
@@ -74,7 +126,34 @@ pub struct Opening<E: Pairing> {
     pub v: E::G1Affine,
 }
 
-#[derive(CanonicalSerialize)]
+/// opening that aggregates openings at several positions
+/// and for several commitments. It contains one group
+/// element per distinct position, and a single field element
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct CrossOpening<E: Pairing> {
+    /// the distinct positions, in increasing order
+    pub positions: Vec<u32>,
+
+    /// vs[t] aggregates the witnesses for position positions[t]
+    pub vs: Vec<E::G1Affine>,
+
+    /// aggregated evaluation of the randomizer polynomials
+    pub hat_y: E::ScalarField,
+}
+
+/// opening that aggregates openings at the same position
+/// incrementally. It contains one aggregated opening per
+/// aggregate that has been merged into it
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct MergeableOpening<E: Pairing> {
+    /// sizes[t] is the number of openings aggregated into parts[t]
+    pub sizes: Vec<u32>,
+
+    /// parts[t] aggregates the next sizes[t] openings as aggregate does
+    pub parts: Vec<Opening<E>>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
 pub struct Commitment<E: Pairing> {
     /// actual kzg commitment, g1^{f(alpha)}
     pub com_kzg: E::G1Affine,
@@ -86,7 +165,13 @@ pub struct Commitment<E: Pairing> {
     pub tau0: Opening<E>,
 }
 
+/// secret state of a commitment, which is zeroized on drop
+#[derive(Zeroize, ZeroizeOnDrop)]
 pub struct State<E: Pairing> {
+    /// keeps the pages of evals locked, if enabled
+    #[zeroize(skip)]
+    pub lock: MemoryLock,
+
     /// stores both the evaluations of the polynomial
     /// and the evaluations of the masking polynomial
     /// polynomial: 0..deg, masking: deg..2*deg
@@ -94,5 +179,48 @@ pub struct State<E: Pairing> {
 
     /// optionally stores precomputed KZG openings
     /// Note: this is only the group element part
+    #[zeroize(skip)]
     pub precomputed_v: Option<Vec<E::G1>>,
 }
+
+// the state is serialized without the lock, which is
+// acquired again for the evaluations when deserializing
+impl<E: Pairing> CanonicalSerialize for State<E> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.evals.serialize_with_mode(&mut writer, compress)?;
+        self.precomputed_v
+            .serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.evals.serialized_size(compress) + self.precomputed_v.serialized_size(compress)
+    }
+}
+
+impl<E: Pairing> Valid for State<E> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.evals.check()?;
+        self.precomputed_v.check()
+    }
+}
+
+impl<E: Pairing> CanonicalDeserialize for State<E> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let evals = Vec::<E::ScalarField>::deserialize_with_mode(&mut reader, compress, validate)?;
+        let precomputed_v =
+            Option::<Vec<E::G1>>::deserialize_with_mode(&mut reader, compress, validate)?;
+        Ok(Self {
+            lock: MemoryLock::new(&evals),
+            evals,
+            precomputed_v,
+        })
+    }
+}
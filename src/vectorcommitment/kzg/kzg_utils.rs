@@ -2,82 +2,153 @@ use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
 use ark_ff::{batch_inversion, Field};
 use ark_poly::{EvaluationDomain, Polynomial};
 use ark_serialize::CanonicalSerialize;
+use ark_std::iter::zip;
+use ark_std::ops::Mul;
+use ark_std::{vec, vec::Vec};
 use ark_std::{One, Zero};
-use sha2::{Digest, Sha256};
-use std::ops::Mul;
 
 use super::Commitment;
 use super::CommitmentKey;
 use super::Opening;
+use super::VerifierKey;
+use crate::transcript::{Transcript, TranscriptHash};
 
 // This module contains helper functions for the Simulation Extractable KZG Vector commitment
 
 /// Computes the challenge for a commitment
 #[inline]
-pub fn get_z0<E: Pairing>(com_kzg: &E::G1Affine) -> E::ScalarField {
+pub fn get_z0<E: Pairing, H: TranscriptHash>(com_kzg: &E::G1Affine) -> E::ScalarField {
     // z0 = Hash(com_kzg)
-    let mut com_ser = Vec::new();
-    com_kzg
-        .serialize_uncompressed(&mut com_ser)
-        .expect("Failed to serialize commitment in get_z0");
-    let mut res = None;
-    let mut i = 0u64;
-    // Efficiency could be improved here by copying midstate
-    while res.is_none() {
-        let mut hasher = Sha256::new_with_prefix("KZG-SIM-EXT//".as_bytes());
-        i += 1;
-        hasher.update(&com_ser);
-        hasher.update(i.to_le_bytes());
-        let digest = hasher.finalize();
-        res = E::ScalarField::from_random_bytes(&digest);
-    }
-    res.unwrap()
+    let mut transcript = Transcript::<H>::new(b"KZG-SIM-EXT//");
+    transcript.append_serializable(b"com", com_kzg);
+    transcript.challenge_field(b"z0")
 }
 
 /// Computes the aggregation coefficient
 /// for a bunch of commitments and expected values
 #[inline]
-pub fn get_chi<E: Pairing>(
+pub fn get_chi<E: Pairing, H: TranscriptHash>(
     i: u32,
-    mis: &Vec<E::ScalarField>,
-    coms: &Vec<&Commitment<E>>,
+    mis: &[E::ScalarField],
+    coms: &[&Commitment<E>],
 ) -> E::ScalarField {
-    // chi = Hash(i,(mi[j],com[j])_j)
-    let mut mis_ser = Vec::new();
     let mut coms_ser = Vec::new();
-    for j in 0..mis.len() {
-        let mut mi_ser = Vec::new();
+    for com in coms.iter().take(mis.len()) {
         let mut com_ser = Vec::new();
-        mis[j]
-            .serialize_uncompressed(&mut mi_ser)
-            .expect("Failed to serialize mi in get_chi");
-        coms[j]
-            .serialize_uncompressed(&mut com_ser)
+        com.serialize_uncompressed(&mut com_ser)
             .expect("Failed to serialize com in get_chi");
-        mis_ser.push(mi_ser);
         coms_ser.push(com_ser);
     }
-    let mut cnt = 0u64;
-    let mut res = None;
-    while res.is_none() {
-        let mut hasher = Sha256::new_with_prefix("KZG-AGG//".as_bytes());
-        cnt += 1;
-        hasher.update(cnt.to_le_bytes());
-        hasher.update(i.to_be_bytes());
-        for j in 0..mis.len() {
-            hasher.update(&mis_ser[j]);
-            hasher.update(&coms_ser[j]);
-        }
-        let digest = hasher.finalize();
-        res = E::ScalarField::from_random_bytes(&digest);
+    let coms_ser: Vec<_> = coms_ser.iter().map(|c| c.as_slice()).collect();
+    get_chi_serialized::<E, H>(i, mis, &coms_ser)
+}
+
+/// Same as get_chi, but takes the uncompressed
+/// serializations of the commitments as input
+#[inline]
+pub fn get_chi_serialized<E: Pairing, H: TranscriptHash>(
+    i: u32,
+    mis: &[E::ScalarField],
+    coms_ser: &[&[u8]],
+) -> E::ScalarField {
+    // chi = Hash(i,(mi[j],com[j])_j)
+    let mut transcript = Transcript::<H>::new(b"KZG-AGG//");
+    transcript.append_u32(b"i", i);
+    for j in 0..mis.len() {
+        transcript.append_serializable(b"mi", &mis[j]);
+        transcript.append_message(b"com", coms_ser[j]);
+    }
+    transcript.challenge_field(b"chi")
+}
+
+/// Computes the coefficient that combines aggregated
+/// openings for different positions into one opening
+#[inline]
+pub fn get_rho<E: Pairing, H: TranscriptHash>(
+    positions: &[u32],
+    chis: &[E::ScalarField],
+    vs: &[E::G1Affine],
+) -> E::ScalarField {
+    // rho = Hash((positions[t],chis[t],vs[t])_t)
+    let mut transcript = Transcript::<H>::new(b"KZG-CROSS-AGG//");
+    for t in 0..positions.len() {
+        transcript.append_u32(b"position", positions[t]);
+        transcript.append_serializable(b"chi", &chis[t]);
+        transcript.append_serializable(b"v", &vs[t]);
     }
-    res.unwrap()
+    transcript.challenge_field(b"rho")
+}
+
+/// Computes the coefficient that combines merged
+/// aggregates for the same position into one opening
+#[inline]
+pub fn get_merge_rho<E: Pairing, H: TranscriptHash>(
+    i: u32,
+    sizes: &[u32],
+    chis: &[E::ScalarField],
+    parts: &[Opening<E>],
+) -> E::ScalarField {
+    // rho = Hash(i,(sizes[t],chis[t],vs[t],hat_ys[t])_t)
+    let mut transcript = Transcript::<H>::new(b"KZG-MERGE-AGG//");
+    transcript.append_u32(b"i", i);
+    for t in 0..parts.len() {
+        transcript.append_u32(b"size", sizes[t]);
+        transcript.append_serializable(b"chi", &chis[t]);
+        transcript.append_serializable(b"v", &parts[t].v);
+        transcript.append_serializable(b"hat_y", &parts[t].hat_y);
+    }
+    transcript.challenge_field(b"rho")
+}
+
+/// Computes the vector [1, x, x^2, ..., x^{le-1}]
+#[inline]
+pub fn powers<F: Field>(x: F, le: usize) -> Vec<F> {
+    let mut res = Vec::with_capacity(le);
+    if le < 1 {
+        return res;
+    }
+    res.push(F::one());
+    for j in 1..le {
+        res.push(res[j - 1] * x);
+    }
+    res
+}
+
+/// Combines commitments coms[j] with expected values mis[j]
+/// using aggregation coefficients cs[j], as done by the verifier
+/// of aggregated openings. Outputs
+/// com = prod_{j=1}^L comj^{cj} and
+/// mi = sum_{j=1}^L mij * cj
+#[inline]
+pub fn combine_commitments<E: Pairing>(
+    cs: &[E::ScalarField],
+    mis: &[E::ScalarField],
+    coms: &[&Commitment<E>],
+) -> (E::G1, E::ScalarField) {
+    let com_kzgs: Vec<_> = coms.iter().map(|com| com.com_kzg).collect();
+    let com = <E::G1 as VariableBaseMSM>::msm(&com_kzgs, cs).unwrap();
+    let mi: E::ScalarField = zip(mis, cs).map(|(m, c)| *m * c).sum();
+    (com, mi)
+}
+
+/// Checks that a group element is in the prime-order subgroup and
+/// is not the identity. Elements that have been deserialized without
+/// validation may not be on the curve or of low order
+#[inline]
+pub fn is_valid_element<G: AffineRepr>(p: &G) -> bool {
+    !p.is_zero() && p.check().is_ok()
+}
+
+/// Checks all group elements of a commitment
+#[inline]
+pub fn is_valid_commitment<E: Pairing>(com: &Commitment<E>) -> bool {
+    is_valid_element(&com.com_kzg) && is_valid_element(&com.tau0.v)
 }
 
 /// Standard KZG verification. Verifies that f(z) = y
 #[inline]
 pub fn plain_kzg_verify<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
-    ck: &CommitmentKey<E, D>,
+    vk: &VerifierKey<E, D>,
     com_kzg: &E::G1Affine,
     z: E::ScalarField,
     y: E::ScalarField,
@@ -85,16 +156,16 @@ pub fn plain_kzg_verify<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
 ) -> bool {
     // check e(com*g1^{-y}*h^{-hat_y},g2) == e(v,r*g2^{-z})
     let mut lhs_left = com_kzg.into_group();
-    lhs_left -= ck.u[0].mul(y);
-    lhs_left -= ck.hat_u[0].mul(tau.hat_y);
-    let rhs_right = ck.r.into_group() - ck.g2.mul(z);
+    lhs_left -= vk.g1.mul(y);
+    lhs_left -= vk.h.mul(tau.hat_y);
+    let rhs_right = vk.r.into_group() - vk.g2.mul(z);
     // Naive Implementation:
-    //  let lhs = E::pairing(lhs_left, ck.g2);
+    //  let lhs = E::pairing(lhs_left, vk.g2);
     //  let rhs = E::pairing(tau.v, rhs_right);
     //  lhs == rhs
     // We can do it slightly faster:
     let left = vec![E::G1Prepared::from(-lhs_left), E::G1Prepared::from(tau.v)];
-    let right = vec![E::G2Prepared::from(ck.g2), E::G2Prepared::from(rhs_right)];
+    let right = vec![E::G2Prepared::from(vk.g2), E::G2Prepared::from(rhs_right)];
     let q = E::multi_pairing(left, right);
     q.is_zero()
 }
@@ -103,7 +174,7 @@ pub fn plain_kzg_verify<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
 /// but assumes that z = domain.element(i)
 #[inline]
 pub fn plain_kzg_verify_inside<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
-    ck: &CommitmentKey<E, D>,
+    vk: &VerifierKey<E, D>,
     i: usize,
     com_kzg: &E::G1Affine,
     y: E::ScalarField,
@@ -111,15 +182,15 @@ pub fn plain_kzg_verify_inside<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
 ) -> bool {
     // check e(com*g1^{-y}*h^{-hat_y0},g2) == e(v0,r*g2^{-z0})
     let mut lhs_left = com_kzg.into_group();
-    lhs_left -= ck.u[0].mul(y);
-    lhs_left -= ck.hat_u[0].mul(tau.hat_y);
+    lhs_left -= vk.g1.mul(y);
+    lhs_left -= vk.h.mul(tau.hat_y);
     // Naive Implementation:
-    //  let lhs = E::pairing(lhs_left, ck.g2);
-    //  let rhs = E::pairing(tau.v, ck.d[i]);
+    //  let lhs = E::pairing(lhs_left, vk.g2);
+    //  let rhs = E::pairing(tau.v, d[i]);
     //  lhs == rhs
     // We can do it slightly faster:
     let left = vec![E::G1Prepared::from(-lhs_left), E::G1Prepared::from(tau.v)];
-    let right = vec![E::G2Prepared::from(ck.g2), E::G2Prepared::from(ck.d[i])];
+    let right = vec![E::G2Prepared::from(vk.g2), E::G2Prepared::from(vk.d(i))];
     let q = E::multi_pairing(left, right);
     q.is_zero()
 }
@@ -180,9 +251,9 @@ pub fn witness_evals_inside<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
     let xi = domain.element(i);
     let mut nums = Vec::new();
     let mut denoms = Vec::new();
-    for j in 0..domain.size() {
+    for (j, eval) in evals.iter().enumerate().take(domain.size()) {
         // f(x_j) - f(x_i)
-        nums.push(evals[j] - fxi);
+        nums.push(*eval - fxi);
         // x_j-x_i
         denoms.push(domain.element(j) - xi);
     }
@@ -190,8 +261,8 @@ pub fn witness_evals_inside<E: Pairing, D: EvaluationDomain<E::ScalarField>>(
     // to make batch inversion possible
     denoms[i] = E::ScalarField::one();
     batch_inversion(&mut denoms);
-    for j in 0..domain.size() {
-        witn_evals.push(nums[j] * denoms[j]);
+    for (num, denom) in nums.iter().zip(&denoms) {
+        witn_evals.push(*num * denom);
     }
     // now witn_evals is correctly computed for all j!=i.
     // whats left is to compute the ith evaluation properly
@@ -281,6 +352,7 @@ mod tests {
     use ark_poly::{EvaluationDomain, Evaluations, Polynomial};
     use ark_std::One;
     use ark_std::UniformRand;
+    use sha2::Sha256;
 
     use super::{
         evaluate_outside, find_in_domain, get_z0, inv_diffs, witness_evals_inside,
@@ -356,9 +428,10 @@ mod tests {
             // compute its inverse differences
             let inv_diffs = inv_diffs::<Bls12_381, D>(&domain, z);
             // check that each element is really the inverse
-            for i in 0..domain.size() {
+            assert_eq!(inv_diffs.len(), domain.size());
+            for (i, inv_diff) in inv_diffs.iter().enumerate() {
                 let diff = domain.element(i) - z;
-                let prod = diff * inv_diffs[i];
+                let prod = diff * inv_diff;
                 assert_eq!(prod, F::one());
             }
         }
@@ -448,10 +521,10 @@ mod tests {
             let com1 = <Bls12_381 as Pairing>::G1::rand(&mut rng).into_affine();
             let com2 = <Bls12_381 as Pairing>::G1::rand(&mut rng).into_affine();
             // run the function we want to test
-            let z10 = get_z0::<Bls12_381>(&com1);
-            let z11 = get_z0::<Bls12_381>(&com1);
-            let z20 = get_z0::<Bls12_381>(&com2);
-            let z21 = get_z0::<Bls12_381>(&com2);
+            let z10 = get_z0::<Bls12_381, Sha256>(&com1);
+            let z11 = get_z0::<Bls12_381, Sha256>(&com1);
+            let z20 = get_z0::<Bls12_381, Sha256>(&com2);
+            let z21 = get_z0::<Bls12_381, Sha256>(&com2);
             assert_eq!(z10, z11);
             assert_eq!(z20, z21);
             assert_ne!(z10, z20);
@@ -0,0 +1,361 @@
+use ark_ff::Field;
+use ark_std::collections::{BTreeMap, BTreeSet};
+use ark_std::{vec, vec::Vec};
+use rand::Rng;
+
+/// this module contains all types associated with
+/// the vector commitment based on salted Merkle trees
+pub mod merkle_types;
+
+/// this module contains several functions
+/// we use often for our vector commitment
+pub(crate) mod merkle_utils;
+
+pub use self::merkle_types::Commitment;
+pub use self::merkle_types::CommitmentKey;
+pub use self::merkle_types::MultiProof;
+pub use self::merkle_types::Opening;
+pub use self::merkle_types::State;
+pub use self::merkle_types::VcMerkle;
+
+use self::merkle_utils::build_tree;
+use self::merkle_utils::compute_root;
+use self::merkle_utils::hash_leaf;
+use self::merkle_utils::multiproof_nodes;
+
+use super::CrossAggregation;
+use super::VectorCommitmentScheme;
+use crate::memlock::MemoryLock;
+
+/// groups the indices j by commitment coms[j]
+/// groups are ordered by first occurrence
+fn group_by_commitment(coms: &[&Commitment]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of = BTreeMap::new();
+    for (j, com) in coms.iter().enumerate() {
+        let g = *group_of.entry(com.root).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(j);
+    }
+    groups
+}
+
+impl<F: Field> VectorCommitmentScheme<F> for VcMerkle<F> {
+    type CommitmentKey = CommitmentKey;
+    type VerifierKey = CommitmentKey;
+    type Commitment = Commitment;
+    type Opening = Opening;
+    type State = State;
+
+    fn setup<R: Rng>(_rng: &mut R, message_length: usize) -> Option<Self::CommitmentKey> {
+        if message_length < 1 {
+            return None;
+        }
+        // there is nothing to set up except for the shape of the tree
+        let depth = message_length.checked_next_power_of_two()?.trailing_zeros() as usize;
+        Some(CommitmentKey {
+            message_length,
+            depth,
+        })
+    }
+
+    fn commit<R: Rng>(
+        rng: &mut R,
+        ck: &Self::CommitmentKey,
+        m: &[F],
+    ) -> (Self::Commitment, Self::State) {
+        // every leaf is a salted hash of the value,
+        // and leaves without value are random
+        let n = 1 << ck.depth;
+        let mut salts = Vec::with_capacity(n);
+        let mut leaves = Vec::with_capacity(n);
+        for j in 0..n {
+            let salt: [u8; 32] = rng.gen();
+            if j < m.len() {
+                leaves.push(hash_leaf(&salt, &m[j]));
+            } else {
+                leaves.push(rng.gen());
+            }
+            salts.push(salt);
+        }
+        let levels = build_tree(leaves);
+        let root = levels[levels.len() - 1][0];
+        let state = State {
+            lock: MemoryLock::new(&salts),
+            salts,
+            levels,
+        };
+        (Commitment { root }, state)
+    }
+
+    // the commitment key is already minimal
+    fn verifier_key(ck: &Self::CommitmentKey) -> &Self::VerifierKey {
+        ck
+    }
+
+    fn light_verifier_key(ck: &Self::CommitmentKey) -> Self::VerifierKey {
+        ck.clone()
+    }
+
+    fn verify_commitment(_vk: &Self::VerifierKey, _com: &Self::Commitment) -> bool {
+        // every digest is a valid root
+        true
+    }
+
+    fn open(ck: &Self::CommitmentKey, st: &Self::State, i: u32) -> Option<Self::Opening> {
+        if i as usize >= ck.message_length {
+            return None;
+        }
+
+        // the opening is the salt and the authentication path
+        let positions = BTreeSet::from([i as usize]);
+        let nodes = multiproof_nodes(ck.depth, &positions, |level, idx| {
+            st.levels[level].get(idx).copied()
+        })?;
+        let salts = vec![*st.salts.get(i as usize)?];
+        Some(Opening {
+            proofs: vec![MultiProof { salts, nodes }],
+        })
+    }
+
+    fn aggregate(
+        ck: &Self::CommitmentKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::Opening> {
+        // paths of different trees do not share any nodes,
+        // so this is the same as aggregating across positions
+        let is = vec![i; mis.len()];
+        Self::aggregate_cross(ck, &is, mis, coms, openings)
+    }
+
+    fn verify(
+        vk: &Self::VerifierKey,
+        i: u32,
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        opening: &Self::Opening,
+    ) -> bool {
+        let is = vec![i; mis.len()];
+        Self::verify_cross(vk, &is, mis, coms, opening)
+    }
+}
+
+impl<F: Field> CrossAggregation<F> for VcMerkle<F> {
+    type CrossOpening = Opening;
+
+    fn aggregate_cross(
+        ck: &Self::CommitmentKey,
+        is: &[u32],
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        openings: &[&Self::Opening],
+    ) -> Option<Self::CrossOpening> {
+        if is.is_empty() || is.len() != mis.len() {
+            return None;
+        }
+        if is.len() != coms.len() || is.len() != openings.len() {
+            return None;
+        }
+
+        // for each tree, we collect all nodes we know from the
+        // individual openings, and build a single multiproof
+        let mut proofs = Vec::new();
+        for js in group_by_commitment(coms) {
+            let mut salts = BTreeMap::new();
+            let mut known = BTreeMap::new();
+            for j in js {
+                let idx = is[j] as usize;
+                if idx >= ck.message_length || openings[j].proofs.len() != 1 {
+                    return None;
+                }
+                let proof = &openings[j].proofs[0];
+                if proof.salts.len() != 1 || proof.nodes.len() != ck.depth {
+                    return None;
+                }
+                if salts.insert(idx, proof.salts[0]).is_some() {
+                    // the same position is opened twice
+                    return None;
+                }
+                for (level, node) in proof.nodes.iter().enumerate() {
+                    known.insert((level, (idx >> level) ^ 1), *node);
+                }
+            }
+            let positions = salts.keys().copied().collect();
+            let nodes = multiproof_nodes(ck.depth, &positions, |level, idx| {
+                known.get(&(level, idx)).copied()
+            })?;
+            let salts = salts.into_values().collect();
+            proofs.push(MultiProof { salts, nodes });
+        }
+        Some(Opening { proofs })
+    }
+
+    fn verify_cross(
+        vk: &Self::VerifierKey,
+        is: &[u32],
+        mis: &[F],
+        coms: &[&Self::Commitment],
+        opening: &Self::CrossOpening,
+    ) -> bool {
+        if is.is_empty() || is.len() != mis.len() || is.len() != coms.len() {
+            return false;
+        }
+        let groups = group_by_commitment(coms);
+        if groups.len() != opening.proofs.len() {
+            return false;
+        }
+
+        // recompute the root of each tree from its opened leaves
+        for (js, proof) in groups.iter().zip(&opening.proofs) {
+            let mut values = BTreeMap::new();
+            for j in js {
+                let idx = is[*j] as usize;
+                if idx >= vk.message_length || values.insert(idx, mis[*j]).is_some() {
+                    return false;
+                }
+            }
+            if values.len() != proof.salts.len() {
+                return false;
+            }
+            let leaves = values
+                .iter()
+                .zip(&proof.salts)
+                .map(|((idx, m), salt)| (*idx, hash_leaf(salt, m)))
+                .collect();
+            let root = compute_root(vk.depth, &leaves, &proof.nodes);
+            if root != Some(coms[js[0]].root) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bls12_381::Fr;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use ark_std::One;
+
+    use super::{Commitment, Opening, VcMerkle};
+    use crate::vectorcommitment::{
+        _vc_test_agg_opening, _vc_test_batch_verify, _vc_test_com_ver, _vc_test_cross_agg_opening,
+        _vc_test_malformed_inputs, _vc_test_opening, _vc_test_setup, CrossAggregation,
+        VectorCommitmentScheme,
+    };
+
+    type F = Fr;
+    type VC = VcMerkle<F>;
+
+    #[test]
+    fn merkle_vc_test_setup() {
+        _vc_test_setup::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_com_ver() {
+        _vc_test_com_ver::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_opening() {
+        _vc_test_opening::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_malformed_inputs() {
+        _vc_test_malformed_inputs::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_batch_verify() {
+        _vc_test_batch_verify::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_agg_opening() {
+        _vc_test_agg_opening::<F, VC>();
+    }
+
+    #[test]
+    fn merkle_vc_test_cross_agg_opening() {
+        _vc_test_cross_agg_opening::<F, VC>();
+    }
+
+    /// test that multiproofs share nodes and
+    /// that wrong values or positions are rejected
+    #[test]
+    fn merkle_vc_test_multiproof() {
+        let mut rng = ark_std::rand::thread_rng();
+        let message_length = 16;
+        let ck = VC::setup(&mut rng, message_length).unwrap();
+        assert_eq!(ck.depth, 4);
+        let m: Vec<F> = (0..message_length as u64).map(F::from).collect();
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+
+        // opening positions 4 and 5 only needs the nodes above them
+        let is = [5, 4];
+        let mis = [m[5], m[4]];
+        let ops: Vec<_> = is.iter().map(|i| VC::open(&ck, &st, *i).unwrap()).collect();
+        let ops_r: Vec<_> = ops.iter().collect();
+        let op = VC::aggregate_cross(&ck, &is, &mis, &[&com, &com], &ops_r).unwrap();
+        assert_eq!(op.proofs.len(), 1);
+        assert_eq!(op.proofs[0].nodes.len(), 3);
+        assert!(op.compressed_size() < ops[0].compressed_size() + ops[1].compressed_size());
+        assert!(VC::verify_cross(&ck, &is, &mis, &[&com, &com], &op));
+        assert!(!VC::verify_cross(
+            &ck,
+            &is,
+            &[m[4], m[5]],
+            &[&com, &com],
+            &op
+        ));
+        assert!(!VC::verify_cross(&ck, &[5, 6], &mis, &[&com, &com], &op));
+
+        // a single opening for a wrong value or position is rejected
+        assert!(VC::verify(
+            &ck,
+            3,
+            &[m[3]],
+            &[&com],
+            &VC::open(&ck, &st, 3).unwrap()
+        ));
+        assert!(!VC::verify(&ck, 3, &[m[3] + F::one()], &[&com], &ops[0]));
+        assert!(!VC::verify(&ck, 4, &[m[5]], &[&com], &ops[0]));
+
+        // opening the same position twice is not allowed
+        let dup = [&ops[0], &ops[0]];
+        assert!(VC::aggregate_cross(&ck, &[5, 5], &[m[5], m[5]], &[&com, &com], &dup).is_none());
+    }
+
+    /// test that commitments and openings are read back, and that
+    /// truncated inputs are rejected without panicking
+    #[test]
+    fn merkle_vc_test_serialization() {
+        let mut rng = ark_std::rand::thread_rng();
+        let ck = VC::setup(&mut rng, 5).unwrap();
+        let m: Vec<F> = (0..5u64).map(F::from).collect();
+        let (com, st) = VC::commit(&mut rng, &ck, &m);
+        let op = VC::open(&ck, &st, 2).unwrap();
+        let mut com_bytes = Vec::new();
+        com.serialize_compressed(&mut com_bytes).unwrap();
+        let mut op_bytes = Vec::new();
+        op.serialize_compressed(&mut op_bytes).unwrap();
+        assert_eq!(
+            Commitment::deserialize_compressed(&com_bytes[..]).unwrap(),
+            com
+        );
+        assert_eq!(Opening::deserialize_compressed(&op_bytes[..]).unwrap(), op);
+        for len in 0..com_bytes.len() {
+            assert!(Commitment::deserialize_compressed(&com_bytes[..len]).is_err());
+        }
+        for len in 0..op_bytes.len() {
+            assert!(Opening::deserialize_compressed(&op_bytes[..len]).is_err());
+        }
+    }
+}
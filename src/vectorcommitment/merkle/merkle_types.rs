@@ -0,0 +1,112 @@
+use ark_ff::Field;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+};
+use ark_std::marker::PhantomData;
+use ark_std::vec::Vec;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::merkle_utils::{read_digest, read_digests};
+use crate::memlock::MemoryLock;
+
+// This module contains types for the vector commitment based on salted Merkle trees
+
+/// hash value of a node in the tree
+pub type Digest = [u8; 32];
+
+/// Vector commitment based on Merkle trees over SHA-256.
+/// Every leaf is salted with fresh randomness, which makes it
+/// hiding. It only relies on hash functions, and therefore
+/// is a plausibly post-quantum secure alternative
+pub struct VcMerkle<F: Field> {
+    _f: PhantomData<F>,
+}
+
+#[derive(CanonicalSerialize, CanonicalDeserialize, PartialEq, Eq, Debug, Clone)]
+pub struct CommitmentKey {
+    /// length of messages to which we commit
+    pub message_length: usize,
+
+    /// depth of the tree, such that it has
+    /// 2^depth >= message_length leaves
+    pub depth: usize,
+}
+
+/// proof that opens several leaves of the same tree.
+/// It contains every node that is needed to recompute the root
+/// and can not be computed from the opened leaves, only once
+#[derive(CanonicalSerialize, Clone, PartialEq, Eq, Debug)]
+pub struct MultiProof {
+    /// salts of the opened leaves, by increasing position
+    pub salts: Vec<Digest>,
+
+    /// the nodes needed to recompute the root, ordered by
+    /// level (starting at the leaves) and position in the level
+    pub nodes: Vec<Digest>,
+}
+
+/// opening for one or several commitments
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Opening {
+    /// one proof per (distinct) commitment
+    pub proofs: Vec<MultiProof>,
+}
+
+#[derive(CanonicalSerialize, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Commitment {
+    /// root of the tree
+    pub root: Digest,
+}
+
+// digests are deserialized by hand, as ark-serialize
+// panics when arrays are read from a short input
+
+impl Valid for MultiProof {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for MultiProof {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        _validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let salts = read_digests(&mut reader, compress)?;
+        let nodes = read_digests(&mut reader, compress)?;
+        Ok(MultiProof { salts, nodes })
+    }
+}
+
+impl Valid for Commitment {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalDeserialize for Commitment {
+    fn deserialize_with_mode<R: Read>(
+        reader: R,
+        _compress: Compress,
+        _validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let root = read_digest(reader)?;
+        Ok(Commitment { root })
+    }
+}
+
+/// secret state of a commitment, which is zeroized on drop
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct State {
+    /// keeps the pages of salts locked, if enabled
+    #[zeroize(skip)]
+    pub lock: MemoryLock,
+
+    /// salts of all leaves
+    pub salts: Vec<Digest>,
+
+    /// all nodes of the tree, level by level,
+    /// starting with the leaves and ending with the root
+    pub levels: Vec<Vec<Digest>>,
+}
@@ -0,0 +1,130 @@
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, Compress, Read, SerializationError, Validate};
+use ark_std::collections::{BTreeMap, BTreeSet};
+use ark_std::{vec, vec::Vec};
+use sha2::{Digest as _, Sha256};
+
+use super::merkle_types::Digest;
+
+// This module contains helper functions for the vector commitment based on salted Merkle trees
+
+/// Reads a digest as serialized by CanonicalSerialize.
+/// In contrast to the implementation for arrays in ark-serialize,
+/// this returns an error instead of panicking if the input is too short
+#[inline]
+pub fn read_digest<R: Read>(mut reader: R) -> Result<Digest, SerializationError> {
+    let mut digest = [0u8; 32];
+    reader.read_exact(&mut digest)?;
+    Ok(digest)
+}
+
+/// Reads a vector of digests as serialized by CanonicalSerialize,
+/// i.e., its length followed by the digests
+pub fn read_digests<R: Read>(
+    mut reader: R,
+    compress: Compress,
+) -> Result<Vec<Digest>, SerializationError> {
+    let len = u64::deserialize_with_mode(&mut reader, compress, Validate::No)?;
+    let mut digests = Vec::new();
+    for _ in 0..len {
+        digests.push(read_digest(&mut reader)?);
+    }
+    Ok(digests)
+}
+
+/// Computes the leaf for value m with the given salt
+#[inline]
+pub fn hash_leaf<F: Field>(salt: &Digest, m: &F) -> Digest {
+    let mut m_ser = Vec::new();
+    m.serialize_uncompressed(&mut m_ser)
+        .expect("Failed to serialize m in hash_leaf");
+    let mut hasher = Sha256::new_with_prefix("MERKLE-LEAF//".as_bytes());
+    hasher.update(salt);
+    hasher.update(&m_ser);
+    hasher.finalize().into()
+}
+
+/// Computes an inner node from its children
+#[inline]
+pub fn hash_node(left: &Digest, right: &Digest) -> Digest {
+    let mut hasher = Sha256::new_with_prefix("MERKLE-NODE//".as_bytes());
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Computes all levels of the tree with the given leaves,
+/// the number of which should be a power of two
+pub fn build_tree(leaves: Vec<Digest>) -> Vec<Vec<Digest>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let level = &levels[levels.len() - 1];
+        let next = level
+            .chunks(2)
+            .map(|pair| hash_node(&pair[0], &pair[1]))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Computes the nodes that a multiproof for the given positions has to
+/// contain, in the order in which compute_root consumes them.
+/// Function node(level, index) should return these nodes
+pub fn multiproof_nodes<N: Fn(usize, usize) -> Option<Digest>>(
+    depth: usize,
+    positions: &BTreeSet<usize>,
+    node: N,
+) -> Option<Vec<Digest>> {
+    let mut nodes = Vec::new();
+    let mut known = positions.clone();
+    for level in 0..depth {
+        for idx in &known {
+            let sibling = idx ^ 1;
+            if !known.contains(&sibling) {
+                nodes.push(node(level, sibling)?);
+            }
+        }
+        known = known.iter().map(|idx| idx >> 1).collect();
+    }
+    Some(nodes)
+}
+
+/// Recomputes the root of a tree of the given depth from some leaves,
+/// given by their position, and the nodes of a multiproof.
+/// Returns None if the number of nodes does not fit
+pub fn compute_root(
+    depth: usize,
+    leaves: &BTreeMap<usize, Digest>,
+    nodes: &[Digest],
+) -> Option<Digest> {
+    if leaves.is_empty() || leaves.keys().any(|idx| idx >> depth != 0) {
+        return None;
+    }
+    let mut nodes = nodes.iter();
+    let mut known = leaves.clone();
+    for _ in 0..depth {
+        let mut parents = BTreeMap::new();
+        for (idx, value) in &known {
+            if parents.contains_key(&(idx >> 1)) {
+                // we already computed the parent from the left sibling
+                continue;
+            }
+            let sibling = match known.get(&(idx ^ 1)) {
+                Some(sibling) => sibling,
+                None => nodes.next()?,
+            };
+            let parent = if idx & 1 == 0 {
+                hash_node(value, sibling)
+            } else {
+                hash_node(sibling, value)
+            };
+            parents.insert(idx >> 1, parent);
+        }
+        known = parents;
+    }
+    if nodes.next().is_some() {
+        return None;
+    }
+    known.remove(&0)
+}